@@ -31,4 +31,54 @@ pub enum StreamError {
     InvalidRate = 11,
     /// Operation requires an active stream, but the stream is currently paused.
     StreamPaused = 12,
+    /// Duration exceeds the admin-configured `max_duration`.
+    DurationTooLong = 13,
+    /// Claimable balance is below the caller-supplied minimum.
+    BelowMinimum = 14,
+    /// Stream is winding down gracefully and no longer accepts control changes.
+    StreamWindingDown = 15,
+    /// The stream's first-withdrawal delay has not yet elapsed.
+    TooSoon = 16,
+    /// The deposit would push the token's total locked past its exposure cap.
+    ExposureCapReached = 17,
+    /// A batch call listed more items than `MAX_BATCH_SIZE`.
+    BatchTooLarge = 18,
+    /// The contract's token balance cannot cover the requested payout.
+    InsufficientReserve = 19,
+    /// The stream's withdraw gate rejected (or failed to answer) the release.
+    GateRejected = 20,
+    /// The stream has been finalized and no longer accepts any mutation.
+    StreamFinalized = 21,
+    /// The stream must be cancelled or completed with nothing parked first.
+    StreamNotSettled = 22,
+    /// A milestone schedule is unordered or does not sum to the deposit.
+    InvalidMilestones = 23,
+    /// The circuit breaker has paused the protocol until the admin resets it.
+    ProtocolPaused = 24,
+    /// Strict mode: the deposit is smaller than the duration in seconds.
+    DepositTooSmallForDuration = 25,
+    /// Force-close attempted on a stream whose token is not marked defunct.
+    TokenNotDefunct = 26,
+    /// Tithe share exceeds `MAX_TITHE_BPS` or targets the recipient or contract.
+    InvalidTithe = 27,
+    /// The recipient is the stream contract itself or the sender, a
+    /// `withdraw_to` destination is the contract, or `transfer_recipient`
+    /// targets the current recipient, the sender or the contract.
+    InvalidRecipient = 28,
+    /// The cliff is longer than the stream's duration.
+    InvalidCliff = 29,
+    /// Cancellation payouts do not add up to the stream's unpaid balance.
+    AccountingError = 30,
+    /// The admin has paused the contract; creation, top-ups and withdrawals
+    /// are rejected until it is unpaused.
+    ContractPaused = 31,
+    /// Parallel batch inputs have different lengths.
+    BatchLengthMismatch = 32,
+    /// A token-transferring call was re-entered while another was in progress.
+    Reentrancy = 33,
+    /// Streams may still be in the legacy layout, untracked in the token
+    /// totals; run `migrate_legacy_streams` to completion first.
+    MigrationPending = 34,
+    /// `withdraw_to` named a destination outside the stream's allow-list.
+    DestinationNotAllowed = 35,
 }
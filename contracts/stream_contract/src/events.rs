@@ -1,4 +1,6 @@
-use soroban_sdk::{contracttype, Address};
+use soroban_sdk::{contracttype, Address, BytesN, Symbol, Vec};
+
+use crate::types::{CancellerRole, StreamAssetKind};
 
 /// Emitted when a new stream is created.
 ///
@@ -15,6 +17,14 @@ pub struct StreamCreatedEvent {
     /// Net deposited amount after protocol fee deduction.
     pub deposited_amount: i128,
     pub start_time: u64,
+    /// Entry point that created the stream (e.g. `direct`).
+    pub created_by: Symbol,
+    /// Nominal timestamp at which the whole deposit is vested.
+    pub end_time: u64,
+    /// Timestamp before which nothing is withdrawable (`start_time` if no cliff).
+    pub cliff_time: u64,
+    /// Integrator reference from `StreamOptions.memo`, if any.
+    pub memo: Option<Symbol>,
 }
 
 /// Emitted when a sender tops up an active stream.
@@ -29,6 +39,33 @@ pub struct StreamToppedUpEvent {
     pub amount: i128,
     /// Total deposited amount on the stream after this top-up.
     pub new_deposited_amount: i128,
+    /// Nominal end time after this top-up.
+    pub new_end_time: u64,
+}
+
+/// Emitted when a top-up is parked awaiting recipient acknowledgment.
+///
+/// Topic: `("topup_parked", stream_id)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TopUpParkedEvent {
+    pub stream_id: u64,
+    pub sender: Address,
+    /// Net amount parked by this top-up (after protocol fee).
+    pub amount: i128,
+    /// Total amount now parked on the stream.
+    pub pending_amount: i128,
+}
+
+/// Emitted when a sender reclaims a parked, unacknowledged top-up.
+///
+/// Topic: `("topup_reclaimed", stream_id)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TopUpReclaimedEvent {
+    pub stream_id: u64,
+    pub sender: Address,
+    pub amount: i128,
 }
 
 /// Emitted when the recipient withdraws accrued tokens.
@@ -41,11 +78,123 @@ pub struct TokensWithdrawnEvent {
     pub recipient: Address,
     pub amount: i128,
     pub timestamp: u64,
+    /// Reconciliation memo supplied via `withdraw_with_memo`, if any.
+    pub memo: Option<Symbol>,
+    /// Amount still withdrawable right after this withdrawal: 0 after a full
+    /// withdrawal, the unclaimed accrual after a partial `withdraw_amount`.
+    pub remaining: i128,
+}
+
+/// Emitted when a recipient's lifetime earnings in a token cross a multiple
+/// of the token's configured earnings threshold.
+///
+/// Topic: `("recipient_earnings", recipient)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecipientEarningsEvent {
+    pub recipient: Address,
+    pub token: Address,
+    /// Lifetime amount paid to the recipient in `token`, across all streams.
+    pub lifetime_withdrawn: i128,
+    /// Highest threshold multiple reached by this payout.
+    pub threshold_crossed: i128,
+}
+
+/// Emitted when the admin changes a token's earnings-event threshold.
+///
+/// Topic: `("earnings_threshold_updated",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EarningsThresholdUpdatedEvent {
+    pub admin: Address,
+    pub token: Address,
+    /// New step in token units; 0 disables earnings events.
+    pub threshold: i128,
+}
+
+/// Emitted when the admin updates a token's dust threshold.
+///
+/// Topic: `("dust_threshold_updated",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DustThresholdUpdatedEvent {
+    pub admin: Address,
+    pub token: Address,
+    /// Remainders strictly below this are swept; 0 disables sweeping.
+    pub threshold: i128,
+}
+
+/// Emitted when the admin sets or disables a token's circuit breaker.
+///
+/// Topic: `("circuit_breaker_updated",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CircuitBreakerUpdatedEvent {
+    pub admin: Address,
+    pub token: Address,
+    /// Maximum volume per window; 0 disables the breaker.
+    pub max_volume: i128,
+    pub window_secs: u64,
+}
+
+/// Emitted when withdrawal volume in a token exceeds its limit and the
+/// protocol is paused.
+///
+/// Topic: `("circuit_breaker_tripped", token)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CircuitBreakerTrippedEvent {
+    pub token: Address,
+    /// Volume withdrawn in the window, including the tripping withdrawal.
+    pub volume: i128,
+    pub max_volume: i128,
+    pub window_start: u64,
+}
+
+/// Emitted when the admin releases the global pause.
+///
+/// Topic: `("circuit_breaker_reset",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CircuitBreakerResetEvent {
+    pub admin: Address,
 }
 
-/// Emitted when a sender cancels an active stream.
+/// Emitted when a recipient hands a stream over to a new recipient.
 ///
-/// Topic: `("stream_cancelled", stream_id)`
+/// Topic: `("recipient_transferred", stream_id)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecipientTransferredEvent {
+    pub stream_id: u64,
+    pub old_recipient: Address,
+    pub new_recipient: Address,
+}
+
+/// Emitted when the admin pauses or unpauses the contract.
+///
+/// Topic: `("contract_pause_updated",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ContractPauseUpdatedEvent {
+    pub admin: Address,
+    pub paused: bool,
+}
+
+/// Emitted when the admin replaces the contract's code via `upgrade`.
+///
+/// Topic: `("contract_upgraded",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ContractUpgradedEvent {
+    pub admin: Address,
+    pub new_wasm_hash: BytesN<32>,
+}
+
+/// Emitted when an active stream is cancelled immediately.
+///
+/// Topic: `("stream_cancelled", stream_id, cancelled_by)`, so indexers can
+/// filter by initiator role.
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct StreamCancelledEvent {
@@ -56,6 +205,98 @@ pub struct StreamCancelledEvent {
     pub amount_withdrawn: i128,
     /// Unspent amount (deposited - withdrawn) returned to sender.
     pub refunded_amount: i128,
+    /// Ledger timestamp of the cancellation (the stream's final `last_update_time`).
+    pub cancelled_at: u64,
+    /// Which party or parties initiated the cancellation.
+    pub cancelled_by: CancellerRole,
+}
+
+/// Emitted when a multi-token bundle is created.
+///
+/// Topic: `("bundle_created", bundle_id)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BundleCreatedEvent {
+    pub bundle_id: u64,
+    pub sender: Address,
+    pub recipient: Address,
+    /// One stream per token, in the order the legs were given.
+    pub stream_ids: Vec<u64>,
+}
+
+/// Emitted when the admin marks a token contract as permanently broken.
+///
+/// Topic: `("token_defunct",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenDefunctEvent {
+    pub admin: Address,
+    pub token: Address,
+}
+
+/// Emitted when the admin sweeps stray tokens out of the contract.
+///
+/// Topic: `("token_recovered",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenRecoveredEvent {
+    pub admin: Address,
+    pub token: Address,
+    pub to: Address,
+    pub amount: i128,
+}
+
+/// Emitted when a stream in a defunct token is closed without transfers.
+///
+/// Topic: `("force_closed", stream_id)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ForceClosedEvent {
+    pub stream_id: u64,
+    pub token: Address,
+    /// Deposited (plus parked) amount never paid out, written off the books.
+    pub unpaid_amount: i128,
+    pub closed_at: u64,
+}
+
+/// Emitted when a settled stream is locked as an immutable record.
+///
+/// Topic: `("stream_finalized", stream_id)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamFinalizedEvent {
+    pub stream_id: u64,
+    /// Sender or recipient who finalized the stream.
+    pub finalized_by: Address,
+}
+
+/// Emitted when `close_stream` settles and closes a stream.
+///
+/// Topic: `("stream_closed", stream_id)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamClosedEvent {
+    pub stream_id: u64,
+    /// The party (sender or recipient) that closed the stream.
+    pub closed_by: Address,
+    /// Vested tokens paid to the recipient by the close.
+    pub recipient_payout: i128,
+    /// Unvested tokens returned to the sender (0 for a fully vested stream).
+    pub refunded_amount: i128,
+    pub closed_at: u64,
+}
+
+/// Emitted when a sender requests a graceful wind-down of a stream.
+///
+/// Topic: `("stream_winding_down", stream_id)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamWindingDownEvent {
+    pub stream_id: u64,
+    pub sender: Address,
+    pub recipient: Address,
+    /// Projected ledger timestamp at which the stream will be fully vested.
+    pub end_time: u64,
 }
 
 /// Emitted when a protocol fee is collected during create or top-up.
@@ -94,6 +335,75 @@ pub struct FeeConfigUpdatedEvent {
     pub new_fee_rate_bps: u32,
 }
 
+/// Emitted when the admin adds or removes a fee exemption.
+///
+/// Topic: `("fee_exemption_updated",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeExemptionUpdatedEvent {
+    pub admin: Address,
+    pub address: Address,
+    pub exempt: bool,
+}
+
+/// Emitted when the admin changes the maximum stream duration.
+///
+/// Topic: `("max_duration_updated",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MaxDurationUpdatedEvent {
+    pub admin: Address,
+    pub old_max_duration: u64,
+    /// New limit in seconds; 0 means unlimited.
+    pub new_max_duration: u64,
+}
+
+/// Emitted when the admin toggles the strict deposit check.
+///
+/// Topic: `("strict_deposit_check_updated",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StrictDepositCheckUpdatedEvent {
+    pub admin: Address,
+    pub strict: bool,
+}
+
+/// Emitted when the admin changes a token's exposure cap.
+///
+/// Topic: `("max_exposure_updated",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MaxExposureUpdatedEvent {
+    pub admin: Address,
+    pub token: Address,
+    pub old_max_exposure: i128,
+    /// New cap on total locked; 0 means unlimited.
+    pub new_max_exposure: i128,
+}
+
+/// Emitted when the admin sets or clears a token's fee-rate override.
+///
+/// Topic: `("token_fee_updated",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenFeeUpdatedEvent {
+    pub admin: Address,
+    pub token: Address,
+    /// New override in bps; `None` means the global rate applies again.
+    pub fee_bps: Option<u32>,
+}
+
+/// Emitted when the admin registers the asset kind of a token.
+///
+/// Topic: `("asset_kind_updated",)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AssetKindUpdatedEvent {
+    pub admin: Address,
+    pub token: Address,
+    pub kind: StreamAssetKind,
+}
+
 /// Emitted when the protocol admin is transferred to a new address.
 ///
 /// Topic: `("admin_transferred",)`
@@ -116,6 +426,9 @@ pub struct StreamPausedEvent {
     pub sender: Address,
     /// Ledger timestamp at which accrual was frozen.
     pub paused_at: u64,
+    /// When the pause lifts by itself (`pause_until`), or `None` until
+    /// `resume_stream`.
+    pub resume_at: Option<u64>,
 }
 
 /// Emitted when a sender resumes a paused stream.
@@ -126,7 +439,7 @@ pub struct StreamPausedEvent {
 pub struct StreamResumedEvent {
     pub stream_id: u64,
     pub sender: Address,
-    /// Recomputed ledger timestamp at which the stream will fully drain.
+    /// Projected ledger timestamp at which the stream becomes fully vested.
     pub new_end_time: u64,
 }
 
@@ -140,3 +453,16 @@ pub struct StreamCompletedEvent {
     pub recipient: Address,
     pub total_withdrawn: i128,
 }
+
+/// Emitted when a stream's accrual is checkpointed without moving tokens.
+///
+/// Topic: `("stream_settled", stream_id)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamSettledEvent {
+    pub stream_id: u64,
+    /// Cumulative vested amount after the checkpoint.
+    pub accrued_checkpoint: i128,
+    /// Ledger timestamp up to which accrual has been folded in.
+    pub settled_at: u64,
+}
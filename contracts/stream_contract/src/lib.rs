@@ -9,23 +9,87 @@ mod types;
 #[cfg(test)]
 mod test;
 
-use soroban_sdk::{contract, contractimpl, token, vec, Address, Env, InvokeError, Symbol};
+use soroban_sdk::{
+    contract, contractimpl, token, vec, Address, BytesN, Env, IntoVal, InvokeError, Map, Symbol,
+    Vec,
+};
 
 use errors::StreamError;
 use events::{
-    AdminTransferredEvent, FeeCollectedEvent, FeeConfigUpdatedEvent, InitializedEvent,
-    StreamCancelledEvent, StreamCompletedEvent, StreamCreatedEvent, StreamPausedEvent,
-    StreamResumedEvent, StreamToppedUpEvent, TokensWithdrawnEvent,
+    AdminTransferredEvent, AssetKindUpdatedEvent, BundleCreatedEvent, CircuitBreakerResetEvent,
+    CircuitBreakerTrippedEvent, CircuitBreakerUpdatedEvent, ContractPauseUpdatedEvent,
+    ContractUpgradedEvent, DustThresholdUpdatedEvent, EarningsThresholdUpdatedEvent,
+    FeeCollectedEvent, FeeConfigUpdatedEvent, FeeExemptionUpdatedEvent, ForceClosedEvent,
+    InitializedEvent, MaxDurationUpdatedEvent, MaxExposureUpdatedEvent, RecipientEarningsEvent,
+    RecipientTransferredEvent, StreamCancelledEvent, StreamClosedEvent, StreamCompletedEvent,
+    StreamCreatedEvent, StreamFinalizedEvent, StreamPausedEvent, StreamResumedEvent,
+    StreamSettledEvent, StreamToppedUpEvent, StreamWindingDownEvent,
+    StrictDepositCheckUpdatedEvent, TokenDefunctEvent, TokenFeeUpdatedEvent, TokenRecoveredEvent,
+    TokensWithdrawnEvent, TopUpParkedEvent, TopUpReclaimedEvent,
 };
 use storage::{
-    config_exists, load_config, load_stream, next_stream_id, save_config, save_stream,
-    try_load_config, try_load_stream,
+    active_stream_count, add_token_fees, add_token_locked, config_exists, decrement_active_streams,
+    end_pause, increment_active_streams, index_party_streams, index_private_streams,
+    index_token_stream, is_contract_paused, is_globally_paused, is_legacy_migrated, is_locked,
+    is_token_defunct, legacy_migration_cursor, load_allowed_destinations, load_asset_kind,
+    load_bundle, load_circuit_breaker, load_config, load_dust_threshold, load_earnings_threshold,
+    load_fee_exempt, load_max_duration, load_max_exposure, load_pending_topup,
+    load_recipient_earnings, load_stream, load_stream_readonly, load_strict_deposit_check,
+    load_tithe, load_token_fee_bps, load_token_totals, load_withdrawal_window, next_bundle_id,
+    next_stream_id, private_stream_ids, recipient_stream_ids, reindex_private_stream,
+    reindex_recipient_stream, save_allowed_destinations, save_asset_kind, save_bundle,
+    save_circuit_breaker, save_config, save_dust_threshold, save_earnings_threshold,
+    save_fee_exempt, save_legacy_migration_cursor, save_max_duration, save_max_exposure,
+    save_pending_topup, save_recipient_earnings, save_stream, save_strict_deposit_check,
+    save_tithe, save_token_defunct, save_token_fee_bps, save_withdrawal_window, sender_stream_ids,
+    set_contract_paused, set_globally_paused, set_legacy_migrated, set_locked, stream_count,
+    token_stream_ids, tracked_token, tracked_token_count, try_bump_stream_ttl, try_load_config,
+    try_load_stream, DAY_IN_LEDGERS, STREAM_TTL_THRESHOLD,
+};
+use types::{
+    AttentionReason, CancelMode, CancellerRole, CircuitBreakerConfig, ProtocolConfig,
+    ProtocolStats, RateUnit, Stream, StreamAssetKind, StreamFlags, StreamOptions, StreamStatus,
+    StreamView, StreamsPage, Tithe, TokenTotals, WithdrawalWindow,
 };
-use types::{ProtocolConfig, Stream, StreamStatus};
+
+/// Basis-point denominator: 10 000 bps = 100%.
+const MAX_BPS: u32 = 10_000;
 
 /// Maximum allowed protocol fee: 1 000 bps = 10%.
 const MAX_FEE_RATE_BPS: u32 = 1_000;
 
+/// Maximum number of samples returned by `claimable_curve`.
+const MAX_CURVE_POINTS: u32 = 100;
+
+/// Maximum number of streams returned by a single paginated query.
+const MAX_PAGE_SIZE: u32 = 50;
+
+/// Maximum number of items accepted by a single batch call.
+const MAX_BATCH_SIZE: u32 = 50;
+
+/// Maximum share of each withdrawal a recipient may route to a tithe: 50%.
+const MAX_TITHE_BPS: u32 = 5_000;
+
+/// Maximum number of release steps on a milestone stream.
+const MAX_MILESTONES: u32 = 50;
+
+/// Seconds in the day used by `get_rate_per_day`.
+const SECS_PER_DAY: i128 = 86_400;
+
+/// Seconds in the 30-day month used by `get_rate_per_month`.
+const SECS_PER_MONTH: i128 = 30 * SECS_PER_DAY;
+
+/// Approximate ledger close time, used to place milestone sequences on the
+/// timeline.
+const SECS_PER_LEDGER: i128 = 5;
+
+/// Funded runway below which an active stream is reported as `NearDepleted`.
+const NEAR_DEPLETION_SECS: i128 = 86_400;
+
+/// Staleness after which a stream is reported as `LowTtl`: half of
+/// `STREAM_TTL_THRESHOLD`, converted from ledgers to seconds (~5 s per ledger).
+const TTL_STALE_SECS: u64 = (STREAM_TTL_THRESHOLD / DAY_IN_LEDGERS) as u64 * 86_400 / 2;
+
 #[contract]
 pub struct StreamContract;
 
@@ -61,6 +125,11 @@ impl StreamContract {
                 fee_rate_bps,
             },
         );
+        // A contract initialized before any stream exists never held the
+        // legacy layout.
+        if stream_count(&env) == 0 {
+            set_legacy_migrated(&env);
+        }
 
         env.events().publish(
             (Symbol::new(&env, "initialized"),),
@@ -159,538 +228,3588 @@ impl StreamContract {
         Ok(())
     }
 
-    /// Returns the current protocol fee configuration, or `None` if not yet initialized.
-    pub fn get_fee_config(env: Env) -> Option<ProtocolConfig> {
-        try_load_config(&env)
-    }
-
-    // ─── Stream Operations ────────────────────────────────────────────────────
-
-    /// Create a new payment stream.
-    ///
-    /// Transfers `amount` tokens from `sender` to the contract, deducts the
-    /// protocol fee (if configured), and records the stream with a calculated
-    /// `rate_per_second = net_amount / duration`.
+    /// Replace the contract's code with the uploaded wasm `new_wasm_hash`,
+    /// keeping all storage. Admin-only.
     ///
-    /// Returns the new stream ID (starts at 1, increments monotonically).
+    /// The event is published before the swap; the new code takes effect
+    /// from the next invocation.
     ///
     /// # Errors
-    /// - `InvalidAmount`   — `amount` ≤ 0.
-    /// - `InvalidDuration` — `duration` is 0.
-    /// - `InvalidRate`     — `net_amount / duration` rounds to zero.
-    /// - `InvalidTokenAddress` — `token_address` is not a token contract.
-    pub fn create_stream(
-        env: Env,
-        sender: Address,
-        recipient: Address,
-        token_address: Address,
-        amount: i128,
-        duration: u64,
-    ) -> Result<u64, StreamError> {
-        sender.require_auth();
-
-        if amount <= 0 {
-            return Err(StreamError::InvalidAmount);
-        }
-        if duration == 0 {
-            return Err(StreamError::InvalidDuration);
-        }
-        Self::validate_token_contract(&env, &token_address)?;
-
-        let stream_id = next_stream_id(&env);
-        let start_time = env.ledger().timestamp();
-
-        // Transfer gross amount from sender to this contract.
-        let token_client = token::Client::new(&env, &token_address);
-        let contract_address = env.current_contract_address();
-        token_client.transfer(&sender, &contract_address, &amount);
-
-        // Deduct protocol fee; returns net amount (== amount when no fee config).
-        let net_amount = Self::collect_fee(&env, &token_address, amount, stream_id);
-        let rate_per_second = net_amount / (duration as i128);
-
-        // Reject streams where integer division rounds the rate to zero.
-        // Such a stream would lock the sender's tokens in the contract while
-        // never accruing anything to the recipient — almost always a caller
-        // mistake (wrong decimals or an excessively long duration).
-        // Soroban rolls back the entire transaction on Err, so the token
-        // transfer above is unwound automatically.
-        if rate_per_second == 0 {
-            return Err(StreamError::InvalidRate);
-        }
-
-        save_stream(
-            &env,
-            stream_id,
-            &Stream {
-                sender: sender.clone(),
-                recipient: recipient.clone(),
-                token_address: token_address.clone(),
-                rate_per_second,
-                deposited_amount: net_amount,
-                withdrawn_amount: 0,
-                start_time,
-                last_update_time: start_time,
-                is_active: true,
-                paused: false,
-                paused_at: None,
-                status: StreamStatus::Active,
-            },
-        );
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    pub fn upgrade(env: Env, admin: Address, new_wasm_hash: BytesN<32>) -> Result<(), StreamError> {
+        Self::require_admin(&env, &admin)?;
 
         env.events().publish(
-            (Symbol::new(&env, "stream_created"), stream_id),
-            StreamCreatedEvent {
-                stream_id,
-                sender,
-                recipient,
-                rate_per_second,
-                token_address,
-                deposited_amount: net_amount,
-                start_time,
+            (Symbol::new(&env, "contract_upgraded"),),
+            ContractUpgradedEvent {
+                admin,
+                new_wasm_hash: new_wasm_hash.clone(),
             },
         );
+        env.deployer().update_current_contract_wasm(new_wasm_hash);
 
-        Ok(stream_id)
+        Ok(())
     }
 
-    /// Top up an active stream with additional tokens.
+    /// Mark or unmark `address` as exempt from protocol fees. Admin-only.
     ///
-    /// Only the original sender may top up their own stream. The top-up amount
-    /// is subject to protocol fees (if configured) before being added to the stream.
+    /// A stream pays no fee on creation or top-up when either its sender or
+    /// its recipient is exempt.
     ///
     /// # Errors
-    /// - `InvalidAmount`   — `amount` ≤ 0.
-    /// - `StreamNotFound`  — no stream exists with `stream_id`.
-    /// - `Unauthorized`    — caller is not the stream's sender.
-    /// - `StreamInactive`  — stream has been cancelled or fully withdrawn.
-    pub fn top_up_stream(
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    pub fn set_fee_exempt(
         env: Env,
-        sender: Address,
-        stream_id: u64,
-        amount: i128,
+        admin: Address,
+        address: Address,
+        exempt: bool,
     ) -> Result<(), StreamError> {
-        sender.require_auth();
-
-        if amount <= 0 {
-            return Err(StreamError::InvalidAmount);
-        }
+        Self::require_admin(&env, &admin)?;
 
-        let mut stream = load_stream(&env, stream_id)?;
+        save_fee_exempt(&env, &address, exempt);
 
-        // Validate ownership and active status using helper functions
-        Self::validate_stream_ownership(&stream, &sender)?;
-        Self::validate_stream_active(&stream)?;
+        env.events().publish(
+            (Symbol::new(&env, "fee_exemption_updated"),),
+            FeeExemptionUpdatedEvent {
+                admin,
+                address,
+                exempt,
+            },
+        );
 
-        // Transfer tokens from sender to contract
-        let token_client = token::Client::new(&env, &stream.token_address);
-        let contract_address = env.current_contract_address();
-        token_client.transfer(&sender, &contract_address, &amount);
+        Ok(())
+    }
 
-        // Collect protocol fee and get net amount
-        let net_amount = Self::collect_fee(&env, &stream.token_address, amount, stream_id);
+    /// Returns `true` if `address` is exempt from protocol fees.
+    pub fn is_fee_exempt(env: Env, address: Address) -> bool {
+        load_fee_exempt(&env, &address)
+    }
 
-        // Update stream state. `last_update_time` is intentionally left untouched:
-        // it is the accrual anchor for `calculate_claimable`, and advancing it to
-        // `now` would discard any already-vested, unwithdrawn tokens.
-        stream.deposited_amount += net_amount;
+    /// Set the maximum duration (in seconds) accepted by `create_stream`. Admin-only.
+    ///
+    /// Bounds storage liability from effectively-permanent, near-zero-rate
+    /// streams. A value of 0 disables the limit.
+    ///
+    /// # Errors
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    pub fn set_max_duration(
+        env: Env,
+        admin: Address,
+        max_duration: u64,
+    ) -> Result<(), StreamError> {
+        Self::require_admin(&env, &admin)?;
 
-        save_stream(&env, stream_id, &stream);
+        let old_max_duration = load_max_duration(&env);
+        save_max_duration(&env, max_duration);
 
-        // Emit top-up event
         env.events().publish(
-            (Symbol::new(&env, "stream_topped_up"), stream_id),
-            StreamToppedUpEvent {
-                stream_id,
-                sender,
-                amount: net_amount,
-                new_deposited_amount: stream.deposited_amount,
+            (Symbol::new(&env, "max_duration_updated"),),
+            MaxDurationUpdatedEvent {
+                admin,
+                old_max_duration,
+                new_max_duration: max_duration,
             },
         );
 
         Ok(())
     }
 
-    // ─── Internal Helpers ─────────────────────────────────────────────────────
-
-    /// Ensures the supplied token address implements the Soroban token interface.
-    fn validate_token_contract(env: &Env, token_address: &Address) -> Result<(), StreamError> {
-        match env.try_invoke_contract::<u32, InvokeError>(
-            token_address,
-            &Symbol::new(env, "decimals"),
-            vec![env],
-        ) {
-            Ok(Ok(_)) => Ok(()),
-            _ => Err(StreamError::InvalidTokenAddress),
-        }
+    /// Returns the configured maximum stream duration in seconds (0 = unlimited).
+    pub fn get_max_duration(env: Env) -> u64 {
+        load_max_duration(&env)
     }
 
-    /// Calculate the claimable amount for a stream at a given timestamp.
+    /// Toggle strict deposit checking on stream creation. Admin-only.
     ///
-    /// Excludes any time the stream was paused. If the stream is currently
-    /// paused, accrual stops at `paused_at`.
+    /// When strict, `amount < duration` is rejected with
+    /// `DepositTooSmallForDuration` before any transfer or fee. When
+    /// permissive (the default), such terms still fail the `InvalidRate`
+    /// guard once the net rate is derived.
     ///
-    /// # Overflow Protection
-    /// - Uses `checked_mul` for rate_per_second * elapsed_seconds multiplication
-    /// - Caps at remaining deposited balance if overflow would occur
-    /// - Uses `checked_sub` for deposited - already_withdrawn calculation
-    /// - Overflow boundary: i128::MAX (~1.7e19) for both rate and duration
-    fn calculate_claimable(stream: &Stream, now: u64) -> i128 {
-        // When the stream is paused, accrue only up to the moment it was paused.
-        let effective_now = if stream.paused {
-            stream.paused_at.unwrap_or(stream.last_update_time)
-        } else {
-            now
-        };
+    /// # Errors
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    pub fn set_strict_deposit_check(
+        env: Env,
+        admin: Address,
+        strict: bool,
+    ) -> Result<(), StreamError> {
+        Self::require_admin(&env, &admin)?;
 
-        let elapsed = effective_now.saturating_sub(stream.last_update_time);
+        save_strict_deposit_check(&env, strict);
 
-        // Clamp to 0: withdrawn_amount should never exceed deposited_amount in
-        // normal flow, but guard defensively so the function never returns negative.
-        let remaining = stream
-            .deposited_amount
-            .saturating_sub(stream.withdrawn_amount)
-            .max(0);
+        env.events().publish(
+            (Symbol::new(&env, "strict_deposit_check_updated"),),
+            StrictDepositCheckUpdatedEvent { admin, strict },
+        );
 
-        // Use checked_mul to prevent overflow when multiplying rate * elapsed.
-        // If overflow would occur, cap at the remaining balance.
-        let streamed = match (elapsed as i128).checked_mul(stream.rate_per_second) {
-            Some(result) => result,
-            None => return remaining,
-        };
+        Ok(())
+    }
 
-        streamed.min(remaining)
+    /// Returns whether strict deposit checking is enabled.
+    pub fn get_strict_deposit_check(env: Env) -> bool {
+        load_strict_deposit_check(&env)
     }
 
-    /// Validate that a stream exists and is owned by the caller.
+    /// Cap the total amount that may be locked in streams of `token`. Admin-only.
+    ///
+    /// Limits the blast radius of a token or contract exploit. Enforced on
+    /// `create_stream` and `top_up_stream`; existing balances above a newly
+    /// lowered cap are left untouched. A value of 0 disables the cap.
     ///
     /// # Errors
-    /// - `StreamNotFound` — no stream exists with `stream_id`.
-    /// - `Unauthorized` — caller is not the stream's sender.
-    fn validate_stream_ownership(stream: &Stream, caller: &Address) -> Result<(), StreamError> {
-        if stream.sender != *caller {
-            return Err(StreamError::Unauthorized);
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    /// - `InvalidAmount`  — `max_exposure` is negative.
+    pub fn set_max_exposure_per_token(
+        env: Env,
+        admin: Address,
+        token: Address,
+        max_exposure: i128,
+    ) -> Result<(), StreamError> {
+        Self::require_admin(&env, &admin)?;
+        if max_exposure < 0 {
+            return Err(StreamError::InvalidAmount);
         }
+
+        let old_max_exposure = load_max_exposure(&env, &token);
+        save_max_exposure(&env, &token, max_exposure);
+
+        env.events().publish(
+            (Symbol::new(&env, "max_exposure_updated"),),
+            MaxExposureUpdatedEvent {
+                admin,
+                token,
+                old_max_exposure,
+                new_max_exposure: max_exposure,
+            },
+        );
+
         Ok(())
     }
 
-    /// Validate that a stream is active.
+    /// Returns the exposure cap for `token` (0 = unlimited).
+    pub fn get_max_exposure_per_token(env: Env, token: Address) -> i128 {
+        load_max_exposure(&env, &token)
+    }
+
+    /// Override the protocol fee rate for deposits in `token`. Admin-only.
+    ///
+    /// The override takes precedence over the global `fee_rate_bps` (including
+    /// an override of 0 for a fee-free token). Fees still go to the global
+    /// treasury, and fee-exempt parties remain exempt.
     ///
     /// # Errors
-    /// - `StreamInactive` — stream has been cancelled or fully withdrawn.
-    fn validate_stream_active(stream: &Stream) -> Result<(), StreamError> {
-        if !stream.is_active {
-            return Err(StreamError::StreamInactive);
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    /// - `InvalidFeeRate` — `fee_bps` exceeds `MAX_FEE_RATE_BPS`.
+    pub fn set_token_fee_bps(
+        env: Env,
+        admin: Address,
+        token: Address,
+        fee_bps: u32,
+    ) -> Result<(), StreamError> {
+        Self::require_admin(&env, &admin)?;
+        if fee_bps > MAX_FEE_RATE_BPS {
+            return Err(StreamError::InvalidFeeRate);
         }
+
+        Self::store_token_fee_bps(&env, admin, token, Some(fee_bps));
         Ok(())
     }
 
-    /// Apply a withdrawal: update stream state, persist it, then transfer tokens.
+    /// Remove a token's fee-rate override so the global rate applies. Admin-only.
     ///
-    /// Follows the Checks-Effects-Interactions (CEI) pattern: all state mutations
-    /// and the storage write complete before the external token transfer fires.
-    /// A re-entrant call via a malicious token hook therefore sees the already-updated
-    /// withdrawn_amount in storage and cannot trigger a double payout.
-    fn apply_withdrawal(
-        env: &Env,
-        stream: &mut Stream,
-        stream_id: u64,
-        recipient: &Address,
-        amount: i128,
-        now: u64,
-    ) {
-        // Effects: update stream state
-        stream.withdrawn_amount += amount;
-        stream.last_update_time = now;
-
-        if stream.withdrawn_amount >= stream.deposited_amount {
-            stream.is_active = false;
-            stream.status = StreamStatus::Completed;
-        }
-
-        // Persist state before any external call (CEI)
-        save_stream(env, stream_id, stream);
+    /// # Errors
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    pub fn clear_token_fee_bps(
+        env: Env,
+        admin: Address,
+        token: Address,
+    ) -> Result<(), StreamError> {
+        Self::require_admin(&env, &admin)?;
+        Self::store_token_fee_bps(&env, admin, token, None);
+        Ok(())
+    }
 
-        // Interaction: transfer tokens only after state is committed to storage
-        let token_client = token::Client::new(env, &stream.token_address);
-        token_client.transfer(&env.current_contract_address(), recipient, &amount);
+    /// Returns the fee-rate override for `token`, or `None` if the global rate applies.
+    pub fn get_token_fee_bps(env: Env, token: Address) -> Option<u32> {
+        load_token_fee_bps(&env, &token)
     }
 
-    /// Withdraw all currently claimable tokens from a stream.
-    ///
-    /// Only the stream's recipient may call this. The amount withdrawn is calculated
-    /// based on elapsed time and the stream's rate. The stream is automatically marked
-    /// inactive once fully drained.
+    /// Emit `RecipientEarningsEvent` each time a recipient's lifetime payouts in
+    /// `token` cross a multiple of `threshold`. Admin-only; 0 disables.
     ///
     /// # Errors
-    /// - `StreamNotFound`  — no stream exists with `stream_id`.
-    /// - `Unauthorized`    — caller is not the stream's recipient.
-    /// - `StreamInactive`  — stream is already inactive.
-    /// - `InvalidAmount`   — no claimable balance (fully withdrawn already).
-    pub fn withdraw(env: Env, recipient: Address, stream_id: u64) -> Result<i128, StreamError> {
-        recipient.require_auth();
-
-        let mut stream = load_stream(&env, stream_id)?;
-
-        // Validate recipient authorization
-        if stream.recipient != recipient {
-            return Err(StreamError::Unauthorized);
-        }
-
-        // Validate stream is active and not paused
-        Self::validate_stream_active(&stream)?;
-        if stream.paused {
-            return Err(StreamError::StreamPaused);
-        }
-
-        let now = env.ledger().timestamp();
-        let claimable = Self::calculate_claimable(&stream, now);
-
-        if claimable <= 0 {
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    /// - `InvalidAmount`  — `threshold` is negative.
+    pub fn set_earnings_threshold(
+        env: Env,
+        admin: Address,
+        token: Address,
+        threshold: i128,
+    ) -> Result<(), StreamError> {
+        Self::require_admin(&env, &admin)?;
+        if threshold < 0 {
             return Err(StreamError::InvalidAmount);
         }
 
-        // Apply withdrawal: updates state, persists to storage, then transfers (CEI)
-        Self::apply_withdrawal(&env, &mut stream, stream_id, &recipient, claimable, now);
-
-        let completed = stream.status == StreamStatus::Completed;
+        save_earnings_threshold(&env, &token, threshold);
 
         env.events().publish(
-            (Symbol::new(&env, "tokens_withdrawn"), stream_id),
-            TokensWithdrawnEvent {
-                stream_id,
-                recipient: recipient.clone(),
-                amount: claimable,
-                timestamp: stream.last_update_time,
+            (Symbol::new(&env, "earnings_threshold_updated"),),
+            EarningsThresholdUpdatedEvent {
+                admin,
+                token,
+                threshold,
             },
         );
 
-        // Emit COMPLETED event on final withdrawal
-        if completed {
-            env.events().publish(
-                (Symbol::new(&env, "stream_completed"), stream_id),
-                StreamCompletedEvent {
-                    stream_id,
-                    recipient,
-                    total_withdrawn: stream.withdrawn_amount,
-                },
-            );
-        }
-
-        Ok(claimable)
+        Ok(())
+    }
+
+    /// Returns the earnings-event threshold for `token` (0 = disabled).
+    pub fn get_earnings_threshold(env: Env, token: Address) -> i128 {
+        load_earnings_threshold(&env, &token)
+    }
+
+    /// Set the remainder below which a withdrawal in `token` also sweeps the
+    /// rest of the deposit to the recipient and completes the stream.
+    /// Admin-only; 0 disables.
+    ///
+    /// # Errors
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    /// - `InvalidAmount`  — `threshold` is negative.
+    pub fn set_dust_threshold(
+        env: Env,
+        admin: Address,
+        token: Address,
+        threshold: i128,
+    ) -> Result<(), StreamError> {
+        Self::require_admin(&env, &admin)?;
+        if threshold < 0 {
+            return Err(StreamError::InvalidAmount);
+        }
+
+        save_dust_threshold(&env, &token, threshold);
+
+        env.events().publish(
+            (Symbol::new(&env, "dust_threshold_updated"),),
+            DustThresholdUpdatedEvent {
+                admin,
+                token,
+                threshold,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Returns the dust threshold for `token` (0 = disabled).
+    pub fn get_dust_threshold(env: Env, token: Address) -> i128 {
+        load_dust_threshold(&env, &token)
+    }
+
+    /// Mark `token` as permanently non-functional so its streams can be
+    /// closed with `force_close_defunct`. Admin-only and irreversible.
+    ///
+    /// # Errors
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    pub fn mark_token_defunct(env: Env, admin: Address, token: Address) -> Result<(), StreamError> {
+        Self::require_admin(&env, &admin)?;
+
+        save_token_defunct(&env, &token);
+
+        env.events().publish(
+            (Symbol::new(&env, "token_defunct"),),
+            TokenDefunctEvent { admin, token },
+        );
+
+        Ok(())
+    }
+
+    /// Returns whether `token` has been marked defunct.
+    pub fn is_token_defunct(env: Env, token: Address) -> bool {
+        is_token_defunct(&env, &token)
+    }
+
+    /// Sweep `token` sent straight to the contract (outside any stream) to
+    /// `to`. Admin-only. Returns the amount recovered.
+    ///
+    /// Only the balance above the token's `total_locked` is excess, so funds
+    /// backing streams or parked top-ups are never touched. For a rebasing
+    /// token with live streams the surplus is their rebase share, so nothing
+    /// is recovered.
+    ///
+    /// # Errors
+    /// - `NotInitialized`   — `initialize` has not been called.
+    /// - `NotAdmin`         — caller is not the current admin.
+    /// - `MigrationPending` — legacy streams may still be missing from the
+    ///   token totals; run `migrate_legacy_streams` to completion first.
+    pub fn recover_token(
+        env: Env,
+        admin: Address,
+        token: Address,
+        to: Address,
+    ) -> Result<i128, StreamError> {
+        Self::non_reentrant(&env, || {
+            Self::require_admin(&env, &admin)?;
+            if !is_legacy_migrated(&env) {
+                return Err(StreamError::MigrationPending);
+            }
+
+            if Self::payout_scale(&env, &token).is_some() {
+                return Ok(0);
+            }
+            let token_client = token::Client::new(&env, &token);
+            let contract_address = env.current_contract_address();
+            let excess = token_client.balance(&contract_address)
+                - load_token_totals(&env, &token).total_locked;
+            if excess <= 0 {
+                return Ok(0);
+            }
+
+            token_client.transfer(&contract_address, &to, &excess);
+
+            env.events().publish(
+                (Symbol::new(&env, "token_recovered"),),
+                TokenRecoveredEvent {
+                    admin,
+                    token,
+                    to,
+                    amount: excess,
+                },
+            );
+
+            Ok(excess)
+        })
+    }
+
+    /// Configure the withdrawal-velocity circuit breaker for `token`. Admin-only.
+    ///
+    /// Once more than `max_volume` of `token` is withdrawn within one
+    /// `window_secs` window, the protocol is paused and every withdrawal fails
+    /// with `ProtocolPaused` until `reset_circuit_breaker`. `max_volume = 0`
+    /// disables the breaker for the token.
+    ///
+    /// # Errors
+    /// - `NotInitialized`  — `initialize` has not been called.
+    /// - `NotAdmin`        — caller is not the current admin.
+    /// - `InvalidAmount`   — `max_volume` is negative.
+    /// - `InvalidDuration` — `window_secs` is zero on an enabled breaker.
+    pub fn set_circuit_breaker(
+        env: Env,
+        admin: Address,
+        token: Address,
+        max_volume: i128,
+        window_secs: u64,
+    ) -> Result<(), StreamError> {
+        Self::require_admin(&env, &admin)?;
+        if max_volume < 0 {
+            return Err(StreamError::InvalidAmount);
+        }
+        if max_volume > 0 && window_secs == 0 {
+            return Err(StreamError::InvalidDuration);
+        }
+
+        let config = (max_volume > 0).then_some(CircuitBreakerConfig {
+            max_volume,
+            window_secs,
+        });
+        save_circuit_breaker(&env, &token, config);
+
+        env.events().publish(
+            (Symbol::new(&env, "circuit_breaker_updated"),),
+            CircuitBreakerUpdatedEvent {
+                admin,
+                token,
+                max_volume,
+                window_secs,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Returns the circuit-breaker limit for `token`, if one is configured.
+    pub fn get_circuit_breaker(env: Env, token: Address) -> Option<CircuitBreakerConfig> {
+        load_circuit_breaker(&env, &token)
+    }
+
+    /// Release the global pause engaged by a tripped circuit breaker. Admin-only.
+    ///
+    /// Leaves any `pause_contract` pause in place; see `pause_contract`.
+    ///
+    /// # Errors
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    pub fn reset_circuit_breaker(env: Env, admin: Address) -> Result<(), StreamError> {
+        Self::require_admin(&env, &admin)?;
+        set_globally_paused(&env, false);
+
+        env.events().publish(
+            (Symbol::new(&env, "circuit_breaker_reset"),),
+            CircuitBreakerResetEvent { admin },
+        );
+
+        Ok(())
+    }
+
+    /// Returns whether the circuit breaker currently holds the protocol paused.
+    pub fn is_protocol_paused(env: Env) -> bool {
+        is_globally_paused(&env)
+    }
+
+    /// Halt stream creation, top-ups and withdrawals. Admin-only.
+    ///
+    /// Independent of the circuit breaker's pause (`ProtocolPaused`), which
+    /// the contract engages on its own and which only stops withdrawals; the
+    /// two flags are set and cleared separately so resetting a tripped
+    /// breaker never lifts an emergency halt, and unpausing never clears a
+    /// trip. A withdrawal checks the breaker first. Cancellation, pausing and
+    /// views keep working under either so senders can still exit.
+    ///
+    /// # Errors
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    pub fn pause_contract(env: Env, admin: Address) -> Result<(), StreamError> {
+        Self::set_contract_pause(&env, admin, true)
+    }
+
+    /// Release the pause engaged by `pause_contract`. Admin-only.
+    ///
+    /// # Errors
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    pub fn unpause_contract(env: Env, admin: Address) -> Result<(), StreamError> {
+        Self::set_contract_pause(&env, admin, false)
+    }
+
+    /// Returns whether the admin currently holds the contract paused.
+    pub fn is_contract_paused(env: Env) -> bool {
+        is_contract_paused(&env)
+    }
+
+    /// Shared admin path for `pause_contract` and `unpause_contract`.
+    fn set_contract_pause(env: &Env, admin: Address, paused: bool) -> Result<(), StreamError> {
+        Self::require_admin(env, &admin)?;
+        set_contract_paused(env, paused);
+
+        env.events().publish(
+            (Symbol::new(env, "contract_pause_updated"),),
+            ContractPauseUpdatedEvent { admin, paused },
+        );
+
+        Ok(())
+    }
+
+    /// Returns the lifetime amount paid to `recipient` in `token` across all streams.
+    pub fn get_recipient_earnings(env: Env, recipient: Address, token: Address) -> i128 {
+        load_recipient_earnings(&env, &recipient, &token)
+    }
+
+    /// Register how payouts in `token` are normalized. Admin-only.
+    ///
+    /// Should be set before any stream in `token` is created; switching a token
+    /// with live streams from `Plain` to `Rebasing` applies to future payouts.
+    ///
+    /// # Errors
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — caller is not the current admin.
+    pub fn set_asset_kind(
+        env: Env,
+        admin: Address,
+        token: Address,
+        kind: StreamAssetKind,
+    ) -> Result<(), StreamError> {
+        Self::require_admin(&env, &admin)?;
+
+        save_asset_kind(&env, &token, kind);
+
+        env.events().publish(
+            (Symbol::new(&env, "asset_kind_updated"),),
+            AssetKindUpdatedEvent { admin, token, kind },
+        );
+
+        Ok(())
+    }
+
+    /// Returns the registered asset kind for `token` (`Plain` unless set).
+    pub fn get_asset_kind(env: Env, token: Address) -> StreamAssetKind {
+        load_asset_kind(&env, &token)
+    }
+
+    /// Returns the current protocol fee configuration, or `None` if not yet initialized.
+    pub fn get_fee_config(env: Env) -> Option<ProtocolConfig> {
+        try_load_config(&env)
+    }
+
+    // ─── Stream Operations ────────────────────────────────────────────────────
+
+    /// Create a new payment stream.
+    ///
+    /// Transfers `amount` tokens from `sender` to the contract, deducts the
+    /// protocol fee (if configured), and records the stream with a calculated
+    /// `rate_per_second = net_amount / duration`.
+    ///
+    /// Returns the new stream ID (starts at 1, increments monotonically).
+    ///
+    /// # Errors
+    /// - `InvalidAmount`   — `amount` ≤ 0.
+    /// - `InvalidDuration` — `duration` is 0.
+    /// - `DurationTooLong` — `duration` exceeds the configured `max_duration`.
+    /// - `InvalidRate`     — `net_amount / duration` rounds to zero.
+    /// - `DepositTooSmallForDuration` — strict mode is on and `amount < duration`.
+    /// - `InvalidRecipient` — `recipient` is this contract or the sender.
+    /// - `InvalidTokenAddress` — `token_address` is not a token contract.
+    /// - `ExposureCapReached`  — total locked for the token would exceed its cap.
+    /// - `ContractPaused`      — the admin has paused the contract.
+    pub fn create_stream(
+        env: Env,
+        sender: Address,
+        recipient: Address,
+        token_address: Address,
+        amount: i128,
+        duration: u64,
+    ) -> Result<u64, StreamError> {
+        sender.require_auth();
+        Self::create_stream_internal(
+            &env,
+            sender,
+            recipient,
+            token_address,
+            amount,
+            duration,
+            StreamOptions::default(),
+            Symbol::new(&env, "direct"),
+        )
+    }
+
+    /// Create a new payment stream with optional per-stream settings.
+    ///
+    /// Behaves exactly like `create_stream`, additionally applying `options`
+    /// to the new stream. Tagged `created_by = "with_options"`.
+    ///
+    /// # Errors
+    /// Same as `create_stream`, plus:
+    /// - `InvalidMilestones` — `options.milestones` is malformed.
+    /// - `InvalidCliff`      — `options.cliff_duration` exceeds `duration`.
+    pub fn create_stream_with_options(
+        env: Env,
+        sender: Address,
+        recipient: Address,
+        token_address: Address,
+        amount: i128,
+        duration: u64,
+        options: StreamOptions,
+    ) -> Result<u64, StreamError> {
+        sender.require_auth();
+        Self::create_stream_internal(
+            &env,
+            sender,
+            recipient,
+            token_address,
+            amount,
+            duration,
+            options,
+            Symbol::new(&env, "with_options"),
+        )
+    }
+
+    /// Create one stream per `(recipients[i], amounts[i])` pair in `token_address`
+    /// over the same `duration`, under a single sender authorization.
+    ///
+    /// Atomic: if any stream fails to create, the whole call reverts and no
+    /// stream is opened. Tagged `created_by = "batch"`. Returns the new ids in
+    /// input order.
+    ///
+    /// # Errors
+    /// Same as `create_stream` for each stream, plus:
+    /// - `InvalidAmount`       — `recipients` is empty.
+    /// - `BatchLengthMismatch` — `recipients` and `amounts` differ in length.
+    /// - `BatchTooLarge`       — more than `MAX_BATCH_SIZE` streams.
+    pub fn create_streams_batch(
+        env: Env,
+        sender: Address,
+        recipients: Vec<Address>,
+        token_address: Address,
+        amounts: Vec<i128>,
+        duration: u64,
+    ) -> Result<Vec<u64>, StreamError> {
+        sender.require_auth();
+
+        if recipients.len() != amounts.len() {
+            return Err(StreamError::BatchLengthMismatch);
+        }
+        if recipients.is_empty() {
+            return Err(StreamError::InvalidAmount);
+        }
+        if recipients.len() > MAX_BATCH_SIZE {
+            return Err(StreamError::BatchTooLarge);
+        }
+
+        let mut stream_ids = Vec::new(&env);
+        for (recipient, amount) in recipients.iter().zip(amounts.iter()) {
+            stream_ids.push_back(Self::create_stream_internal(
+                &env,
+                sender.clone(),
+                recipient,
+                token_address.clone(),
+                amount,
+                duration,
+                StreamOptions::default(),
+                Symbol::new(&env, "batch"),
+            )?);
+        }
+
+        Ok(stream_ids)
+    }
+
+    /// Create one stream per `(token, amount)` leg, all to `recipient` over the
+    /// same `duration`, grouped under a new bundle id.
+    ///
+    /// Each leg is an ordinary stream with independent accounting, fees, and
+    /// lifecycle; the bundle only records which streams belong together.
+    ///
+    /// # Errors
+    /// Same as `create_stream` for each leg, plus:
+    /// - `InvalidAmount`       — `legs` is empty.
+    /// - `BatchTooLarge`       — more than `MAX_BATCH_SIZE` legs.
+    /// - `InvalidTokenAddress` — the same token appears in two legs.
+    pub fn create_bundle_stream(
+        env: Env,
+        sender: Address,
+        recipient: Address,
+        legs: Vec<(Address, i128)>,
+        duration: u64,
+    ) -> Result<u64, StreamError> {
+        sender.require_auth();
+
+        if legs.is_empty() {
+            return Err(StreamError::InvalidAmount);
+        }
+        if legs.len() > MAX_BATCH_SIZE {
+            return Err(StreamError::BatchTooLarge);
+        }
+
+        let bundle_id = next_bundle_id(&env);
+        let mut stream_ids = Vec::new(&env);
+        for (i, (token_address, amount)) in legs.iter().enumerate() {
+            if legs
+                .iter()
+                .take(i)
+                .any(|(earlier, _)| earlier == token_address)
+            {
+                return Err(StreamError::InvalidTokenAddress);
+            }
+            let stream_id = Self::create_stream_internal(
+                &env,
+                sender.clone(),
+                recipient.clone(),
+                token_address,
+                amount,
+                duration,
+                StreamOptions::default(),
+                Symbol::new(&env, "bundle"),
+            )?;
+            stream_ids.push_back(stream_id);
+        }
+        save_bundle(&env, bundle_id, &stream_ids);
+
+        env.events().publish(
+            (Symbol::new(&env, "bundle_created"), bundle_id),
+            BundleCreatedEvent {
+                bundle_id,
+                sender,
+                recipient,
+                stream_ids,
+            },
+        );
+
+        Ok(bundle_id)
+    }
+
+    /// Preview the `(rate_per_second, end_time)` a `create_stream` call with
+    /// these terms would produce right now, using the contract's own math.
+    ///
+    /// Applies the global fee rate; per-token fee overrides and fee-exempt
+    /// parties are not considered, since no token or parties are given.
+    ///
+    /// # Errors
+    /// Same validation errors as `create_stream` (`InvalidAmount`,
+    /// `InvalidDuration`, `DurationTooLong`, `InvalidRate`).
+    pub fn simulate_create(
+        env: Env,
+        amount: i128,
+        duration: u64,
+    ) -> Result<(i128, u64), StreamError> {
+        Self::validate_create_terms(&env, amount, duration)?;
+
+        let fee_rate_bps = try_load_config(&env).map_or(0, |cfg| cfg.fee_rate_bps);
+        let net_amount = amount - Self::fee_for(amount, fee_rate_bps);
+        let rate_per_second = Self::derive_rate(net_amount, duration)?;
+        let now = env.ledger().timestamp();
+        let end_time =
+            Self::end_time_from(now, net_amount, rate_per_second).min(now.saturating_add(duration));
+
+        Ok((rate_per_second, end_time))
+    }
+
+    /// Validates the amount and duration of a new stream.
+    ///
+    /// # Errors
+    /// - `InvalidAmount`   — `amount` ≤ 0.
+    /// - `InvalidDuration` — `duration` is 0.
+    /// - `DurationTooLong` — `duration` exceeds the configured `max_duration`.
+    /// - `DepositTooSmallForDuration` — strict mode and `amount < duration`.
+    fn validate_create_terms(env: &Env, amount: i128, duration: u64) -> Result<(), StreamError> {
+        if amount <= 0 {
+            return Err(StreamError::InvalidAmount);
+        }
+        if duration == 0 {
+            return Err(StreamError::InvalidDuration);
+        }
+        let max_duration = load_max_duration(env);
+        if max_duration > 0 && duration > max_duration {
+            return Err(StreamError::DurationTooLong);
+        }
+        if load_strict_deposit_check(env) && amount < duration as i128 {
+            return Err(StreamError::DepositTooSmallForDuration);
+        }
+        Ok(())
+    }
+
+    /// Checks a milestone schedule against the stream's net deposit.
+    ///
+    /// # Errors
+    /// - `InvalidMilestones` — the schedule is empty or longer than
+    ///   `MAX_MILESTONES`, sequences do not strictly increase, a cumulative
+    ///   amount is negative or decreases, or the last step is not `net_amount`.
+    fn validate_milestones(
+        milestones: &Vec<(u32, i128)>,
+        net_amount: i128,
+    ) -> Result<(), StreamError> {
+        if milestones.is_empty() || milestones.len() > MAX_MILESTONES {
+            return Err(StreamError::InvalidMilestones);
+        }
+        let mut previous: Option<(u32, i128)> = None;
+        for (sequence, cumulative) in milestones.iter() {
+            let ordered = match previous {
+                Some((prev_sequence, prev_cumulative)) => {
+                    sequence > prev_sequence && cumulative >= prev_cumulative
+                }
+                None => cumulative >= 0,
+            };
+            if !ordered {
+                return Err(StreamError::InvalidMilestones);
+            }
+            previous = Some((sequence, cumulative));
+        }
+        match previous {
+            Some((_, total)) if total == net_amount => Ok(()),
+            _ => Err(StreamError::InvalidMilestones),
+        }
+    }
+
+    /// Rate at which `streamed` tokens accrue over `duration` seconds.
+    ///
+    /// Rejects streams where integer division rounds the rate to zero.
+    /// Such a stream would lock the sender's tokens in the contract while
+    /// never accruing anything to the recipient — almost always a caller
+    /// mistake (wrong decimals or an excessively long duration).
+    ///
+    /// # Errors
+    /// - `InvalidRate` — `streamed / duration` rounds to zero.
+    fn derive_rate(streamed: i128, duration: u64) -> Result<i128, StreamError> {
+        let rate_per_second = streamed / (duration as i128);
+        if rate_per_second == 0 {
+            return Err(StreamError::InvalidRate);
+        }
+        Ok(rate_per_second)
+    }
+
+    /// Shared creation path for every stream-creating entry point.
+    ///
+    /// The caller must already have required `sender` auth. `created_by` tags
+    /// the stream with the entry point that created it.
+    #[allow(clippy::too_many_arguments)]
+    fn create_stream_internal(
+        env: &Env,
+        sender: Address,
+        recipient: Address,
+        token_address: Address,
+        amount: i128,
+        duration: u64,
+        options: StreamOptions,
+        created_by: Symbol,
+    ) -> Result<u64, StreamError> {
+        Self::non_reentrant(env, || {
+            if is_contract_paused(env) {
+                return Err(StreamError::ContractPaused);
+            }
+            Self::validate_create_terms(env, amount, duration)?;
+            // Only the recipient can withdraw, so the contract itself can never
+            // be one; a self-stream would just lock the sender's own funds.
+            if recipient == env.current_contract_address() || recipient == sender {
+                return Err(StreamError::InvalidRecipient);
+            }
+            Self::validate_token_contract(env, &token_address)?;
+
+            let stream_id = next_stream_id(env);
+            let start_time = env.ledger().timestamp();
+
+            // Transfer gross amount from sender to this contract.
+            let token_client = token::Client::new(env, &token_address);
+            let contract_address = env.current_contract_address();
+            token_client.transfer(&sender, &contract_address, &amount);
+
+            // Deduct protocol fee; returns net amount (== amount when no fee config).
+            let net_amount =
+                Self::collect_fee(env, &token_address, amount, stream_id, &sender, &recipient);
+
+            // The initial unlock is released at `start_time`; only the rest streams.
+            if options.initial_unlock < 0 || options.initial_unlock > net_amount {
+                return Err(StreamError::InvalidAmount);
+            }
+            // Soroban rolls back the entire transaction on Err, so the token
+            // transfer above is unwound automatically.
+            let rate_per_second = Self::derive_rate(net_amount - options.initial_unlock, duration)?;
+            if let Some(milestones) = &options.milestones {
+                Self::validate_milestones(milestones, net_amount)?;
+            }
+            if options.cliff_duration > duration {
+                return Err(StreamError::InvalidCliff);
+            }
+            let cliff_time = start_time.saturating_add(options.cliff_duration);
+
+            Self::lock_within_exposure_cap(env, &token_address, net_amount)?;
+            increment_active_streams(env);
+            if options.is_private {
+                index_private_streams(env, &sender, &recipient, stream_id);
+            } else {
+                index_token_stream(env, &token_address, stream_id);
+                index_party_streams(env, &sender, &recipient, stream_id);
+            }
+
+            save_stream(
+                env,
+                stream_id,
+                &Stream {
+                    sender: sender.clone(),
+                    recipient: recipient.clone(),
+                    token_address: token_address.clone(),
+                    rate_per_second,
+                    deposited_amount: net_amount,
+                    withdrawn_amount: 0,
+                    accrued_checkpoint: options.initial_unlock,
+                    start_time,
+                    last_update_time: start_time,
+                    is_active: true,
+                    paused: false,
+                    paused_at: None,
+                    winding_down: false,
+                    status: StreamStatus::Active,
+                    created_by: created_by.clone(),
+                    recipient_must_ack_topups: false,
+                    first_withdraw_delay: options.first_withdraw_delay,
+                    withdraw_gate: options.withdraw_gate,
+                    total_paused_duration: 0,
+                    is_public: !options.is_private,
+                    finalized: false,
+                    initial_unlock: options.initial_unlock,
+                    milestones: options.milestones,
+                    end_time: start_time.saturating_add(duration),
+                    cliff_time,
+                    memo: options.memo.clone(),
+                    resume_at: None,
+                    rate_unit: RateUnit::PerSecond,
+                },
+            );
+
+            env.events().publish(
+                (Symbol::new(env, "stream_created"), stream_id),
+                StreamCreatedEvent {
+                    stream_id,
+                    sender,
+                    recipient,
+                    rate_per_second,
+                    token_address,
+                    deposited_amount: net_amount,
+                    start_time,
+                    created_by,
+                    end_time: start_time.saturating_add(duration),
+                    cliff_time,
+                    memo: options.memo,
+                },
+            );
+
+            Ok(stream_id)
+        })
+    }
+
+    /// Preview the projected end time after topping up `stream_id` by
+    /// `amount`, using the same fee and accrual math as `top_up_stream`.
+    ///
+    /// Does not mutate state. For streams that require recipient
+    /// acknowledgment, the result is the end time once the top-up is acked.
+    ///
+    /// # Errors
+    /// - `InvalidAmount`     — `amount` ≤ 0.
+    /// - `StreamNotFound`    — no stream exists with `stream_id`.
+    /// - `StreamFinalized`   — the stream has been finalized.
+    /// - `StreamInactive`    — stream has been cancelled or fully withdrawn.
+    /// - `StreamWindingDown` — stream is winding down gracefully.
+    /// - `StreamPaused`      — stream is paused.
+    pub fn preview_topup(env: Env, stream_id: u64, amount: i128) -> Result<u64, StreamError> {
+        if amount <= 0 {
+            return Err(StreamError::InvalidAmount);
+        }
+
+        let mut stream = load_stream_readonly(&env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+        Self::validate_stream_active(&stream)?;
+        Self::validate_stream_controllable(&stream)?;
+        if stream.paused {
+            return Err(StreamError::StreamPaused);
+        }
+
+        let fee_rate_bps = Self::effective_fee_rate(
+            &env,
+            &stream.token_address,
+            &stream.sender,
+            &stream.recipient,
+        );
+        Self::credit_deposit(&mut stream, amount - Self::fee_for(amount, fee_rate_bps));
+        Ok(Self::projected_end_time(&stream))
+    }
+
+    /// Top up an active stream with additional tokens.
+    ///
+    /// Only the original sender may top up their own stream. The top-up amount
+    /// is subject to protocol fees (if configured) before being added to the stream.
+    /// If the recipient requires acknowledgment, the net amount is parked instead
+    /// and only credited once the recipient calls `ack_topup`.
+    ///
+    /// Returns the updated stream, so callers need no follow-up `get_stream`.
+    ///
+    /// # Errors
+    /// - `InvalidAmount`   — `amount` ≤ 0.
+    /// - `StreamNotFound`  — no stream exists with `stream_id`.
+    /// - `Unauthorized`    — caller is not the stream's sender.
+    /// - `StreamInactive`  — stream has been cancelled or fully withdrawn.
+    /// - `StreamWindingDown` — stream is winding down gracefully.
+    /// - `StreamPaused`    — stream is paused; resume before adding funds.
+    /// - `ExposureCapReached` — total locked for the token would exceed its cap.
+    /// - `ContractPaused`  — the admin has paused the contract.
+    pub fn top_up_stream(
+        env: Env,
+        sender: Address,
+        stream_id: u64,
+        amount: i128,
+    ) -> Result<Stream, StreamError> {
+        sender.require_auth();
+
+        if amount <= 0 {
+            return Err(StreamError::InvalidAmount);
+        }
+        let stream = Self::load_topup_target(&env, &sender, stream_id)?;
+        Self::apply_topup(&env, sender, stream, stream_id, amount)
+    }
+
+    /// Top up just enough that the stream stays funded for `target_seconds`
+    /// from now, and return the gross amount pulled from `sender`.
+    ///
+    /// The amount is grossed up for the protocol fee, and top-ups still
+    /// parked for acknowledgment count as funded. Pulls nothing and returns
+    /// 0 if the runway already meets the target.
+    ///
+    /// # Errors
+    /// Same as `top_up_stream`, except that `InvalidAmount` means the amount
+    /// needed to reach the target overflows `i128`.
+    pub fn top_up_to_runway(
+        env: Env,
+        sender: Address,
+        stream_id: u64,
+        target_seconds: u64,
+    ) -> Result<i128, StreamError> {
+        sender.require_auth();
+
+        let stream = Self::load_topup_target(&env, &sender, stream_id)?;
+        let mut funded = stream.clone();
+        Self::credit_deposit(&mut funded, load_pending_topup(&env, stream_id));
+        let target_end = env.ledger().timestamp().saturating_add(target_seconds);
+        let shortfall = target_end.saturating_sub(Self::projected_end_time(&funded));
+        if shortfall == 0 {
+            return Ok(0);
+        }
+
+        // Each credited `rate_per_second` pushes the projected end back 1 s.
+        let net_needed = (shortfall as i128)
+            .checked_mul(stream.rate_per_second)
+            .ok_or(StreamError::InvalidAmount)?;
+        let fee_rate_bps = Self::effective_fee_rate(
+            &env,
+            &stream.token_address,
+            &stream.sender,
+            &stream.recipient,
+        );
+        let keep_bps = (MAX_BPS - fee_rate_bps) as i128;
+        let mut amount = net_needed
+            .checked_mul(MAX_BPS as i128)
+            .and_then(|gross| gross.checked_add(keep_bps - 1))
+            .ok_or(StreamError::InvalidAmount)?
+            / keep_bps;
+        // The fee rounds down, so the smallest sufficient amount may be lower.
+        while amount > 1 && amount - 1 - Self::fee_for(amount - 1, fee_rate_bps) >= net_needed {
+            amount -= 1;
+        }
+
+        Self::apply_topup(&env, sender, stream, stream_id, amount)?;
+        Ok(amount)
+    }
+
+    /// Loads `stream_id` and checks that `sender` may top it up right now.
+    fn load_topup_target(
+        env: &Env,
+        sender: &Address,
+        stream_id: u64,
+    ) -> Result<Stream, StreamError> {
+        if is_contract_paused(env) {
+            return Err(StreamError::ContractPaused);
+        }
+
+        let stream = load_stream(env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+
+        // Validate ownership and active status using helper functions
+        Self::validate_stream_ownership(&stream, sender)?;
+        Self::validate_stream_active(&stream)?;
+        Self::validate_stream_controllable(&stream)?;
+        // A halted agreement should not take on new funds; resume first.
+        if stream.paused {
+            return Err(StreamError::StreamPaused);
+        }
+        Ok(stream)
+    }
+
+    /// Shared top-up path: pulls `amount`, takes the fee, and credits or parks
+    /// the net amount. The caller must have validated `stream`.
+    fn apply_topup(
+        env: &Env,
+        sender: Address,
+        mut stream: Stream,
+        stream_id: u64,
+        amount: i128,
+    ) -> Result<Stream, StreamError> {
+        Self::non_reentrant(env, || {
+            // Transfer tokens from sender to contract
+            let token_client = token::Client::new(env, &stream.token_address);
+            let contract_address = env.current_contract_address();
+            token_client.transfer(&sender, &contract_address, &amount);
+
+            // Collect protocol fee and get net amount
+            let net_amount = Self::collect_fee(
+                env,
+                &stream.token_address,
+                amount,
+                stream_id,
+                &stream.sender,
+                &stream.recipient,
+            );
+
+            Self::lock_within_exposure_cap(env, &stream.token_address, net_amount)?;
+
+            if stream.recipient_must_ack_topups {
+                let pending_amount = load_pending_topup(env, stream_id) + net_amount;
+                save_pending_topup(env, stream_id, pending_amount);
+
+                env.events().publish(
+                    (Symbol::new(env, "topup_parked"), stream_id),
+                    TopUpParkedEvent {
+                        stream_id,
+                        sender,
+                        amount: net_amount,
+                        pending_amount,
+                    },
+                );
+
+                return Ok(stream);
+            }
+
+            // Update stream state. `last_update_time` is intentionally left untouched:
+            // it is the accrual anchor for `calculate_claimable`, and advancing it to
+            // `now` would discard any already-vested, unwithdrawn tokens.
+            Self::credit_deposit(&mut stream, net_amount);
+
+            save_stream(env, stream_id, &stream);
+
+            // Emit top-up event
+            env.events().publish(
+                (Symbol::new(env, "stream_topped_up"), stream_id),
+                StreamToppedUpEvent {
+                    stream_id,
+                    sender,
+                    amount: net_amount,
+                    new_deposited_amount: stream.deposited_amount,
+                    new_end_time: stream.end_time,
+                },
+            );
+
+            Ok(stream)
+        })
+    }
+
+    /// Require (or stop requiring) recipient acknowledgment of top-ups.
+    ///
+    /// Only the stream's recipient may change this. Already-parked top-ups stay
+    /// parked until acknowledged or reclaimed.
+    ///
+    /// # Errors
+    /// - `StreamNotFound`  — no stream exists with `stream_id`.
+    /// - `Unauthorized`    — caller is not the stream's recipient.
+    /// - `StreamInactive`  — stream has been cancelled or fully withdrawn.
+    pub fn set_topup_ack_required(
+        env: Env,
+        recipient: Address,
+        stream_id: u64,
+        required: bool,
+    ) -> Result<(), StreamError> {
+        recipient.require_auth();
+
+        let mut stream = load_stream(&env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+        if stream.recipient != recipient {
+            return Err(StreamError::Unauthorized);
+        }
+        Self::validate_stream_active(&stream)?;
+
+        stream.recipient_must_ack_topups = required;
+        save_stream(&env, stream_id, &stream);
+
+        Ok(())
+    }
+
+    /// Route `bps` of every future withdrawal on this stream to `address`.
+    ///
+    /// Only the stream's recipient may configure this; `bps = 0` removes the
+    /// tithe. Applies to `withdraw` and its variants, not to cancellation
+    /// settlement.
+    ///
+    /// # Errors
+    /// - `StreamNotFound`  — no stream exists with `stream_id`.
+    /// - `Unauthorized`    — caller is not the stream's recipient.
+    /// - `StreamInactive`  — stream has been cancelled or fully withdrawn.
+    /// - `InvalidTithe`    — `bps` exceeds `MAX_TITHE_BPS`, or `address` is
+    ///   the recipient or this contract.
+    pub fn set_tithe(
+        env: Env,
+        recipient: Address,
+        stream_id: u64,
+        address: Address,
+        bps: u32,
+    ) -> Result<(), StreamError> {
+        recipient.require_auth();
+
+        let stream = load_stream(&env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+        if stream.recipient != recipient {
+            return Err(StreamError::Unauthorized);
+        }
+        Self::validate_stream_active(&stream)?;
+        if bps > MAX_TITHE_BPS || address == recipient || address == env.current_contract_address()
+        {
+            return Err(StreamError::InvalidTithe);
+        }
+
+        save_tithe(&env, stream_id, (bps > 0).then_some(Tithe { address, bps }));
+        Ok(())
+    }
+
+    /// Returns the tithe configured on a stream, if any.
+    pub fn get_tithe(env: Env, stream_id: u64) -> Option<Tithe> {
+        load_tithe(&env, stream_id)
+    }
+
+    /// Restrict `withdraw_to` on a stream to the addresses in `dests`, e.g.
+    /// the recipient's own custody wallets. An empty list lifts the
+    /// restriction. Plain `withdraw` always pays the recipient and is
+    /// unaffected.
+    ///
+    /// # Errors
+    /// - `StreamNotFound`  — no stream exists with `stream_id`.
+    /// - `Unauthorized`    — caller is not the stream's recipient.
+    /// - `StreamInactive`  — stream has been cancelled or fully withdrawn.
+    /// - `BatchTooLarge`   — more than `MAX_BATCH_SIZE` destinations.
+    pub fn set_allowed_destinations(
+        env: Env,
+        recipient: Address,
+        stream_id: u64,
+        dests: Vec<Address>,
+    ) -> Result<(), StreamError> {
+        recipient.require_auth();
+
+        let stream = load_stream(&env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+        if stream.recipient != recipient {
+            return Err(StreamError::Unauthorized);
+        }
+        Self::validate_stream_active(&stream)?;
+        if dests.len() > MAX_BATCH_SIZE {
+            return Err(StreamError::BatchTooLarge);
+        }
+
+        save_allowed_destinations(&env, stream_id, &dests);
+        Ok(())
+    }
+
+    /// Returns the `withdraw_to` destinations approved for a stream; empty
+    /// means any destination is allowed.
+    pub fn get_allowed_destinations(env: Env, stream_id: u64) -> Vec<Address> {
+        load_allowed_destinations(&env, stream_id)
+    }
+
+    /// Hand the stream over to `new_recipient`, e.g. to move it to a multisig.
+    ///
+    /// Only the current recipient may transfer. Everything accrued but not
+    /// yet withdrawn moves with the stream. The old recipient's tithe and
+    /// `withdraw_to` allow-list are cleared, since they were their own payout
+    /// routing.
+    ///
+    /// # Errors
+    /// - `StreamNotFound`   — no stream exists with `stream_id`.
+    /// - `StreamFinalized`  — the stream has been finalized.
+    /// - `Unauthorized`     — caller is not the stream's recipient.
+    /// - `StreamInactive`   — stream has been cancelled or fully withdrawn.
+    /// - `InvalidRecipient` — `new_recipient` is the current recipient, the
+    ///   sender (a stream to oneself, as `create_stream` rejects), or this
+    ///   contract.
+    pub fn transfer_recipient(
+        env: Env,
+        current_recipient: Address,
+        stream_id: u64,
+        new_recipient: Address,
+    ) -> Result<(), StreamError> {
+        current_recipient.require_auth();
+
+        let mut stream = load_stream(&env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+        if stream.recipient != current_recipient {
+            return Err(StreamError::Unauthorized);
+        }
+        Self::validate_stream_active(&stream)?;
+        if new_recipient == current_recipient
+            || new_recipient == stream.sender
+            || new_recipient == env.current_contract_address()
+        {
+            return Err(StreamError::InvalidRecipient);
+        }
+
+        stream.recipient = new_recipient.clone();
+        save_stream(&env, stream_id, &stream);
+        save_tithe(&env, stream_id, None);
+        save_allowed_destinations(&env, stream_id, &Vec::new(&env));
+        if stream.is_public {
+            reindex_recipient_stream(&env, &current_recipient, &new_recipient, stream_id);
+        } else {
+            reindex_private_stream(&env, &current_recipient, &new_recipient, stream_id);
+        }
+
+        env.events().publish(
+            (Symbol::new(&env, "recipient_transferred"), stream_id),
+            RecipientTransferredEvent {
+                stream_id,
+                old_recipient: current_recipient,
+                new_recipient,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Acknowledge and credit all parked top-ups to the stream.
+    ///
+    /// Only the stream's recipient may acknowledge. Emits `stream_topped_up`
+    /// exactly as a direct top-up would. Returns the credited amount.
+    ///
+    /// # Errors
+    /// - `StreamNotFound`    — no stream exists with `stream_id`.
+    /// - `Unauthorized`      — caller is not the stream's recipient.
+    /// - `StreamInactive`    — stream has been cancelled or fully withdrawn.
+    /// - `StreamWindingDown` — stream is winding down gracefully.
+    /// - `InvalidAmount`     — nothing is parked on the stream.
+    pub fn ack_topup(env: Env, recipient: Address, stream_id: u64) -> Result<i128, StreamError> {
+        recipient.require_auth();
+
+        let mut stream = load_stream(&env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+        if stream.recipient != recipient {
+            return Err(StreamError::Unauthorized);
+        }
+        Self::validate_stream_active(&stream)?;
+        Self::validate_stream_controllable(&stream)?;
+
+        let pending = load_pending_topup(&env, stream_id);
+        if pending <= 0 {
+            return Err(StreamError::InvalidAmount);
+        }
+
+        save_pending_topup(&env, stream_id, 0);
+        Self::credit_deposit(&mut stream, pending);
+        save_stream(&env, stream_id, &stream);
+
+        env.events().publish(
+            (Symbol::new(&env, "stream_topped_up"), stream_id),
+            StreamToppedUpEvent {
+                stream_id,
+                sender: stream.sender,
+                amount: pending,
+                new_deposited_amount: stream.deposited_amount,
+                new_end_time: stream.end_time,
+            },
+        );
+
+        Ok(pending)
+    }
+
+    /// Return a parked, unacknowledged top-up to the sender.
+    ///
+    /// Works whatever the stream's state, so parked funds can never be stranded
+    /// by a cancellation or completion. Returns the refunded amount.
+    ///
+    /// # Errors
+    /// - `StreamNotFound` — no stream exists with `stream_id`.
+    /// - `Unauthorized`   — caller is not the stream's sender.
+    /// - `InvalidAmount`  — nothing is parked on the stream.
+    pub fn reclaim_topup(env: Env, sender: Address, stream_id: u64) -> Result<i128, StreamError> {
+        Self::non_reentrant(&env, || {
+            sender.require_auth();
+
+            let stream = load_stream(&env, stream_id)?;
+            Self::validate_not_finalized(&stream)?;
+            Self::validate_stream_ownership(&stream, &sender)?;
+
+            let pending = load_pending_topup(&env, stream_id);
+            if pending <= 0 {
+                return Err(StreamError::InvalidAmount);
+            }
+
+            let refund =
+                Self::scale_payout(pending, Self::payout_scale(&env, &stream.token_address));
+            save_pending_topup(&env, stream_id, 0);
+            add_token_locked(&env, &stream.token_address, -pending);
+
+            let token_client = token::Client::new(&env, &stream.token_address);
+            token_client.transfer(&env.current_contract_address(), &sender, &refund);
+
+            env.events().publish(
+                (Symbol::new(&env, "topup_reclaimed"), stream_id),
+                TopUpReclaimedEvent {
+                    stream_id,
+                    sender,
+                    amount: pending,
+                },
+            );
+
+            Ok(pending)
+        })
+    }
+
+    /// Returns the net top-up amount parked on a stream awaiting acknowledgment.
+    pub fn get_pending_topup(env: Env, stream_id: u64) -> i128 {
+        load_pending_topup(&env, stream_id)
+    }
+
+    // ─── Internal Helpers ─────────────────────────────────────────────────────
+
+    /// Adds `amount` to the token's total locked, unless that would exceed the
+    /// admin-configured exposure cap.
+    ///
+    /// # Errors
+    /// - `ExposureCapReached` — new total locked would exceed the cap.
+    fn lock_within_exposure_cap(
+        env: &Env,
+        token: &Address,
+        amount: i128,
+    ) -> Result<(), StreamError> {
+        let cap = load_max_exposure(env, token);
+        if cap > 0 {
+            let locked = load_token_totals(env, token).total_locked;
+            if locked.saturating_add(amount) > cap {
+                return Err(StreamError::ExposureCapReached);
+            }
+        }
+        add_token_locked(env, token, amount);
+        Ok(())
+    }
+
+    /// Persists a token fee override and emits `token_fee_updated`.
+    fn store_token_fee_bps(env: &Env, admin: Address, token: Address, fee_bps: Option<u32>) {
+        save_token_fee_bps(env, &token, fee_bps);
+        env.events().publish(
+            (Symbol::new(env, "token_fee_updated"),),
+            TokenFeeUpdatedEvent {
+                admin,
+                token,
+                fee_bps,
+            },
+        );
+    }
+
+    /// Merges two ascending id lists into one ascending list without duplicates.
+    fn merge_sorted_unique(env: &Env, a: &Vec<u64>, b: &Vec<u64>) -> Vec<u64> {
+        let mut merged: Vec<u64> = Vec::new(env);
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            let next = match (a.get(i), b.get(j)) {
+                (Some(x), Some(y)) if x <= y => {
+                    i += 1;
+                    if x == y {
+                        j += 1;
+                    }
+                    x
+                }
+                (Some(_), Some(y)) | (None, Some(y)) => {
+                    j += 1;
+                    y
+                }
+                (Some(x), None) => {
+                    i += 1;
+                    x
+                }
+                (None, None) => break,
+            };
+            if merged.last() != Some(next) {
+                merged.push_back(next);
+            }
+        }
+        merged
+    }
+
+    /// Classifies a stream for `get_streams_needing_attention`.
+    fn attention_reason(env: &Env, stream: &Stream, now: u64) -> Option<AttentionReason> {
+        if stream.is_active {
+            let vested = Self::calculate_vested(env, stream, now);
+            if vested >= stream.deposited_amount {
+                return Some(AttentionReason::CompletedNotClosed);
+            }
+            let runway = (stream.deposited_amount - vested) / stream.rate_per_second.max(1);
+            if !stream.paused && runway < NEAR_DEPLETION_SECS {
+                return Some(AttentionReason::NearDepleted);
+            }
+        }
+        if now.saturating_sub(stream.last_update_time) >= TTL_STALE_SECS {
+            return Some(AttentionReason::LowTtl);
+        }
+        None
+    }
+
+    /// Requires `admin` auth and checks it against the stored protocol admin.
+    ///
+    /// # Errors
+    /// - `NotInitialized` — `initialize` has not been called.
+    /// - `NotAdmin`       — `admin` is not the current admin.
+    fn require_admin(env: &Env, admin: &Address) -> Result<ProtocolConfig, StreamError> {
+        admin.require_auth();
+
+        let config = load_config(env)?;
+        if config.admin != *admin {
+            return Err(StreamError::NotAdmin);
+        }
+        Ok(config)
+    }
+
+    /// Ensures the supplied token address implements the Soroban token interface.
+    fn validate_token_contract(env: &Env, token_address: &Address) -> Result<(), StreamError> {
+        match env.try_invoke_contract::<u32, InvokeError>(
+            token_address,
+            &Symbol::new(env, "decimals"),
+            vec![env],
+        ) {
+            Ok(Ok(_)) => Ok(()),
+            _ => Err(StreamError::InvalidTokenAddress),
+        }
+    }
+
+    /// Asks a stream's withdraw gate whether `amount` may be released.
+    ///
+    /// The gate must expose
+    /// `can_release(stream_id: u64, recipient: Address, amount: i128) -> bool`.
+    /// The call is made with `try_invoke_contract`, so a gate that traps or
+    /// returns the wrong type never aborts the transaction; it simply counts
+    /// as a rejection.
+    fn gate_allows_release(
+        env: &Env,
+        gate: &Address,
+        stream_id: u64,
+        recipient: &Address,
+        amount: i128,
+    ) -> bool {
+        matches!(
+            env.try_invoke_contract::<bool, InvokeError>(
+                gate,
+                &Symbol::new(env, "can_release"),
+                vec![
+                    env,
+                    stream_id.into_val(env),
+                    recipient.into_val(env),
+                    amount.into_val(env),
+                ],
+            ),
+            Ok(Ok(true))
+        )
+    }
+
+    /// Returns the timestamp up to which a stream has accrued at `now`.
+    ///
+    /// When the stream is paused, accrual stops at `paused_at`.
+    fn accrual_frontier(stream: &Stream, now: u64) -> u64 {
+        if stream.paused {
+            stream.paused_at.unwrap_or(stream.last_update_time)
+        } else {
+            now
+        }
+    }
+
+    /// Seconds accrued since `last_update_time`, or `None` if the frontier lies
+    /// before the checkpoint.
+    ///
+    /// Ledger time never runs backwards on-chain, but tests (and any future
+    /// checkpoint bug) can produce `now < last_update_time`. Every accrual path
+    /// goes through here so such a state skips accrual instead of underflowing.
+    fn elapsed_since_checkpoint(stream: &Stream, now: u64) -> Option<u64> {
+        Self::accrual_frontier(stream, now).checked_sub(stream.last_update_time)
+    }
+
+    /// Calculate the cumulative vested amount for a stream at a given timestamp.
+    ///
+    /// Computed as `accrued_checkpoint` plus the delta accrued since
+    /// `last_update_time`, capped at `deposited_amount`.
+    ///
+    /// # Overflow Protection
+    /// - Caps `elapsed` at the seconds left until the projected end, so for
+    ///   streams untouched for years the product stays near the deposit
+    /// - Uses `checked_mul` for rate_per_second * elapsed_seconds multiplication
+    /// - Uses `checked_add` when adding the delta to the checkpoint
+    /// - Caps at the deposited amount if either would overflow
+    fn calculate_vested(env: &Env, stream: &Stream, now: u64) -> i128 {
+        if let Some(milestones) = &stream.milestones {
+            return Self::milestone_vested(env, stream, milestones);
+        }
+        let cap = stream.deposited_amount;
+        let until_end = Self::projected_end_time(stream).saturating_sub(stream.last_update_time);
+        let elapsed = Self::elapsed_since_checkpoint(stream, now).unwrap_or(0);
+        // Reaching the end releases the truncated-rate remainder as well.
+        if elapsed >= until_end {
+            return cap;
+        }
+
+        match (elapsed as i128)
+            .checked_mul(stream.rate_per_second)
+            .and_then(|delta| stream.accrued_checkpoint.checked_add(delta))
+        {
+            Some(vested) => vested.min(cap),
+            None => cap,
+        }
+    }
+
+    /// Vested amount of a milestone stream at the current ledger sequence.
+    ///
+    /// Releases the cumulative amount of the last milestone reached; the final
+    /// milestone releases the whole deposit, so top-ups vest there. A paused
+    /// stream holds at its checkpoint, and milestones passed while paused
+    /// release on resume.
+    fn milestone_vested(env: &Env, stream: &Stream, milestones: &Vec<(u32, i128)>) -> i128 {
+        if stream.paused {
+            return stream.accrued_checkpoint;
+        }
+        let sequence = env.ledger().sequence();
+        let last = milestones.len().saturating_sub(1);
+        let mut released = 0;
+        for (i, (at, cumulative)) in milestones.iter().enumerate() {
+            if sequence < at {
+                break;
+            }
+            released = if i as u32 == last {
+                stream.deposited_amount
+            } else {
+                cumulative
+            };
+        }
+        released
+            .max(stream.accrued_checkpoint)
+            .min(stream.deposited_amount)
+    }
+
+    /// Returns the timestamp at which the stream becomes fully vested: its
+    /// nominal `end_time`, or earlier if accruing at `rate_per_second` from
+    /// the last checkpoint covers the deposit first.
+    fn projected_end_time(stream: &Stream) -> u64 {
+        let remaining = stream
+            .deposited_amount
+            .saturating_sub(stream.accrued_checkpoint)
+            .max(0);
+        Self::end_time_from(stream.last_update_time, remaining, stream.rate_per_second)
+            .min(stream.end_time)
+    }
+
+    /// Timestamp at which the stream becomes fully vested: `projected_end_time`
+    /// for time-based streams, or the estimated time the final milestone's
+    /// ledger sequence is reached.
+    fn vesting_end_time(env: &Env, stream: &Stream) -> u64 {
+        match stream.milestones.as_ref().and_then(|m| m.last()) {
+            Some((sequence, _)) => {
+                Self::estimated_time_at_sequence(env, sequence).max(stream.start_time)
+            }
+            None => Self::projected_end_time(stream),
+        }
+    }
+
+    /// Estimated timestamp of ledger `sequence`, extrapolated from the current
+    /// ledger at `SECS_PER_LEDGER`; sequences already passed map to the past.
+    fn estimated_time_at_sequence(env: &Env, sequence: u32) -> u64 {
+        let ledgers = i128::from(sequence) - i128::from(env.ledger().sequence());
+        let t = i128::from(env.ledger().timestamp()) + ledgers * SECS_PER_LEDGER;
+        u64::try_from(t.max(0)).unwrap_or(u64::MAX)
+    }
+
+    /// `(ledger sequence, cumulative released)` at each milestone; the final
+    /// step releases the whole deposit, top-ups included.
+    fn milestone_steps(
+        env: &Env,
+        stream: &Stream,
+        milestones: &Vec<(u32, i128)>,
+    ) -> Vec<(u32, i128)> {
+        let mut steps = Vec::new(env);
+        let last = milestones.len().saturating_sub(1);
+        for (i, (sequence, cumulative)) in milestones.iter().enumerate() {
+            let released = if i as u32 == last {
+                stream.deposited_amount
+            } else {
+                cumulative
+            };
+            steps.push_back((sequence, released));
+        }
+        steps
+    }
+
+    /// Adds `net_amount` to the deposit and pushes the nominal end back by
+    /// the seconds it takes to stream at the current rate.
+    fn credit_deposit(stream: &mut Stream, net_amount: i128) {
+        stream.deposited_amount += net_amount;
+        stream.end_time = Self::end_time_from(stream.end_time, net_amount, stream.rate_per_second);
+    }
+
+    /// Timestamp at which `remaining` tokens are fully vested from `from`,
+    /// rounding partial seconds up.
+    fn end_time_from(from: u64, remaining: i128, rate_per_second: i128) -> u64 {
+        // rate_per_second is guaranteed >= 1 due to create_stream's InvalidRate guard
+        let rate = rate_per_second.max(1);
+        // Divide first: `remaining + rate - 1` overflows for near-max rates.
+        let seconds = remaining / rate + i128::from(remaining % rate > 0);
+        from.saturating_add(u64::try_from(seconds).unwrap_or(u64::MAX))
+    }
+
+    /// Vested amount at `t` for charting purposes.
+    ///
+    /// Before the last checkpoint the value is interpolated linearly between
+    /// `(start_time, initial_unlock)` and `(last_update_time, accrued_checkpoint)`.
+    fn projected_vested(env: &Env, stream: &Stream, t: u64) -> i128 {
+        if t >= stream.last_update_time {
+            return Self::calculate_vested(env, stream, t);
+        }
+        let window = stream.last_update_time.saturating_sub(stream.start_time) as i128;
+        let elapsed = t.saturating_sub(stream.start_time) as i128;
+        if window == 0 {
+            return stream.accrued_checkpoint;
+        }
+        let accrued = stream.accrued_checkpoint - stream.initial_unlock;
+        stream.initial_unlock + accrued.checked_mul(elapsed).map_or(accrued, |v| v / window)
+    }
+
+    /// `projected_vested`, but 0 before `cliff_time`: the amount released
+    /// to the recipient at `t`.
+    fn projected_released(env: &Env, stream: &Stream, t: u64) -> i128 {
+        if t < stream.cliff_time {
+            return 0;
+        }
+        Self::projected_vested(env, stream, t)
+    }
+
+    /// Calculate the claimable amount for a stream at a given timestamp.
+    ///
+    /// Excludes any time the stream was paused, and is 0 before `cliff_time`.
+    /// Clamped to 0: `withdrawn_amount` should never exceed the vested amount
+    /// in normal flow, but guard defensively so the function never returns
+    /// negative.
+    fn calculate_claimable(env: &Env, stream: &Stream, now: u64) -> i128 {
+        if now < stream.cliff_time {
+            return 0;
+        }
+        Self::calculate_vested(env, stream, now)
+            .saturating_sub(stream.withdrawn_amount)
+            .max(0)
+    }
+
+    /// Checkpoint accrual: fold everything vested up to `now` into
+    /// `accrued_checkpoint` and advance `last_update_time` to the accrual frontier.
+    ///
+    /// Does not move tokens or touch `withdrawn_amount`. A frontier before the
+    /// current checkpoint leaves the stream untouched so time never rewinds.
+    fn checkpoint_accrual(env: &Env, stream: &mut Stream, now: u64) {
+        if Self::elapsed_since_checkpoint(stream, now).is_none() {
+            return;
+        }
+        stream.accrued_checkpoint = Self::calculate_vested(env, stream, now);
+        stream.last_update_time = Self::accrual_frontier(stream, now);
+    }
+
+    /// Validate that a stream exists and is owned by the caller.
+    ///
+    /// # Errors
+    /// - `StreamNotFound` — no stream exists with `stream_id`.
+    /// - `Unauthorized` — caller is not the stream's sender.
+    fn validate_stream_ownership(stream: &Stream, caller: &Address) -> Result<(), StreamError> {
+        if stream.sender != *caller {
+            return Err(StreamError::Unauthorized);
+        }
+        Ok(())
+    }
+
+    /// Validate that a stream has not been finalized.
+    ///
+    /// # Errors
+    /// - `StreamFinalized` — the stream is an immutable record.
+    fn validate_not_finalized(stream: &Stream) -> Result<(), StreamError> {
+        if stream.finalized {
+            return Err(StreamError::StreamFinalized);
+        }
+        Ok(())
+    }
+
+    /// Validate that a stream is active.
+    ///
+    /// # Errors
+    /// - `StreamInactive` — stream has been cancelled or fully withdrawn.
+    fn validate_stream_active(stream: &Stream) -> Result<(), StreamError> {
+        if !stream.is_active {
+            return Err(StreamError::StreamInactive);
+        }
+        Ok(())
+    }
+
+    /// Returns the `(contract_balance, total_locked)` pair used to scale payouts
+    /// of a rebasing token, or `None` when payouts are 1:1.
+    ///
+    /// Must be read before the payout is deducted from the locked total.
+    fn payout_scale(env: &Env, token_address: &Address) -> Option<(i128, i128)> {
+        if load_asset_kind(env, token_address) != StreamAssetKind::Rebasing {
+            return None;
+        }
+        let locked = load_token_totals(env, token_address).total_locked;
+        if locked <= 0 {
+            return None;
+        }
+        let balance =
+            token::Client::new(env, token_address).balance(&env.current_contract_address());
+        Some((balance, locked))
+    }
+
+    /// Converts a nominal payout into the token amount to transfer.
+    ///
+    /// Falls back to the nominal amount if the scaled product would overflow.
+    fn scale_payout(amount: i128, scale: Option<(i128, i128)>) -> i128 {
+        match scale {
+            Some((balance, locked)) => amount
+                .checked_mul(balance)
+                .map_or(amount, |scaled| scaled / locked),
+            None => amount,
+        }
+    }
+
+    /// Validate that the sender may still change a stream's terms.
+    ///
+    /// # Errors
+    /// - `StreamWindingDown` — a graceful wind-down has been requested.
+    fn validate_stream_controllable(stream: &Stream) -> Result<(), StreamError> {
+        if stream.winding_down {
+            return Err(StreamError::StreamWindingDown);
+        }
+        Ok(())
+    }
+
+    /// Adds `amount` to the recipient's lifetime earnings in `token` and emits
+    /// `recipient_earnings` if a threshold multiple was crossed.
+    fn record_recipient_earnings(env: &Env, recipient: &Address, token: &Address, amount: i128) {
+        let before = load_recipient_earnings(env, recipient, token);
+        let after = before.saturating_add(amount);
+        save_recipient_earnings(env, recipient, token, after);
+
+        let threshold = load_earnings_threshold(env, token);
+        if threshold > 0 && after / threshold > before / threshold {
+            env.events().publish(
+                (Symbol::new(env, "recipient_earnings"), recipient.clone()),
+                RecipientEarningsEvent {
+                    recipient: recipient.clone(),
+                    token: token.clone(),
+                    lifetime_withdrawn: after,
+                    threshold_crossed: after / threshold * threshold,
+                },
+            );
+        }
+    }
+
+    /// Adds `amount` to `token`'s circuit-breaker window and engages the global
+    /// pause if the window's volume exceeds the configured limit.
+    ///
+    /// A window opens at the first withdrawal after the previous one expired.
+    /// Tripping starts a fresh window so a reset does not immediately re-trip.
+    fn record_withdrawal_volume(env: &Env, token: &Address, amount: i128, now: u64) {
+        let Some(config) = load_circuit_breaker(env, token) else {
+            return;
+        };
+
+        let mut window = match load_withdrawal_window(env, token) {
+            Some(window) if now < window.window_start.saturating_add(config.window_secs) => window,
+            _ => WithdrawalWindow {
+                window_start: now,
+                volume: 0,
+            },
+        };
+        window.volume = window.volume.saturating_add(amount);
+
+        if window.volume > config.max_volume {
+            set_globally_paused(env, true);
+            env.events().publish(
+                (Symbol::new(env, "circuit_breaker_tripped"), token.clone()),
+                CircuitBreakerTrippedEvent {
+                    token: token.clone(),
+                    volume: window.volume,
+                    max_volume: config.max_volume,
+                    window_start: window.window_start,
+                },
+            );
+            window = WithdrawalWindow {
+                window_start: now,
+                volume: 0,
+            };
+        }
+        save_withdrawal_window(env, token, &window);
+    }
+
+    /// Apply a withdrawal: update stream state, persist it, then transfer tokens.
+    ///
+    /// If the recipient configured a tithe, its share of the payout goes to the
+    /// tithe address and the rest to the recipient.
+    ///
+    /// Follows the Checks-Effects-Interactions (CEI) pattern: all state mutations
+    /// and the storage write complete before the external token transfer fires.
+    /// A re-entrant call via a malicious token hook therefore sees the already-updated
+    /// withdrawn_amount in storage and cannot trigger a double payout.
+    fn apply_withdrawal(
+        env: &Env,
+        stream: &mut Stream,
+        stream_id: u64,
+        recipient: &Address,
+        destination: &Address,
+        amount: i128,
+        now: u64,
+    ) {
+        let payout = Self::scale_payout(amount, Self::payout_scale(env, &stream.token_address));
+
+        // Effects: update stream state
+        Self::checkpoint_accrual(env, stream, now);
+        stream.withdrawn_amount += amount;
+
+        if stream.withdrawn_amount >= stream.deposited_amount {
+            stream.is_active = false;
+            stream.status = StreamStatus::Completed;
+            decrement_active_streams(env);
+        }
+        add_token_locked(env, &stream.token_address, -amount);
+        Self::record_recipient_earnings(env, recipient, &stream.token_address, amount);
+        Self::record_withdrawal_volume(env, &stream.token_address, amount, now);
+
+        // Persist state before any external call (CEI)
+        save_stream(env, stream_id, stream);
+
+        // Interaction: transfer tokens only after state is committed to storage
+        let token_client = token::Client::new(env, &stream.token_address);
+        let contract_address = env.current_contract_address();
+        let mut payout = payout;
+        if let Some(tithe) = load_tithe(env, stream_id) {
+            let share = Self::bps_share(payout, tithe.bps);
+            if share > 0 {
+                token_client.transfer(&contract_address, &tithe.address, &share);
+                payout -= share;
+            }
+        }
+        token_client.transfer(&contract_address, destination, &payout);
+    }
+
+    /// Withdraw all currently claimable tokens from a stream.
+    ///
+    /// Only the stream's recipient may call this. The amount withdrawn is calculated
+    /// based on elapsed time and the stream's rate. The stream is automatically marked
+    /// inactive once fully drained.
+    ///
+    /// # Errors
+    /// - `StreamNotFound`  — no stream exists with `stream_id`.
+    /// - `Unauthorized`    — caller is not the stream's recipient.
+    /// - `StreamInactive`  — stream is already inactive.
+    /// - `InvalidAmount`   — no claimable balance (fully withdrawn already).
+    /// - `TooSoon`         — the stream's `first_withdraw_delay` has not elapsed.
+    /// - `GateRejected`    — the stream's withdraw gate refused or failed.
+    /// - `ProtocolPaused`  — the circuit breaker has paused the protocol.
+    /// - `ContractPaused`  — the admin has paused the contract.
+    pub fn withdraw(env: Env, recipient: Address, stream_id: u64) -> Result<i128, StreamError> {
+        recipient.require_auth();
+        Self::withdraw_internal(&env, recipient.clone(), recipient, stream_id, 0, None, None)
+    }
+
+    /// Withdraw all currently claimable tokens to `destination` instead of
+    /// the recipient's own address, e.g. a custodial or cold wallet.
+    ///
+    /// Otherwise identical to `withdraw`: only the recipient may call it, and
+    /// lifetime earnings and the event are still attributed to the recipient.
+    /// If the recipient has set an allow-list with `set_allowed_destinations`,
+    /// `destination` must be on it.
+    ///
+    /// # Errors
+    /// Same as `withdraw`, plus:
+    /// - `InvalidRecipient`      — `destination` is this contract.
+    /// - `DestinationNotAllowed` — `destination` is not on the allow-list.
+    pub fn withdraw_to(
+        env: Env,
+        recipient: Address,
+        stream_id: u64,
+        destination: Address,
+    ) -> Result<i128, StreamError> {
+        recipient.require_auth();
+
+        if destination == env.current_contract_address() {
+            return Err(StreamError::InvalidRecipient);
+        }
+        let allowed = load_allowed_destinations(&env, stream_id);
+        if !allowed.is_empty() && !allowed.contains(&destination) {
+            return Err(StreamError::DestinationNotAllowed);
+        }
+        Self::withdraw_internal(&env, recipient, destination, stream_id, 0, None, None)
+    }
+
+    /// Withdraw all currently claimable tokens, tagging the event with `memo`.
+    ///
+    /// Identical to `withdraw` except that `memo` is recorded in the emitted
+    /// `TokensWithdrawnEvent` for off-chain reconciliation.
+    ///
+    /// # Errors
+    /// Same as `withdraw`.
+    pub fn withdraw_with_memo(
+        env: Env,
+        recipient: Address,
+        stream_id: u64,
+        memo: Symbol,
+    ) -> Result<i128, StreamError> {
+        recipient.require_auth();
+        Self::withdraw_internal(
+            &env,
+            recipient.clone(),
+            recipient,
+            stream_id,
+            0,
+            None,
+            Some(memo),
+        )
+    }
+
+    /// Push all currently claimable tokens to the recipient on the sender's behalf.
+    ///
+    /// Lets employers pay out without waiting for the recipient to pull. Funds
+    /// always go to the stream's recipient; the sender cannot redirect them.
+    /// Emits the same `tokens_withdrawn` event as `withdraw`.
+    ///
+    /// # Errors
+    /// Same as `withdraw`, except `Unauthorized` means the caller is not the
+    /// stream's sender.
+    pub fn claim_on_behalf(env: Env, sender: Address, stream_id: u64) -> Result<i128, StreamError> {
+        sender.require_auth();
+
+        let stream = load_stream(&env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+        Self::validate_stream_ownership(&stream, &sender)?;
+
+        Self::withdraw_internal(
+            &env,
+            stream.recipient.clone(),
+            stream.recipient,
+            stream_id,
+            0,
+            None,
+            None,
+        )
+    }
+
+    /// Withdraw from every stream in a bundle in one call.
+    ///
+    /// Returns the amount withdrawn per stream, in bundle order. Streams with
+    /// nothing claimable or that have already closed contribute 0 instead of
+    /// failing the call; any other error aborts it.
+    ///
+    /// # Errors
+    /// - `StreamNotFound` — `bundle_id` does not exist.
+    /// - Otherwise the same as `withdraw` for each stream.
+    pub fn withdraw_bundle(
+        env: Env,
+        recipient: Address,
+        bundle_id: u64,
+    ) -> Result<Vec<i128>, StreamError> {
+        recipient.require_auth();
+
+        let stream_ids = load_bundle(&env, bundle_id);
+        if stream_ids.is_empty() {
+            return Err(StreamError::StreamNotFound);
+        }
+
+        let mut amounts = Vec::new(&env);
+        for stream_id in stream_ids.iter() {
+            let amount = match Self::withdraw_internal(
+                &env,
+                recipient.clone(),
+                recipient.clone(),
+                stream_id,
+                0,
+                None,
+                None,
+            ) {
+                Ok(amount) => amount,
+                Err(StreamError::InvalidAmount | StreamError::StreamInactive) => 0,
+                Err(e) => return Err(e),
+            };
+            amounts.push_back(amount);
+        }
+        Ok(amounts)
+    }
+
+    /// Withdraw exactly `amount` of the currently claimable tokens.
+    ///
+    /// The rest stays in the stream for a later withdrawal. `withdraw` remains
+    /// the convenience form that drains everything claimable.
+    ///
+    /// # Errors
+    /// Same as `withdraw`, plus:
+    /// - `InvalidAmount` — `amount` is non-positive or exceeds the claimable balance.
+    pub fn withdraw_amount(
+        env: Env,
+        recipient: Address,
+        stream_id: u64,
+        amount: i128,
+    ) -> Result<i128, StreamError> {
+        recipient.require_auth();
+
+        if amount <= 0 {
+            return Err(StreamError::InvalidAmount);
+        }
+
+        Self::withdraw_internal(
+            &env,
+            recipient.clone(),
+            recipient,
+            stream_id,
+            0,
+            Some(amount),
+            None,
+        )
+    }
+
+    /// Withdraw all currently claimable tokens, but only if at least `min_amount`
+    /// has accrued.
+    ///
+    /// Lets automated recipients avoid spending a transaction on a tiny accrual.
+    ///
+    /// # Errors
+    /// Same as `withdraw`, plus:
+    /// - `InvalidAmount` — `min_amount` is negative.
+    /// - `BelowMinimum`  — claimable balance is less than `min_amount`.
+    pub fn withdraw_min(
+        env: Env,
+        recipient: Address,
+        stream_id: u64,
+        min_amount: i128,
+    ) -> Result<i128, StreamError> {
+        recipient.require_auth();
+
+        if min_amount < 0 {
+            return Err(StreamError::InvalidAmount);
+        }
+
+        Self::withdraw_internal(
+            &env,
+            recipient.clone(),
+            recipient,
+            stream_id,
+            min_amount,
+            None,
+            None,
+        )
+    }
+
+    /// Runs `f` under the reentrancy lock.
+    ///
+    /// Every path that transfers tokens goes through this, so a token contract
+    /// that calls back into the stream contract mid-transfer is rejected.
+    fn non_reentrant<T>(
+        env: &Env,
+        f: impl FnOnce() -> Result<T, StreamError>,
+    ) -> Result<T, StreamError> {
+        if is_locked(env) {
+            return Err(StreamError::Reentrancy);
+        }
+        set_locked(env, true);
+        let result = f();
+        set_locked(env, false);
+        result
+    }
+
+    /// Shared implementation of every withdrawal entry point.
+    ///
+    /// The caller must already have required the appropriate auth.
+    fn withdraw_internal(
+        env: &Env,
+        recipient: Address,
+        destination: Address,
+        stream_id: u64,
+        min_amount: i128,
+        exact_amount: Option<i128>,
+        memo: Option<Symbol>,
+    ) -> Result<i128, StreamError> {
+        Self::non_reentrant(env, || {
+            if is_globally_paused(env) {
+                return Err(StreamError::ProtocolPaused);
+            }
+            if is_contract_paused(env) {
+                return Err(StreamError::ContractPaused);
+            }
+            let mut stream = load_stream(env, stream_id)?;
+            Self::validate_not_finalized(&stream)?;
+
+            // Validate recipient authorization
+            if stream.recipient != recipient {
+                return Err(StreamError::Unauthorized);
+            }
+
+            // Validate stream is active and not paused
+            Self::validate_stream_active(&stream)?;
+            if stream.paused {
+                return Err(StreamError::StreamPaused);
+            }
+
+            let now = env.ledger().timestamp();
+            if now
+                < stream
+                    .start_time
+                    .saturating_add(stream.first_withdraw_delay)
+            {
+                return Err(StreamError::TooSoon);
+            }
+
+            let claimable = Self::calculate_claimable(env, &stream, now);
+
+            if claimable < min_amount {
+                return Err(StreamError::BelowMinimum);
+            }
+            if claimable <= 0 {
+                return Err(StreamError::InvalidAmount);
+            }
+
+            let claimable = match exact_amount {
+                Some(amount) if amount <= 0 || amount > claimable => {
+                    return Err(StreamError::InvalidAmount);
+                }
+                // An explicit amount is paid as requested; no dust sweep.
+                Some(amount) => amount,
+                None => {
+                    // Sweep a sub-threshold remainder so the stream closes instead of
+                    // leaving dust that is not worth a further withdrawal.
+                    let remaining = stream.deposited_amount - stream.withdrawn_amount - claimable;
+                    if remaining > 0 && remaining < load_dust_threshold(env, &stream.token_address)
+                    {
+                        claimable + remaining
+                    } else {
+                        claimable
+                    }
+                }
+            };
+
+            if let Some(gate) = &stream.withdraw_gate {
+                if !Self::gate_allows_release(env, gate, stream_id, &recipient, claimable) {
+                    return Err(StreamError::GateRejected);
+                }
+            }
+
+            // Apply withdrawal: updates state, persists to storage, then transfers (CEI)
+            Self::apply_withdrawal(
+                env,
+                &mut stream,
+                stream_id,
+                &recipient,
+                &destination,
+                claimable,
+                now,
+            );
+
+            let completed = stream.status == StreamStatus::Completed;
+            let remaining = if completed {
+                0
+            } else {
+                Self::calculate_claimable(env, &stream, now)
+            };
+
+            env.events().publish(
+                (Symbol::new(env, "tokens_withdrawn"), stream_id),
+                TokensWithdrawnEvent {
+                    stream_id,
+                    recipient: recipient.clone(),
+                    amount: claimable,
+                    timestamp: stream.last_update_time,
+                    memo,
+                    remaining,
+                },
+            );
+
+            // Emit COMPLETED event on final withdrawal
+            if completed {
+                env.events().publish(
+                    (Symbol::new(env, "stream_completed"), stream_id),
+                    StreamCompletedEvent {
+                        stream_id,
+                        recipient,
+                        total_withdrawn: stream.withdrawn_amount,
+                    },
+                );
+            }
+
+            Ok(claimable)
+        })
+    }
+
+    /// Cancel an active stream.
+    ///
+    /// Only the stream's original sender may cancel. The recipient receives all
+    /// accrued tokens up to the cancellation moment, and any remaining unspent
+    /// balance is refunded to the sender.
+    ///
+    /// # Errors
+    /// - `StreamNotFound`  — no stream exists with `stream_id`.
+    /// - `Unauthorized`    — caller is not the stream's sender.
+    /// - `StreamInactive`  — stream is already inactive.
+    /// - `StreamWindingDown` — stream is already winding down gracefully.
+    /// - `InsufficientReserve` — the contract's balance cannot cover payout + refund.
+    /// - `AccountingError` — payout + refund differ from the unpaid balance.
+    pub fn cancel_stream(env: Env, sender: Address, stream_id: u64) -> Result<(), StreamError> {
+        Self::cancel_stream_with_mode(env, sender, stream_id, CancelMode::Immediate)
+    }
+
+    /// Cancel an active stream using the given `CancelMode`.
+    ///
+    /// `Immediate` behaves exactly like `cancel_stream`. `Graceful` performs no
+    /// clawback: the stream keeps paying the recipient until fully drained, but
+    /// stops accepting top-ups, pauses, and further cancellation.
+    ///
+    /// # Errors
+    /// Same as `cancel_stream`, plus:
+    /// - `StreamPaused` — `Graceful` was requested on a paused stream; resume
+    ///   it first so the wind-down can actually pay out.
+    pub fn cancel_stream_with_mode(
+        env: Env,
+        sender: Address,
+        stream_id: u64,
+        mode: CancelMode,
+    ) -> Result<(), StreamError> {
+        sender.require_auth();
+
+        let stream = load_stream(&env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+
+        // Validate ownership and active status
+        Self::validate_stream_ownership(&stream, &sender)?;
+        Self::validate_stream_active(&stream)?;
+        Self::validate_stream_controllable(&stream)?;
+
+        if mode == CancelMode::Graceful {
+            return Self::begin_wind_down(&env, stream, stream_id);
+        }
+
+        Self::cancel_with_refund_bps(&env, stream, stream_id, MAX_BPS, CancellerRole::Sender)
+    }
+
+    /// Cancel a stream on negotiated terms agreed by both parties.
+    ///
+    /// Pays the recipient everything accrued so far, then splits the unvested
+    /// balance: `sender_refund_bps` of it returns to the sender and the rest
+    /// goes to the recipient. `10_000` behaves like `cancel_stream`; `0` hands
+    /// the recipient the whole remaining balance. Requires both auths.
+    ///
+    /// # Errors
+    /// Same as `cancel_stream`, plus:
+    /// - `Unauthorized`  — `recipient` is not the stream's recipient.
+    /// - `InvalidAmount` — `sender_refund_bps` exceeds 10 000.
+    pub fn cancel_with_split(
+        env: Env,
+        sender: Address,
+        recipient: Address,
+        stream_id: u64,
+        sender_refund_bps: u32,
+    ) -> Result<(), StreamError> {
+        sender.require_auth();
+        recipient.require_auth();
+
+        if sender_refund_bps > MAX_BPS {
+            return Err(StreamError::InvalidAmount);
+        }
+
+        let stream = load_stream(&env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+        Self::validate_stream_ownership(&stream, &sender)?;
+        if stream.recipient != recipient {
+            return Err(StreamError::Unauthorized);
+        }
+        Self::validate_stream_active(&stream)?;
+        Self::validate_stream_controllable(&stream)?;
+
+        Self::cancel_with_refund_bps(
+            &env,
+            stream,
+            stream_id,
+            sender_refund_bps,
+            CancellerRole::Mutual,
+        )
+    }
+
+    /// Settle and close a stream, callable by either party.
+    ///
+    /// Once the stream has fully vested, the sender or the recipient may close
+    /// it: the remaining balance is paid to the recipient and the stream is
+    /// marked `Completed`. That payout is a withdrawal, subject to every check
+    /// `withdraw` applies and emitting the same events. Before that only the
+    /// sender may close, which settles like `cancel_stream` (accrued to the
+    /// recipient, unvested back to the sender).
+    ///
+    /// # Errors
+    /// - `StreamNotFound`  — no stream exists with `stream_id`.
+    /// - `StreamFinalized` — the stream has been finalized.
+    /// - `StreamInactive`  — stream is already inactive.
+    /// - `Unauthorized`    — caller is neither the sender nor the recipient.
+    /// - `TooSoon`         — the recipient closed before the stream fully vested,
+    ///   or the stream's `first_withdraw_delay` has not elapsed.
+    /// - Otherwise the same as `withdraw` for a vested close, or as
+    ///   `cancel_stream` for an early close by the sender.
+    pub fn close_stream(env: Env, caller: Address, stream_id: u64) -> Result<(), StreamError> {
+        caller.require_auth();
+
+        let stream = load_stream(&env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+        Self::validate_stream_active(&stream)?;
+        if caller != stream.sender && caller != stream.recipient {
+            return Err(StreamError::Unauthorized);
+        }
+
+        let now = env.ledger().timestamp();
+        let unpaid = stream.deposited_amount - stream.withdrawn_amount;
+        let mut recipient_payout = Self::calculate_claimable(&env, &stream, now).max(0);
+        let refunded_amount = unpaid - recipient_payout;
+
+        if refunded_amount > 0 {
+            if caller != stream.sender {
+                return Err(StreamError::TooSoon);
+            }
+            Self::validate_stream_controllable(&stream)?;
+            Self::cancel_with_refund_bps(&env, stream, stream_id, MAX_BPS, CancellerRole::Sender)?;
+        } else {
+            let recipient = stream.recipient;
+            recipient_payout = Self::withdraw_internal(
+                &env,
+                recipient.clone(),
+                recipient,
+                stream_id,
+                0,
+                None,
+                None,
+            )?;
+        }
+
+        env.events().publish(
+            (Symbol::new(&env, "stream_closed"), stream_id),
+            StreamClosedEvent {
+                stream_id,
+                closed_by: caller,
+                recipient_payout,
+                refunded_amount,
+                closed_at: now,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Permissionless cleanup: close an active stream whose token has been
+    /// marked defunct, without attempting any transfer.
+    ///
+    /// The unpaid balance, including any parked top-up, is written out of the
+    /// token's locked total and the active count, so indexes and stats stay
+    /// clean. The stream is marked `Cancelled` and can then be finalized.
+    ///
+    /// # Errors
+    /// - `StreamNotFound`  — no stream exists with `stream_id`.
+    /// - `StreamFinalized` — the stream has been finalized.
+    /// - `StreamInactive`  — stream is already closed.
+    /// - `TokenNotDefunct` — the stream's token has not been marked defunct.
+    pub fn force_close_defunct(env: Env, stream_id: u64) -> Result<(), StreamError> {
+        let mut stream = load_stream(&env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+        Self::validate_stream_active(&stream)?;
+        if !is_token_defunct(&env, &stream.token_address) {
+            return Err(StreamError::TokenNotDefunct);
+        }
+
+        let now = env.ledger().timestamp();
+        let pending = load_pending_topup(&env, stream_id);
+        let unpaid_amount = stream
+            .deposited_amount
+            .saturating_sub(stream.withdrawn_amount)
+            .max(0)
+            + pending;
+
+        stream.is_active = false;
+        stream.status = StreamStatus::Cancelled;
+        stream.last_update_time = now;
+        save_pending_topup(&env, stream_id, 0);
+        decrement_active_streams(&env);
+        add_token_locked(&env, &stream.token_address, -unpaid_amount);
+        save_stream(&env, stream_id, &stream);
+
+        env.events().publish(
+            (Symbol::new(&env, "force_closed"), stream_id),
+            ForceClosedEvent {
+                stream_id,
+                token: stream.token_address,
+                unpaid_amount,
+                closed_at: now,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Shared immediate-cancel path.
+    ///
+    /// Settles accrual to the recipient, returns `sender_refund_bps` of the
+    /// unvested balance to the sender and the remainder to the recipient, then
+    /// closes the stream, tagging the event with `cancelled_by`. State is
+    /// persisted before any transfer (CEI).
+    fn cancel_with_refund_bps(
+        env: &Env,
+        mut stream: Stream,
+        stream_id: u64,
+        sender_refund_bps: u32,
+        cancelled_by: CancellerRole,
+    ) -> Result<(), StreamError> {
+        Self::non_reentrant(env, || {
+            let now = env.ledger().timestamp();
+            let accrued_amount = Self::calculate_claimable(env, &stream, now);
+            let previously_withdrawn = stream.withdrawn_amount;
+
+            // Effects: update all stream state before any external call
+            Self::checkpoint_accrual(env, &mut stream, now);
+            if accrued_amount > 0 {
+                stream.withdrawn_amount = stream.withdrawn_amount.saturating_add(accrued_amount);
+            }
+
+            let unvested = stream
+                .deposited_amount
+                .saturating_sub(stream.withdrawn_amount)
+                .max(0);
+            let refunded_amount = Self::bps_share(unvested, sender_refund_bps);
+            let recipient_share = unvested - refunded_amount;
+            stream.withdrawn_amount = stream.withdrawn_amount.saturating_add(recipient_share);
+            let recipient_payout = accrued_amount.max(0) + recipient_share;
+
+            // Conservation: the two payouts must cover exactly what is still unpaid.
+            if recipient_payout + refunded_amount != stream.deposited_amount - previously_withdrawn
+            {
+                return Err(StreamError::AccountingError);
+            }
+
+            stream.is_active = false;
+            stream.status = StreamStatus::Cancelled;
+            stream.last_update_time = now;
+
+            let sender = stream.sender.clone();
+            let recipient = stream.recipient.clone();
+            let amount_withdrawn = stream.withdrawn_amount;
+
+            let scale = Self::payout_scale(env, &stream.token_address);
+            let payout = Self::scale_payout(recipient_payout, scale);
+            let refund = Self::scale_payout(refunded_amount, scale);
+
+            // Reserve check: never pay out more than the contract actually holds,
+            // so accounting drift surfaces as an error instead of a failed transfer
+            // halfway through the payouts.
+            let token_client = token::Client::new(env, &stream.token_address);
+            let contract_address = env.current_contract_address();
+            if payout.saturating_add(refund) > token_client.balance(&contract_address) {
+                return Err(StreamError::InsufficientReserve);
+            }
+
+            decrement_active_streams(env);
+            add_token_locked(
+                env,
+                &stream.token_address,
+                -(recipient_payout + refunded_amount),
+            );
+            if recipient_payout > 0 {
+                Self::record_recipient_earnings(
+                    env,
+                    &recipient,
+                    &stream.token_address,
+                    recipient_payout,
+                );
+            }
+
+            // Persist state before any external calls (CEI)
+            save_stream(env, stream_id, &stream);
+
+            // Interactions: token transfers after state is committed to storage
+            if payout > 0 {
+                token_client.transfer(&contract_address, &recipient, &payout);
+            }
+
+            if refund > 0 {
+                token_client.transfer(&contract_address, &sender, &refund);
+            }
+
+            // Emit cancellation event
+            env.events().publish(
+                (
+                    Symbol::new(env, "stream_cancelled"),
+                    stream_id,
+                    cancelled_by,
+                ),
+                StreamCancelledEvent {
+                    stream_id,
+                    sender,
+                    recipient,
+                    amount_withdrawn,
+                    refunded_amount,
+                    cancelled_at: now,
+                    cancelled_by,
+                },
+            );
+
+            Ok(())
+        })
+    }
+
+    /// Mark a stream as winding down: no refund, no further control changes.
+    fn begin_wind_down(env: &Env, mut stream: Stream, stream_id: u64) -> Result<(), StreamError> {
+        if stream.paused {
+            return Err(StreamError::StreamPaused);
+        }
+
+        stream.winding_down = true;
+        save_stream(env, stream_id, &stream);
+
+        env.events().publish(
+            (Symbol::new(env, "stream_winding_down"), stream_id),
+            StreamWindingDownEvent {
+                stream_id,
+                sender: stream.sender.clone(),
+                recipient: stream.recipient.clone(),
+                end_time: Self::projected_end_time(&stream),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Pause an active stream. Only the sender may pause.
+    ///
+    /// # Errors
+    /// - `StreamNotFound`  — no stream exists with `stream_id`.
+    /// - `Unauthorized`    — caller is not the stream's sender.
+    /// - `StreamInactive`  — stream is already inactive.
+    /// - `StreamWindingDown` — stream is winding down gracefully.
+    pub fn pause_stream(env: Env, sender: Address, stream_id: u64) -> Result<(), StreamError> {
+        Self::pause_internal(&env, sender, stream_id, None)
+    }
+
+    /// Pause an active stream until `resume_at`, after which it resumes on its
+    /// own with no further transaction. Only the sender may pause.
+    ///
+    /// Accrual treats `[paused_at, resume_at]` as paused, exactly as if
+    /// `resume_stream` were called at `resume_at`; the sender may still resume
+    /// earlier. The scheduled resume emits no event.
+    ///
+    /// # Errors
+    /// Same as `pause_stream`, plus:
+    /// - `InvalidDuration` — `resume_at` is not in the future.
+    pub fn pause_until(
+        env: Env,
+        sender: Address,
+        stream_id: u64,
+        resume_at: u64,
+    ) -> Result<(), StreamError> {
+        if resume_at <= env.ledger().timestamp() {
+            return Err(StreamError::InvalidDuration);
+        }
+        Self::pause_internal(&env, sender, stream_id, Some(resume_at))
+    }
+
+    /// Shared body of `pause_stream` and `pause_until`.
+    fn pause_internal(
+        env: &Env,
+        sender: Address,
+        stream_id: u64,
+        resume_at: Option<u64>,
+    ) -> Result<(), StreamError> {
+        sender.require_auth();
+
+        let mut stream = load_stream(env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+        Self::validate_stream_ownership(&stream, &sender)?;
+        Self::validate_stream_active(&stream)?;
+        Self::validate_stream_controllable(&stream)?;
+
+        if stream.paused {
+            return Err(StreamError::StreamInactive);
+        }
+
+        let now = env.ledger().timestamp();
+        // Freeze what has vested so far; milestone streams report only the
+        // checkpoint while paused, so a tranche reached before the pause must
+        // already be recorded there.
+        Self::checkpoint_accrual(env, &mut stream, now);
+        stream.paused = true;
+        stream.paused_at = Some(now);
+        stream.resume_at = resume_at;
+        stream.status = StreamStatus::Paused;
+        save_stream(env, stream_id, &stream);
+
+        env.events().publish(
+            (Symbol::new(env, "stream_paused"), stream_id),
+            StreamPausedEvent {
+                stream_id,
+                sender,
+                paused_at: now,
+                resume_at,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Resume a paused stream. Adjusts `end_time` by the pause duration.
+    ///
+    /// The `last_update_time` is advanced by the pause duration so that
+    /// accrual resumes from the current moment, effectively extending the
+    /// stream by the duration it was paused. Returns the projected end time,
+    /// the same value `get_stream_summary` reports afterwards.
+    ///
+    /// # Errors
+    /// - `StreamNotFound`  — no stream exists with `stream_id`.
+    /// - `Unauthorized`    — caller is not the stream's sender.
+    /// - `StreamInactive`  — stream is not paused (already active or cancelled).
+    pub fn resume_stream(env: Env, sender: Address, stream_id: u64) -> Result<u64, StreamError> {
+        sender.require_auth();
+
+        let mut stream = load_stream(&env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+        Self::validate_stream_ownership(&stream, &sender)?;
+
+        if !stream.paused {
+            return Err(StreamError::StreamInactive);
+        }
+
+        let now = env.ledger().timestamp();
+        // Advance last_update_time by pause duration so accrual resumes from now.
+        end_pause(&mut stream, now);
+        save_stream(&env, stream_id, &stream);
+        let new_end_time = Self::vesting_end_time(&env, &stream);
+
+        env.events().publish(
+            (Symbol::new(&env, "stream_resumed"), stream_id),
+            StreamResumedEvent {
+                stream_id,
+                sender,
+                new_end_time,
+            },
+        );
+
+        Ok(new_end_time)
+    }
+
+    /// Checkpoint a stream's accrual without transferring any tokens.
+    ///
+    /// Folds everything vested so far into `accrued_checkpoint` so that later
+    /// mutations start from a consistent accrual baseline. Permissionless:
+    /// keepers may call it for any stream. Returns the new checkpoint.
+    ///
+    /// # Errors
+    /// - `StreamNotFound`  — no stream exists with `stream_id`.
+    /// - `StreamInactive`  — stream has been cancelled or fully withdrawn.
+    pub fn settle(env: Env, stream_id: u64) -> Result<i128, StreamError> {
+        let mut stream = load_stream(&env, stream_id)?;
+        Self::validate_not_finalized(&stream)?;
+        Self::validate_stream_active(&stream)?;
+
+        Self::settle_loaded(&env, &mut stream, stream_id);
+        Ok(stream.accrued_checkpoint)
+    }
+
+    /// Settle every listed stream in one transaction.
+    ///
+    /// Permissionless keeper entry. Ids with no stream, and streams that are
+    /// inactive or finalized, are skipped. Returns the ids whose checkpoint
+    /// actually advanced, so keepers can tell which calls did work.
+    ///
+    /// # Errors
+    /// - `BatchTooLarge` — more than `MAX_BATCH_SIZE` ids were supplied.
+    pub fn batch_settle(env: Env, ids: Vec<u64>) -> Result<Vec<u64>, StreamError> {
+        if ids.len() > MAX_BATCH_SIZE {
+            return Err(StreamError::BatchTooLarge);
+        }
+
+        let now = env.ledger().timestamp();
+        let mut changed = Vec::new(&env);
+        for id in ids.iter() {
+            let Ok(mut stream) = load_stream(&env, id) else {
+                continue;
+            };
+            if !stream.is_active || stream.finalized {
+                continue;
+            }
+            if Self::elapsed_since_checkpoint(&stream, now).unwrap_or(0) == 0 {
+                continue;
+            }
+            Self::settle_loaded(&env, &mut stream, id);
+            changed.push_back(id);
+        }
+
+        Ok(changed)
+    }
+
+    /// Checkpoint `stream`, persist it and emit `stream_settled`.
+    fn settle_loaded(env: &Env, stream: &mut Stream, stream_id: u64) {
+        Self::checkpoint_accrual(env, stream, env.ledger().timestamp());
+        save_stream(env, stream_id, stream);
+
+        env.events().publish(
+            (Symbol::new(env, "stream_settled"), stream_id),
+            StreamSettledEvent {
+                stream_id,
+                accrued_checkpoint: stream.accrued_checkpoint,
+                settled_at: stream.last_update_time,
+            },
+        );
+    }
+
+    /// Lock a settled stream as an immutable record.
+    ///
+    /// Either party may finalize once the stream is cancelled or completed and
+    /// no top-up is left parked. Afterwards every mutating entry point returns
+    /// `StreamFinalized`; views and TTL extension keep working.
+    ///
+    /// # Errors
+    /// - `StreamNotFound`   — no stream exists with `stream_id`.
+    /// - `Unauthorized`     — caller is neither the sender nor the recipient.
+    /// - `StreamFinalized`  — stream is already finalized.
+    /// - `StreamNotSettled` — stream is still active or has a parked top-up.
+    pub fn finalize_stream(env: Env, caller: Address, stream_id: u64) -> Result<(), StreamError> {
+        caller.require_auth();
+
+        let mut stream = load_stream(&env, stream_id)?;
+        if caller != stream.sender && caller != stream.recipient {
+            return Err(StreamError::Unauthorized);
+        }
+        Self::validate_not_finalized(&stream)?;
+        if stream.is_active || load_pending_topup(&env, stream_id) > 0 {
+            return Err(StreamError::StreamNotSettled);
+        }
+
+        stream.finalized = true;
+        save_stream(&env, stream_id, &stream);
+
+        env.events().publish(
+            (Symbol::new(&env, "stream_finalized"), stream_id),
+            StreamFinalizedEvent {
+                stream_id,
+                finalized_by: caller,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Extend the TTL of a single stream entry when it is running low.
+    ///
+    /// Permissionless, like `extend_ttls`.
+    ///
+    /// # Errors
+    /// - `StreamNotFound` — no stream exists with `stream_id`.
+    pub fn bump_stream_ttl(env: Env, stream_id: u64) -> Result<(), StreamError> {
+        if !try_bump_stream_ttl(&env, stream_id) {
+            return Err(StreamError::StreamNotFound);
+        }
+        Ok(())
+    }
+
+    /// Extend the TTL of every listed stream in one transaction.
+    ///
+    /// Permissionless keeper entry for storage health. Ids with no stream are
+    /// skipped. Returns how many entries were extended.
+    ///
+    /// # Errors
+    /// - `BatchTooLarge` — more than `MAX_BATCH_SIZE` ids were supplied.
+    pub fn extend_ttls(env: Env, ids: Vec<u64>) -> Result<u32, StreamError> {
+        if ids.len() > MAX_BATCH_SIZE {
+            return Err(StreamError::BatchTooLarge);
+        }
+
+        let mut extended = 0;
+        for id in ids.iter() {
+            if try_bump_stream_ttl(&env, id) {
+                extended += 1;
+            }
+        }
+
+        Ok(extended)
+    }
+
+    /// Check the next `limit` stream ids for the legacy layout, migrating any
+    /// found (see `load_stream`). Returns `true` once every id has been
+    /// checked, which unlocks `recover_token`.
+    ///
+    /// Permissionless keeper entry that resumes where the previous call
+    /// stopped. `limit` is clamped to `MAX_PAGE_SIZE`.
+    pub fn migrate_legacy_streams(env: Env, limit: u32) -> bool {
+        if is_legacy_migrated(&env) {
+            return true;
+        }
+
+        let first = legacy_migration_cursor(&env);
+        let end = first
+            .saturating_add(limit.min(MAX_PAGE_SIZE) as u64)
+            .min(stream_count(&env).saturating_add(1));
+        for id in first..end {
+            let _ = load_stream(&env, id);
+        }
+        save_legacy_migration_cursor(&env, end);
+
+        // Streams created from now on are written in the current layout.
+        let done = end > stream_count(&env);
+        if done {
+            set_legacy_migrated(&env);
+        }
+        done
+    }
+
+    // ─── Read-only Queries ────────────────────────────────────────────────────
+    //
+    // No view requires `initialize`: config is only ever read through
+    // `try_load_config`, and a missing config behaves as a 0 bps fee.
+
+    /// Returns the stream record for `stream_id`, or `None` if it does not exist.
+    ///
+    /// A pure read: it never extends the entry's TTL. Use `bump_stream_ttl`
+    /// to keep a stream's entry live.
+    pub fn get_stream(env: Env, stream_id: u64) -> Option<Stream> {
+        try_load_stream(&env, stream_id)
+    }
+
+    /// Returns the stream with its derived values, or `None` if it does not exist.
+    ///
+    /// Guaranteed never to write storage (no TTL extension), so simulation RPCs
+    /// can call it freely. Use `bump_stream_ttl` to keep a stream's entry live.
+    pub fn preview_stream(env: Env, stream_id: u64) -> Option<StreamView> {
+        let stream = try_load_stream(&env, stream_id)?;
+        let now = env.ledger().timestamp();
+        let (vested, claimable) = if stream.is_active {
+            (
+                Self::calculate_vested(&env, &stream, now),
+                Self::calculate_claimable(&env, &stream, now),
+            )
+        } else {
+            (stream.withdrawn_amount, 0)
+        };
+
+        Some(StreamView {
+            vested,
+            claimable,
+            end_time: Self::vesting_end_time(&env, &stream),
+            pending_topup: load_pending_topup(&env, stream_id),
+            stream,
+        })
+    }
+
+    /// Compact `(status_code, withdrawable, end_time)` for cheap polling, or
+    /// `None` if the stream does not exist.
+    ///
+    /// Status codes: 0 `Active`, 1 `Paused`, 2 `Cancelled`, 3 `Completed`,
+    /// 4 `Pending` (an active, unpaused stream before its `cliff_time`).
+    /// `withdrawable` and `end_time` match `preview_stream`'s `claimable` and
+    /// `end_time`. Never writes storage. The code is a `u32` rather than a
+    /// `u8` because the contract ABI has no 8-bit value type; it always fits
+    /// in a byte.
+    pub fn get_stream_summary(env: Env, stream_id: u64) -> Option<(u32, i128, u64)> {
+        let stream = try_load_stream(&env, stream_id)?;
+        let status = if stream.status == StreamStatus::Active
+            && env.ledger().timestamp() < stream.cliff_time
+        {
+            StreamStatus::Pending
+        } else {
+            stream.status.clone()
+        };
+        let status_code = match status {
+            StreamStatus::Active => 0,
+            StreamStatus::Paused => 1,
+            StreamStatus::Cancelled => 2,
+            StreamStatus::Completed => 3,
+            StreamStatus::Pending => 4,
+        };
+        let withdrawable = if stream.is_active {
+            Self::calculate_claimable(&env, &stream, env.ledger().timestamp())
+        } else {
+            0
+        };
+        let end_time = Self::vesting_end_time(&env, &stream);
+        Some((status_code, withdrawable, end_time))
+    }
+
+    /// Status derived from the stream's flags and the current ledger time.
+    ///
+    /// Unlike the stored `status`, which only changes when the stream is
+    /// touched, this reports `Pending` before `cliff_time` and `Completed` as
+    /// soon as the whole deposit has vested, even before the final withdrawal.
+    /// Closed streams report their stored `Cancelled` or `Completed`.
+    ///
+    /// # Errors
+    /// - `StreamNotFound` — no stream exists with `stream_id`.
+    pub fn get_stream_status(env: Env, stream_id: u64) -> Result<StreamStatus, StreamError> {
+        let stream = load_stream_readonly(&env, stream_id)?;
+        let now = env.ledger().timestamp();
+        Ok(if !stream.is_active {
+            stream.status
+        } else if stream.paused {
+            StreamStatus::Paused
+        } else if now < stream.cliff_time {
+            StreamStatus::Pending
+        } else if Self::calculate_vested(&env, &stream, now) >= stream.deposited_amount {
+            StreamStatus::Completed
+        } else {
+            StreamStatus::Active
+        })
+    }
+
+    /// Every boolean state of a stream in one call, or `None` if it does not
+    /// exist. `cancelable` and `transferable` are derived from the stored flags.
+    pub fn get_stream_flags(env: Env, stream_id: u64) -> Option<StreamFlags> {
+        let stream = try_load_stream(&env, stream_id)?;
+        let open = stream.is_active && !stream.finalized;
+        Some(StreamFlags {
+            is_active: stream.is_active,
+            paused: stream.paused,
+            winding_down: stream.winding_down,
+            finalized: stream.finalized,
+            is_public: stream.is_public,
+            recipient_must_ack_topups: stream.recipient_must_ack_topups,
+            cancelable: open && !stream.winding_down,
+            transferable: open,
+        })
+    }
+
+    /// Returns `(sender, recipient, token)` for a stream, or `None` if it does not exist.
+    pub fn get_counterparties(env: Env, stream_id: u64) -> Option<(Address, Address, Address)> {
+        try_load_stream(&env, stream_id)
+            .map(|stream| (stream.sender, stream.recipient, stream.token_address))
+    }
+
+    /// Returns `true` if the stream exists and has status `Completed`.
+    pub fn is_stream_completed(env: Env, stream_id: u64) -> bool {
+        try_load_stream(&env, stream_id)
+            .map(|s| s.status == StreamStatus::Completed)
+            .unwrap_or(false)
     }
 
-    /// Cancel an active stream.
+    /// Seconds since the stream's `start_time` (0 if it starts in the future).
     ///
-    /// Only the stream's original sender may cancel. The recipient receives all
-    /// accrued tokens up to the cancellation moment, and any remaining unspent
-    /// balance is refunded to the sender.
+    /// Returns `None` if the stream does not exist.
+    pub fn get_stream_age(env: Env, stream_id: u64) -> Option<u64> {
+        try_load_stream(&env, stream_id)
+            .map(|stream| env.ledger().timestamp().saturating_sub(stream.start_time))
+    }
+
+    /// Returns `(deposited, withdrawn, accrued_unwithdrawn)` for a stream in a
+    /// single read, so clients need not recombine the accrual math.
+    ///
+    /// `accrued_unwithdrawn` is what `withdraw` would pay right now (0 once the
+    /// stream is inactive); `deposited - withdrawn - accrued_unwithdrawn` is the
+    /// still-unvested balance.
     ///
     /// # Errors
-    /// - `StreamNotFound`  — no stream exists with `stream_id`.
-    /// - `Unauthorized`    — caller is not the stream's sender.
-    /// - `StreamInactive`  — stream is already inactive.
-    pub fn cancel_stream(env: Env, sender: Address, stream_id: u64) -> Result<(), StreamError> {
-        sender.require_auth();
+    /// - `StreamNotFound` — no stream exists with `stream_id`.
+    pub fn get_effective_balance(
+        env: Env,
+        stream_id: u64,
+    ) -> Result<(i128, i128, i128), StreamError> {
+        let stream = load_stream_readonly(&env, stream_id)?;
+        let accrued = if stream.is_active {
+            Self::calculate_claimable(&env, &stream, env.ledger().timestamp())
+        } else {
+            0
+        };
+        Ok((stream.deposited_amount, stream.withdrawn_amount, accrued))
+    }
 
-        let mut stream = load_stream(&env, stream_id)?;
+    /// Amount `withdraw` would pay out right now.
+    ///
+    /// Shares `calculate_claimable` with `withdraw`, so the view and the
+    /// mutating path cannot drift apart. Returns 0 for inactive streams.
+    ///
+    /// # Errors
+    /// - `StreamNotFound` — no stream exists with `stream_id`.
+    pub fn get_withdrawable_amount(env: Env, stream_id: u64) -> Result<i128, StreamError> {
+        let stream = load_stream_readonly(&env, stream_id)?;
+        if !stream.is_active {
+            return Ok(0);
+        }
+        Ok(Self::calculate_claimable(
+            &env,
+            &stream,
+            env.ledger().timestamp(),
+        ))
+    }
 
-        // Validate ownership and active status
-        Self::validate_stream_ownership(&stream, &sender)?;
+    /// Earliest ledger timestamp at which `withdraw` is allowed: the later of
+    /// the end of the stream's `first_withdraw_delay` and its `cliff_time`,
+    /// or now once both have passed.
+    ///
+    /// Covers time gating only; a withdrawal at that time still needs a
+    /// non-zero claimable amount.
+    ///
+    /// # Errors
+    /// - `StreamNotFound` — no stream exists with `stream_id`.
+    /// - `StreamInactive` — stream has been cancelled or fully withdrawn.
+    /// - `StreamPaused`   — stream is paused; no withdrawal until resumed.
+    pub fn next_withdrawable_at(env: Env, stream_id: u64) -> Result<u64, StreamError> {
+        let stream = load_stream_readonly(&env, stream_id)?;
         Self::validate_stream_active(&stream)?;
+        if stream.paused {
+            return Err(StreamError::StreamPaused);
+        }
+        Ok(stream
+            .start_time
+            .saturating_add(stream.first_withdraw_delay)
+            .max(stream.cliff_time)
+            .max(env.ledger().timestamp()))
+    }
 
-        let now = env.ledger().timestamp();
-        let accrued_amount = Self::calculate_claimable(&stream, now);
+    /// Share of the accrued amount the recipient has already withdrawn, in bps.
+    ///
+    /// Low values flag streams with large owed-but-unwithdrawn balances.
+    /// Returns `10_000` when nothing has accrued yet (nothing is owed) and for
+    /// inactive streams, which have settled everything owed.
+    ///
+    /// # Errors
+    /// - `StreamNotFound` — no stream exists with `stream_id`.
+    pub fn get_utilization_bps(env: Env, stream_id: u64) -> Result<u32, StreamError> {
+        let stream = load_stream_readonly(&env, stream_id)?;
+        if !stream.is_active {
+            return Ok(MAX_BPS);
+        }
 
-        // Effects: update all stream state before any external call
-        if accrued_amount > 0 {
-            stream.withdrawn_amount = stream.withdrawn_amount.saturating_add(accrued_amount);
+        let accrued = Self::calculate_vested(&env, &stream, env.ledger().timestamp());
+        if accrued <= 0 {
+            return Ok(MAX_BPS);
         }
+        let bps = stream.withdrawn_amount.saturating_mul(MAX_BPS as i128) / accrued;
+        Ok(bps.clamp(0, MAX_BPS as i128) as u32)
+    }
 
-        let refunded_amount = stream
-            .deposited_amount
-            .saturating_sub(stream.withdrawn_amount);
+    /// Get the current claimable amount for a stream without modifying state.
+    ///
+    /// This is a read-only query that calculates how many tokens the recipient
+    /// can currently withdraw based on elapsed time and stream rate.
+    ///
+    /// Returns `None` if the stream doesn't exist, otherwise returns the claimable amount.
+    pub fn get_claimable_amount(env: Env, stream_id: u64) -> Option<i128> {
+        try_load_stream(&env, stream_id).map(|stream| {
+            if !stream.is_active {
+                return 0;
+            }
+            let now = env.ledger().timestamp();
+            Self::calculate_claimable(&env, &stream, now)
+        })
+    }
 
-        stream.is_active = false;
-        stream.status = StreamStatus::Cancelled;
-        stream.last_update_time = now;
+    /// Get the total amount withdrawn from a stream so far.
+    ///
+    /// A lightweight alternative to `get_stream` for progress displays that
+    /// only need this one figure. Returns `None` if the stream doesn't exist.
+    pub fn get_withdrawn_amount(env: Env, stream_id: u64) -> Option<i128> {
+        try_load_stream(&env, stream_id).map(|stream| stream.withdrawn_amount)
+    }
 
-        let recipient = stream.recipient.clone();
-        let amount_withdrawn = stream.withdrawn_amount;
+    /// Time-weighted average rate over the stream's life so far.
+    ///
+    /// Computed as total accrued divided by seconds since `start_time`, so
+    /// periods at different rates are weighted by their length and paused time
+    /// counts at rate zero. For inactive streams the window ends at cancellation
+    /// or completion and the amount is what had vested by then, so unvested
+    /// balance handed to the recipient by `cancel_with_split` is not counted.
+    /// Returns 0 if the stream doesn't exist or no time has elapsed.
+    pub fn get_average_rate(env: Env, stream_id: u64) -> i128 {
+        let Some(stream) = try_load_stream(&env, stream_id) else {
+            return 0;
+        };
 
-        // Persist state before any external calls (CEI)
-        save_stream(&env, stream_id, &stream);
+        let (accrued, end) = if stream.is_active {
+            let now = env.ledger().timestamp();
+            (Self::calculate_vested(&env, &stream, now), now)
+        } else {
+            (stream.accrued_checkpoint, stream.last_update_time)
+        };
 
-        // Interactions: token transfers after state is committed to storage
-        let token_client = token::Client::new(&env, &stream.token_address);
-        let contract_address = env.current_contract_address();
+        let elapsed = end.saturating_sub(stream.start_time);
+        if elapsed == 0 {
+            return 0;
+        }
+        accrued / elapsed as i128
+    }
+
+    /// The stream's rate expressed per day (86 400 seconds).
+    ///
+    /// Returns 0 if the stream doesn't exist; saturates instead of overflowing.
+    pub fn get_rate_per_day(env: Env, stream_id: u64) -> i128 {
+        Self::rate_over(&env, stream_id, SECS_PER_DAY)
+    }
+
+    /// The stream's rate expressed per 30-day month (2 592 000 seconds).
+    ///
+    /// Returns 0 if the stream doesn't exist; saturates instead of overflowing.
+    pub fn get_rate_per_month(env: Env, stream_id: u64) -> i128 {
+        Self::rate_over(&env, stream_id, SECS_PER_MONTH)
+    }
 
-        if accrued_amount > 0 {
-            token_client.transfer(&contract_address, &recipient, &accrued_amount);
+    /// Amount the stream accrues over `seconds` at its current rate.
+    fn rate_over(env: &Env, stream_id: u64, seconds: i128) -> i128 {
+        try_load_stream(env, stream_id).map_or(0, |stream| match stream.rate_unit {
+            RateUnit::PerSecond => stream.rate_per_second.saturating_mul(seconds),
+        })
+    }
+
+    /// Seconds the unaccrued balance will last at the current `rate_per_second`.
+    ///
+    /// Computed as `(deposited_amount - vested) / rate_per_second`, so it
+    /// tracks rate changes rather than the nominal end time. A milestone
+    /// stream instead reports the estimated seconds until its final milestone
+    /// (see `SECS_PER_LEDGER`). Returns 0 if the stream doesn't exist, is
+    /// inactive, or has a zero rate.
+    pub fn seconds_funded(env: Env, stream_id: u64) -> i128 {
+        let Some(stream) = try_load_stream(&env, stream_id) else {
+            return 0;
+        };
+        if !stream.is_active || stream.rate_per_second <= 0 {
+            return 0;
         }
 
-        if refunded_amount > 0 {
-            token_client.transfer(&contract_address, &sender, &refunded_amount);
+        let now = env.ledger().timestamp();
+        if stream.milestones.is_some() {
+            return i128::from(Self::vesting_end_time(&env, &stream).saturating_sub(now));
+        }
+        let remaining = stream
+            .deposited_amount
+            .saturating_sub(Self::calculate_vested(&env, &stream, now));
+        remaining / stream.rate_per_second
+    }
+
+    /// Returns `points` evenly spaced `(timestamp, accrued)` samples spanning
+    /// the stream from `start_time` to its projected fully-vested time.
+    ///
+    /// `points` is clamped to `[2, MAX_CURVE_POINTS]`. Samples before the last
+    /// checkpoint are interpolated linearly from `initial_unlock` at
+    /// `start_time`; later samples are projected at the current rate as if the
+    /// stream is not paused. Samples before `cliff_time` are 0, so a cliff shows
+    /// as a step (`get_payout_schedule` gives its exact point). The final
+    /// sample always equals `deposited_amount`. Returns an empty vector if the
+    /// stream does not exist.
+    ///
+    /// A milestone stream ignores `points` and returns its steps instead:
+    /// `(start_time, 0)`, then each milestone at the estimated time its ledger
+    /// sequence is reached (see `SECS_PER_LEDGER`).
+    pub fn claimable_curve(env: Env, stream_id: u64, points: u32) -> Vec<(u64, i128)> {
+        let mut curve = Vec::new(&env);
+        let Some(mut stream) = try_load_stream(&env, stream_id) else {
+            return curve;
+        };
+        stream.paused = false;
+
+        if let Some(milestones) = &stream.milestones {
+            curve.push_back((stream.start_time, 0));
+            for (sequence, released) in Self::milestone_steps(&env, &stream, milestones).iter() {
+                let t = Self::estimated_time_at_sequence(&env, sequence).max(stream.start_time);
+                curve.push_back((t, released));
+            }
+            return curve;
         }
 
-        // Emit cancellation event
-        env.events().publish(
-            (Symbol::new(&env, "stream_cancelled"), stream_id),
-            StreamCancelledEvent {
-                stream_id,
-                sender,
-                recipient,
-                amount_withdrawn,
-                refunded_amount,
-            },
-        );
+        let points = points.clamp(2, MAX_CURVE_POINTS) as u128;
+        let end = Self::projected_end_time(&stream);
+        let span = end.saturating_sub(stream.start_time) as u128;
 
-        Ok(())
+        for i in 0..points {
+            let offset = (span * i / (points - 1)) as u64;
+            let t = stream.start_time + offset;
+            curve.push_back((t, Self::projected_released(&env, &stream, t)));
+        }
+
+        curve
     }
 
-    /// Pause an active stream. Only the sender may pause.
+    /// Returns the stream's release points as `(timestamp, cumulative released)`.
     ///
-    /// # Errors
-    /// - `StreamNotFound`  — no stream exists with `stream_id`.
-    /// - `Unauthorized`    — caller is not the stream's sender.
-    /// - `StreamInactive`  — stream is already inactive.
-    pub fn pause_stream(env: Env, sender: Address, stream_id: u64) -> Result<(), StreamError> {
-        sender.require_auth();
+    /// Linear streams return `(start_time, initial_unlock)` and
+    /// `(projected end, deposited_amount)`; with a cliff the start point is
+    /// `(start_time, 0)` and `(cliff_time, accrued at the cliff)` follows it.
+    /// Milestone streams return one point
+    /// per step keyed by *ledger sequence* rather than timestamp, with the last
+    /// step reporting the full `deposited_amount`. Returns an empty vector if
+    /// the stream does not exist.
+    pub fn get_payout_schedule(env: Env, stream_id: u64) -> Vec<(u64, i128)> {
+        let mut schedule = Vec::new(&env);
+        let Some(stream) = try_load_stream(&env, stream_id) else {
+            return schedule;
+        };
 
-        let mut stream = load_stream(&env, stream_id)?;
-        Self::validate_stream_ownership(&stream, &sender)?;
-        Self::validate_stream_active(&stream)?;
+        if let Some(milestones) = &stream.milestones {
+            for (sequence, released) in Self::milestone_steps(&env, &stream, milestones).iter() {
+                schedule.push_back((sequence as u64, released));
+            }
+            return schedule;
+        }
 
-        if stream.paused {
-            return Err(StreamError::StreamInactive);
+        let end = Self::projected_end_time(&stream);
+        if stream.cliff_time > stream.start_time && stream.cliff_time < end {
+            schedule.push_back((stream.start_time, 0));
+            let at_cliff = Self::projected_vested(&env, &stream, stream.cliff_time);
+            schedule.push_back((stream.cliff_time, at_cliff));
+        } else if stream.cliff_time > stream.start_time {
+            schedule.push_back((stream.start_time, 0));
+        } else {
+            schedule.push_back((stream.start_time, stream.initial_unlock));
         }
+        schedule.push_back((end, stream.deposited_amount));
+        schedule
+    }
 
-        let now = env.ledger().timestamp();
-        stream.paused = true;
-        stream.paused_at = Some(now);
-        stream.status = StreamStatus::Paused;
-        save_stream(&env, stream_id, &stream);
+    /// Returns streams for `token` whose status equals `status`, paginated.
+    ///
+    /// `offset` skips that many *matching* streams; `limit` is clamped to
+    /// `MAX_PAGE_SIZE`. Streams are returned in creation order. The scan walks
+    /// the token's index from the start, so the cost is O(streams for token)
+    /// storage reads in the worst case regardless of `limit`.
+    pub fn get_streams_by_token_and_status(
+        env: Env,
+        token: Address,
+        status: StreamStatus,
+        offset: u32,
+        limit: u32,
+    ) -> Vec<Stream> {
+        let limit = limit.min(MAX_PAGE_SIZE);
+        let mut page = Vec::new(&env);
+        if limit == 0 {
+            return page;
+        }
 
-        env.events().publish(
-            (Symbol::new(&env, "stream_paused"), stream_id),
-            StreamPausedEvent {
-                stream_id,
-                sender,
-                paused_at: now,
-            },
-        );
+        let mut skipped = 0;
+        for id in token_stream_ids(&env, &token).iter() {
+            let Some(stream) = try_load_stream(&env, id) else {
+                continue;
+            };
+            if stream.status != status {
+                continue;
+            }
+            if skipped < offset {
+                skipped += 1;
+                continue;
+            }
+            page.push_back(stream);
+            if page.len() == limit {
+                break;
+            }
+        }
 
-        Ok(())
+        page
     }
 
-    /// Resume a paused stream. Adjusts `end_time` by the pause duration.
+    /// Returns ids of every public stream funded by `sender`, in creation order.
     ///
-    /// The `last_update_time` is advanced to `now` so that accrual resumes
-    /// from the current moment, effectively extending the stream by the
-    /// duration it was paused.
+    /// Cancelled and completed streams stay listed. For large senders prefer
+    /// the paginated `get_streams_involving`.
+    pub fn get_streams_by_sender(env: Env, sender: Address) -> Vec<u64> {
+        sender_stream_ids(&env, &sender)
+    }
+
+    /// Returns ids of every public stream paying `recipient`, in creation order.
     ///
-    /// # Errors
-    /// - `StreamNotFound`  — no stream exists with `stream_id`.
-    /// - `Unauthorized`    — caller is not the stream's sender.
-    /// - `StreamInactive`  — stream is not paused (already active or cancelled).
-    pub fn resume_stream(env: Env, sender: Address, stream_id: u64) -> Result<u64, StreamError> {
-        sender.require_auth();
+    /// Cancelled and completed streams stay listed, since they remain
+    /// queryable. For large recipients prefer the paginated
+    /// `get_streams_involving`.
+    pub fn get_streams_by_recipient(env: Env, recipient: Address) -> Vec<u64> {
+        recipient_stream_ids(&env, &recipient)
+    }
 
-        let mut stream = load_stream(&env, stream_id)?;
-        Self::validate_stream_ownership(&stream, &sender)?;
+    /// Returns ids of public streams where `address` is sender or recipient.
+    ///
+    /// The sender and recipient indexes are merged in ascending id order and
+    /// de-duplicated, so a stream with `address` in both roles appears once.
+    /// `offset` skips that many merged ids; `limit` is clamped to
+    /// `MAX_PAGE_SIZE`. Cost is O(streams involving `address`).
+    pub fn get_streams_involving(env: Env, address: Address, offset: u32, limit: u32) -> Vec<u64> {
+        let merged = Self::merge_sorted_unique(
+            &env,
+            &sender_stream_ids(&env, &address),
+            &recipient_stream_ids(&env, &address),
+        );
+        let start = offset.min(merged.len());
+        let end = start
+            .saturating_add(limit.min(MAX_PAGE_SIZE))
+            .min(merged.len());
+        merged.slice(start..end)
+    }
 
-        if !stream.paused {
-            return Err(StreamError::StreamInactive);
-        }
+    /// Returns ids of every stream where `caller` is sender or recipient,
+    /// private streams included, in ascending id order. Requires `caller`'s
+    /// auth.
+    ///
+    /// For deployments that want enumeration gated behind the party's own
+    /// signature: unlike `get_streams_involving`, it also reads the private
+    /// index, which no unauthenticated query exposes.
+    pub fn get_my_streams(env: Env, caller: Address) -> Vec<u64> {
+        caller.require_auth();
+        let public = Self::merge_sorted_unique(
+            &env,
+            &sender_stream_ids(&env, &caller),
+            &recipient_stream_ids(&env, &caller),
+        );
+        Self::merge_sorted_unique(&env, &public, &private_stream_ids(&env, &caller))
+    }
 
-        let now = env.ledger().timestamp();
-        let paused_at = stream.paused_at.unwrap_or(now);
-        let pause_duration = now.saturating_sub(paused_at);
+    /// Returns the stream ids in a bundle, one per token, or an empty vector.
+    pub fn get_bundle(env: Env, bundle_id: u64) -> Vec<u64> {
+        load_bundle(&env, bundle_id)
+    }
 
-        // Advance last_update_time by pause duration so accrual resumes from now.
-        stream.last_update_time = stream.last_update_time.saturating_add(pause_duration);
-        // new_end_time represents when the stream will fully drain from now.
-        let remaining = stream
-            .deposited_amount
-            .saturating_sub(stream.withdrawn_amount);
-        // rate_per_second is guaranteed >= 1 due to create_stream's InvalidRate guard
-        let new_end_time = now + (remaining / stream.rate_per_second) as u64;
+    /// Snapshot of every id in `[start_id, end_id]`, for analytics backfills.
+    ///
+    /// The span is capped at `MAX_PAGE_SIZE` ids, counted from `start_id`.
+    /// Each id is paired with its stream, or `None` if it is missing, closed
+    /// (inactive), or private. Returns an empty vector if `start_id > end_id`.
+    /// Read-only: no TTLs are extended.
+    pub fn get_stream_range(env: Env, start_id: u64, end_id: u64) -> Vec<(u64, Option<Stream>)> {
+        let mut range = Vec::new(&env);
+        if start_id > end_id {
+            return range;
+        }
+        let last = end_id.min(start_id.saturating_add(MAX_PAGE_SIZE as u64 - 1));
 
-        stream.paused = false;
-        stream.paused_at = None;
-        stream.status = StreamStatus::Active;
-        save_stream(&env, stream_id, &stream);
+        for id in start_id..=last {
+            let stream =
+                try_load_stream(&env, id).filter(|stream| stream.is_active && stream.is_public);
+            range.push_back((id, stream));
+        }
 
-        env.events().publish(
-            (Symbol::new(&env, "stream_resumed"), stream_id),
-            StreamResumedEvent {
-                stream_id,
-                sender,
-                new_end_time,
-            },
-        );
+        range
+    }
 
-        Ok(new_end_time)
+    /// Number of streams ever created; ids run from 1 to this value.
+    pub fn get_stream_count(env: Env) -> u64 {
+        stream_count(&env)
     }
 
-    // ─── Read-only Queries ────────────────────────────────────────────────────
+    /// Backfill page: the existing public streams among the `limit` ids
+    /// starting at `start_id`, paired with their ids in ascending order.
+    ///
+    /// `limit` is clamped to `MAX_PAGE_SIZE` and bounds the ids scanned, not
+    /// the streams returned, so a call costs at most that many storage reads.
+    /// Missing and private ids are skipped (a page may therefore be short or
+    /// empty), and closed streams are included. Continue from `next_id` until
+    /// it is `None`. Read-only: no TTLs are extended.
+    pub fn get_streams_page(env: Env, start_id: u64, limit: u32) -> StreamsPage {
+        let first = start_id.max(1);
+        let end = first
+            .saturating_add(limit.min(MAX_PAGE_SIZE) as u64)
+            .min(stream_count(&env).saturating_add(1));
+
+        let mut streams = Vec::new(&env);
+        for id in first..end {
+            if let Some(stream) = try_load_stream(&env, id).filter(|stream| stream.is_public) {
+                streams.push_back((id, stream));
+            }
+        }
 
-    /// Returns the stream record for `stream_id`, or `None` if it does not exist.
-    pub fn get_stream(env: Env, stream_id: u64) -> Option<Stream> {
-        try_load_stream(&env, stream_id)
+        StreamsPage {
+            streams,
+            next_id: (end <= stream_count(&env)).then_some(end),
+        }
     }
 
-    /// Returns `true` if the stream exists and has status `Completed`.
-    pub fn is_stream_completed(env: Env, stream_id: u64) -> bool {
-        try_load_stream(&env, stream_id)
-            .map(|s| s.status == StreamStatus::Completed)
-            .unwrap_or(false)
+    /// Keeper query: streams in the id window `[offset + 1, offset + limit]`
+    /// that need attention, each with the first matching `AttentionReason`.
+    ///
+    /// `limit` is clamped to `MAX_PAGE_SIZE`, so a call costs at most that many
+    /// storage reads; page through `offset` to cover every stream. Private
+    /// streams are skipped. TTL itself cannot be read on-chain, so `LowTtl` is
+    /// inferred from how long ago the stream was last checkpointed.
+    pub fn get_streams_needing_attention(
+        env: Env,
+        offset: u32,
+        limit: u32,
+    ) -> Vec<(u64, AttentionReason)> {
+        let mut flagged = Vec::new(&env);
+        let now = env.ledger().timestamp();
+        let first = offset as u64 + 1;
+        let last = (offset as u64 + limit.min(MAX_PAGE_SIZE) as u64).min(stream_count(&env));
+
+        for id in first..=last {
+            let Some(stream) = try_load_stream(&env, id) else {
+                continue;
+            };
+            if !stream.is_public {
+                continue;
+            }
+            if let Some(reason) = Self::attention_reason(&env, &stream, now) {
+                flagged.push_back((id, reason));
+            }
+        }
+
+        flagged
     }
 
-    /// Get the current claimable amount for a stream without modifying state.
+    /// Returns protocol-wide aggregates: total and active stream counts and
+    /// the number of tokens that have backed a stream.
     ///
-    /// This is a read-only query that calculates how many tokens the recipient
-    /// can currently withdraw based on elapsed time and stream rate.
+    /// Built from counters maintained on every state change, so the cost is
+    /// constant. Per-token TVL and fees come from `get_token_stats`.
+    pub fn get_protocol_stats(env: Env) -> ProtocolStats {
+        ProtocolStats {
+            total_streams: stream_count(&env),
+            active_streams: active_stream_count(&env),
+            token_count: tracked_token_count(&env),
+        }
+    }
+
+    /// Returns locked and fee totals for the tokens at first-seen positions
+    /// `[offset, offset + limit)`, keyed by token address.
     ///
-    /// Returns `None` if the stream doesn't exist, otherwise returns the claimable amount.
-    pub fn get_claimable_amount(env: Env, stream_id: u64) -> Option<i128> {
-        try_load_stream(&env, stream_id).map(|stream| {
-            if !stream.is_active {
-                return 0;
+    /// `limit` is clamped to `MAX_PAGE_SIZE`, so a call reads at most that
+    /// many tokens; page through `offset` up to `ProtocolStats::token_count`
+    /// to cover every token.
+    pub fn get_token_stats(env: Env, offset: u32, limit: u32) -> Map<Address, TokenTotals> {
+        let last = offset
+            .saturating_add(limit.min(MAX_PAGE_SIZE))
+            .min(tracked_token_count(&env));
+
+        let mut token_totals = Map::new(&env);
+        for index in offset..last {
+            if let Some(token) = tracked_token(&env, index) {
+                let totals = load_token_totals(&env, &token);
+                token_totals.set(token, totals);
             }
-            let now = env.ledger().timestamp();
-            Self::calculate_claimable(&stream, now)
-        })
+        }
+        token_totals
     }
 
     // ─── Internal Helpers ─────────────────────────────────────────────────────
 
+    /// Protocol fee on `amount` at `fee_rate_bps`, truncated toward zero.
+    fn fee_for(amount: i128, fee_rate_bps: u32) -> i128 {
+        Self::bps_share(amount, fee_rate_bps)
+    }
+
+    /// `amount * bps / MAX_BPS`, truncated toward zero, without overflowing.
+    ///
+    /// Splits `amount` into whole multiples of `MAX_BPS` and a remainder so no
+    /// intermediate product exceeds `amount`. The result never exceeds
+    /// `amount` for `bps <= MAX_BPS`; callers give the rounding remainder to
+    /// the other side of the split so the parts always sum to `amount`.
+    fn bps_share(amount: i128, bps: u32) -> i128 {
+        let max_bps = MAX_BPS as i128;
+        let bps = bps as i128;
+        amount / max_bps * bps + amount % max_bps * bps / max_bps
+    }
+
+    /// Fee rate that applies to a deposit in `token_address` between these
+    /// parties: 0 if either is fee-exempt or no config exists, otherwise the
+    /// token's override or the global rate.
+    fn effective_fee_rate(
+        env: &Env,
+        token_address: &Address,
+        sender: &Address,
+        recipient: &Address,
+    ) -> u32 {
+        if load_fee_exempt(env, sender) || load_fee_exempt(env, recipient) {
+            return 0;
+        }
+        try_load_config(env).map_or(0, |cfg| {
+            load_token_fee_bps(env, token_address).unwrap_or(cfg.fee_rate_bps)
+        })
+    }
+
     /// Deducts the protocol fee from `amount`, transfers it to the treasury,
     /// emits a `fee_collected` event, and returns the net amount.
     ///
+    /// The token's fee override, if set, replaces the global fee rate.
     /// If no protocol config exists or the fee rate is 0, returns `amount` unchanged.
+    /// If the stream's sender or recipient is fee-exempt, returns `amount` unchanged.
     /// If fee calculation truncates to 0, no transfer/event occurs and `amount` is unchanged.
     /// Time complexity: O(1).
-    fn collect_fee(env: &Env, token_address: &Address, amount: i128, stream_id: u64) -> i128 {
-        match try_load_config(env) {
-            Some(cfg) if cfg.fee_rate_bps > 0 => {
-                let fee = amount * (cfg.fee_rate_bps as i128) / 10_000;
-                if fee > 0 {
-                    let token_client = token::Client::new(env, token_address);
-                    token_client.transfer(&env.current_contract_address(), &cfg.treasury, &fee);
-                    env.events().publish(
-                        (Symbol::new(env, "fee_collected"), stream_id),
-                        FeeCollectedEvent {
-                            stream_id,
-                            treasury: cfg.treasury,
-                            fee_amount: fee,
-                            token: token_address.clone(),
-                        },
-                    );
-                }
-                amount - fee
-            }
-            _ => amount,
+    fn collect_fee(
+        env: &Env,
+        token_address: &Address,
+        amount: i128,
+        stream_id: u64,
+        sender: &Address,
+        recipient: &Address,
+    ) -> i128 {
+        let Some(cfg) = try_load_config(env) else {
+            return amount;
+        };
+        let fee_rate_bps = Self::effective_fee_rate(env, token_address, sender, recipient);
+
+        if fee_rate_bps == 0 {
+            return amount;
+        }
+
+        let fee = Self::fee_for(amount, fee_rate_bps);
+        if fee > 0 {
+            let token_client = token::Client::new(env, token_address);
+            token_client.transfer(&env.current_contract_address(), &cfg.treasury, &fee);
+            add_token_fees(env, token_address, fee);
+            env.events().publish(
+                (Symbol::new(env, "fee_collected"), stream_id),
+                FeeCollectedEvent {
+                    stream_id,
+                    treasury: cfg.treasury,
+                    fee_amount: fee,
+                    token: token_address.clone(),
+                },
+            );
         }
+        amount - fee
     }
 }
@@ -1,7 +1,21 @@
-use soroban_sdk::Env;
+use soroban_sdk::{Address, Env, IntoVal, Map, Symbol, TryFromVal, Val, Vec};
 
 use crate::errors::StreamError;
-use crate::types::{DataKey, ProtocolConfig, Stream};
+use crate::types::{
+    CircuitBreakerConfig, DataKey, LegacyStream, ProtocolConfig, RateUnit, Stream, StreamAssetKind,
+    StreamStatus, Tithe, TokenTotals, WithdrawalWindow,
+};
+
+// ─── TTL Policy ───────────────────────────────────────────────────────────────
+
+/// Approximate number of ledgers closed per day (~5 s per ledger).
+pub const DAY_IN_LEDGERS: u32 = 17_280;
+
+/// Stream entries whose remaining TTL falls below this are extended.
+pub const STREAM_TTL_THRESHOLD: u32 = 30 * DAY_IN_LEDGERS;
+
+/// Remaining TTL a stream entry is extended to when bumped.
+pub const STREAM_TTL_EXTEND_TO: u32 = 60 * DAY_IN_LEDGERS;
 
 // ─── Stream Counter ───────────────────────────────────────────────────────────
 
@@ -20,17 +34,58 @@ pub fn next_stream_id(env: &Env) -> u64 {
     id
 }
 
+/// Returns the total number of streams created so far (the last assigned ID).
+pub fn stream_count(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKey::StreamCounter)
+        .unwrap_or(0)
+}
+
 // ─── Stream CRUD ─────────────────────────────────────────────────────────────
 
-/// Loads a stream by ID from persistent storage.
+/// Loads a stream by ID from persistent storage, for a state-changing call.
 ///
 /// Returns `StreamNotFound` if no entry exists, keeping error handling
 /// central and preventing duplicated `match storage.get(...)` patterns.
+/// An entry still in the `LegacyStream` layout is migrated on this load: it
+/// is rewritten in the current layout, added to the token and party indexes
+/// and, while active, counted as active with its unpaid balance added to the
+/// token's `total_locked`; the old version tracked none of these. Views use
+/// `load_stream_readonly` instead.
 pub fn load_stream(env: &Env, stream_id: u64) -> Result<Stream, StreamError> {
-    env.storage()
-        .persistent()
-        .get(&DataKey::Stream(stream_id))
-        .ok_or(StreamError::StreamNotFound)
+    let (stream, legacy) = read_stream(env, stream_id).ok_or(StreamError::StreamNotFound)?;
+    if legacy {
+        if stream.is_active {
+            let unpaid = stream.deposited_amount - stream.withdrawn_amount;
+            add_token_locked(env, &stream.token_address, unpaid);
+            increment_active_streams(env);
+        }
+        // Legacy streams predate private streams, so every one is public.
+        insert_id_index(
+            env,
+            DataKey::TokenStreams(stream.token_address.clone()),
+            stream_id,
+        );
+        insert_id_index(
+            env,
+            DataKey::SenderStreams(stream.sender.clone()),
+            stream_id,
+        );
+        insert_id_index(
+            env,
+            DataKey::RecipientStreams(stream.recipient.clone()),
+            stream_id,
+        );
+        save_stream(env, stream_id, &stream);
+    }
+    Ok(stream)
+}
+
+/// Read-only counterpart of `load_stream`: a legacy entry is upgraded in
+/// memory only.
+pub fn load_stream_readonly(env: &Env, stream_id: u64) -> Result<Stream, StreamError> {
+    try_load_stream(env, stream_id).ok_or(StreamError::StreamNotFound)
 }
 
 /// Persists a stream record in persistent storage.
@@ -41,11 +96,197 @@ pub fn save_stream(env: &Env, stream_id: u64, stream: &Stream) {
     env.storage()
         .persistent()
         .set(&DataKey::Stream(stream_id), stream);
+    bump_stream_ttl(env, stream_id);
 }
 
 /// Returns the stream if it exists, `None` otherwise (used by read-only queries).
+///
+/// Never writes storage, so it is safe for simulation and indexer reads. A
+/// `pause_until` pause whose `resume_at` has passed is lifted in memory, so
+/// callers always see the stream as it stands at the current ledger time;
+/// the next write persists the resumed state.
 pub fn try_load_stream(env: &Env, stream_id: u64) -> Option<Stream> {
-    env.storage().persistent().get(&DataKey::Stream(stream_id))
+    read_stream(env, stream_id).map(|(stream, _)| stream)
+}
+
+/// Decodes the stream entry and applies any scheduled resume in memory.
+/// The flag reports whether the entry is still in the `LegacyStream` layout.
+fn read_stream(env: &Env, stream_id: u64) -> Option<(Stream, bool)> {
+    let raw: Val = env
+        .storage()
+        .persistent()
+        .get(&DataKey::Stream(stream_id))?;
+    let (mut stream, legacy) = decode_stream(env, &raw)?;
+    if let (true, Some(resume_at)) = (stream.paused, stream.resume_at) {
+        if env.ledger().timestamp() >= resume_at {
+            end_pause(&mut stream, resume_at);
+        }
+    }
+    Some((stream, legacy))
+}
+
+/// Lifts a pause as of `resumed_at`.
+///
+/// Shifts `last_update_time` and `end_time` by the pause length so the paused
+/// window never accrues, and adds it to `total_paused_duration`. Shared by
+/// `resume_stream` and the scheduled resume applied on load.
+pub fn end_pause(stream: &mut Stream, resumed_at: u64) {
+    let pause_duration = resumed_at.saturating_sub(stream.paused_at.unwrap_or(resumed_at));
+    stream.last_update_time = stream.last_update_time.saturating_add(pause_duration);
+    stream.end_time = stream.end_time.saturating_add(pause_duration);
+    stream.total_paused_duration = stream.total_paused_duration.saturating_add(pause_duration);
+    stream.paused = false;
+    stream.paused_at = None;
+    stream.resume_at = None;
+    stream.status = StreamStatus::Active;
+}
+
+/// Decodes a persisted stream, accepting the current layout or `LegacyStream`.
+///
+/// The layout is picked by inspecting the entry's field names first, because
+/// decoding a struct map with the wrong field count traps the host rather
+/// than returning an error. Legacy entries are upgraded in memory: their
+/// vested amount at `last_update_time` equals `withdrawn_amount` (each
+/// withdrawal advanced the anchor), and every newer field takes its
+/// creation-time default. The flag is `true` for a legacy entry.
+fn decode_stream(env: &Env, raw: &Val) -> Option<(Stream, bool)> {
+    let fields = Map::<Symbol, Val>::try_from_val(env, raw).ok()?;
+    if fields.contains_key(Symbol::new(env, "accrued_checkpoint")) {
+        return Some((Stream::try_from_val(env, raw).ok()?, false));
+    }
+    let legacy = LegacyStream::try_from_val(env, raw).ok()?;
+    // Legacy streams had no nominal end; keep the one their rate implies.
+    let remaining = legacy
+        .deposited_amount
+        .saturating_sub(legacy.withdrawn_amount)
+        .max(0);
+    let rate = legacy.rate_per_second.max(1);
+    let seconds = remaining / rate + i128::from(remaining % rate > 0);
+    let end_time = legacy
+        .last_update_time
+        .saturating_add(u64::try_from(seconds).unwrap_or(u64::MAX));
+    let stream = Stream {
+        sender: legacy.sender,
+        recipient: legacy.recipient,
+        token_address: legacy.token_address,
+        rate_per_second: legacy.rate_per_second,
+        deposited_amount: legacy.deposited_amount,
+        withdrawn_amount: legacy.withdrawn_amount,
+        accrued_checkpoint: legacy.withdrawn_amount,
+        start_time: legacy.start_time,
+        last_update_time: legacy.last_update_time,
+        is_active: legacy.is_active,
+        paused: legacy.paused,
+        paused_at: legacy.paused_at,
+        winding_down: false,
+        status: legacy.status,
+        created_by: Symbol::new(env, "direct"),
+        recipient_must_ack_topups: false,
+        first_withdraw_delay: 0,
+        withdraw_gate: None,
+        total_paused_duration: 0,
+        is_public: true,
+        finalized: false,
+        initial_unlock: 0,
+        milestones: None,
+        end_time,
+        cliff_time: legacy.start_time,
+        memo: None,
+        resume_at: None,
+        rate_unit: RateUnit::PerSecond,
+    };
+    Some((stream, true))
+}
+
+/// Extends the TTL of a stream entry if it has dropped below the threshold.
+///
+/// The caller must ensure the entry exists.
+pub fn bump_stream_ttl(env: &Env, stream_id: u64) {
+    env.storage().persistent().extend_ttl(
+        &DataKey::Stream(stream_id),
+        STREAM_TTL_THRESHOLD,
+        STREAM_TTL_EXTEND_TO,
+    );
+}
+
+/// Extends the TTL of a stream entry if it exists; returns whether it did.
+pub fn try_bump_stream_ttl(env: &Env, stream_id: u64) -> bool {
+    if !env.storage().persistent().has(&DataKey::Stream(stream_id)) {
+        return false;
+    }
+    bump_stream_ttl(env, stream_id);
+    true
+}
+
+/// Extends the TTL of a non-stream persistent entry, on the same policy as
+/// streams. The caller must ensure the entry exists.
+fn bump_entry_ttl(env: &Env, key: &DataKey) {
+    env.storage()
+        .persistent()
+        .extend_ttl(key, STREAM_TTL_THRESHOLD, STREAM_TTL_EXTEND_TO);
+}
+
+/// Reads a persistent entry, extending its TTL when present.
+///
+/// Per-token and per-account entries go through here and `set_persistent`
+/// so that entries which are read often but rarely rewritten (config, fee
+/// exemptions) are not archived out from under the contract.
+fn get_persistent<V: TryFromVal<Env, Val>>(env: &Env, key: &DataKey) -> Option<V> {
+    let value = env.storage().persistent().get(key)?;
+    bump_entry_ttl(env, key);
+    Some(value)
+}
+
+/// Writes a persistent entry and extends its TTL.
+fn set_persistent<V: IntoVal<Env, Val>>(env: &Env, key: &DataKey, value: &V) {
+    env.storage().persistent().set(key, value);
+    bump_entry_ttl(env, key);
+}
+
+/// Returns the net top-up amount parked on a stream, or 0 if none.
+pub fn load_pending_topup(env: &Env, stream_id: u64) -> i128 {
+    get_persistent(env, &DataKey::PendingTopUp(stream_id)).unwrap_or(0)
+}
+
+/// Returns the tithe configured on `stream_id`, if any.
+pub fn load_tithe(env: &Env, stream_id: u64) -> Option<Tithe> {
+    get_persistent(env, &DataKey::Tithe(stream_id))
+}
+
+/// Persists the tithe for `stream_id` (`None` removes it).
+pub fn save_tithe(env: &Env, stream_id: u64, tithe: Option<Tithe>) {
+    let key = DataKey::Tithe(stream_id);
+    match tithe {
+        Some(tithe) => set_persistent(env, &key, &tithe),
+        None => env.storage().persistent().remove(&key),
+    }
+}
+
+/// Returns the `withdraw_to` destinations approved for `stream_id`; empty
+/// means any destination is allowed.
+pub fn load_allowed_destinations(env: &Env, stream_id: u64) -> Vec<Address> {
+    get_persistent(env, &DataKey::AllowedDestinations(stream_id)).unwrap_or(Vec::new(env))
+}
+
+/// Persists the approved destinations for `stream_id`, removing the entry
+/// when the list is empty.
+pub fn save_allowed_destinations(env: &Env, stream_id: u64, dests: &Vec<Address>) {
+    let key = DataKey::AllowedDestinations(stream_id);
+    if dests.is_empty() {
+        env.storage().persistent().remove(&key);
+    } else {
+        set_persistent(env, &key, dests);
+    }
+}
+
+/// Persists the parked top-up amount, removing the entry when it reaches 0.
+pub fn save_pending_topup(env: &Env, stream_id: u64, amount: i128) {
+    let key = DataKey::PendingTopUp(stream_id);
+    if amount > 0 {
+        set_persistent(env, &key, &amount);
+    } else {
+        env.storage().persistent().remove(&key);
+    }
 }
 
 // ─── Protocol Config ──────────────────────────────────────────────────────────
@@ -77,3 +318,466 @@ pub fn save_config(env: &Env, config: &ProtocolConfig) {
 pub fn try_load_config(env: &Env) -> Option<ProtocolConfig> {
     env.storage().instance().get(&DataKey::ProtocolConfig)
 }
+
+/// Returns the configured maximum stream duration, or 0 if unlimited.
+pub fn load_max_duration(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MaxDuration)
+        .unwrap_or(0)
+}
+
+/// Persists the maximum stream duration (0 disables the limit).
+pub fn save_max_duration(env: &Env, max_duration: u64) {
+    env.storage()
+        .instance()
+        .set(&DataKey::MaxDuration, &max_duration);
+}
+
+/// Returns whether deposits smaller than the duration are rejected up front.
+pub fn load_strict_deposit_check(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&DataKey::StrictDepositCheck)
+        .unwrap_or(false)
+}
+
+/// Enables or disables the strict deposit check.
+pub fn save_strict_deposit_check(env: &Env, strict: bool) {
+    if strict {
+        env.storage()
+            .instance()
+            .set(&DataKey::StrictDepositCheck, &true);
+    } else {
+        env.storage()
+            .instance()
+            .remove(&DataKey::StrictDepositCheck);
+    }
+}
+
+/// Returns the exposure cap for `token`, or 0 if unlimited.
+pub fn load_max_exposure(env: &Env, token: &Address) -> i128 {
+    get_persistent(env, &DataKey::MaxExposure(token.clone())).unwrap_or(0)
+}
+
+/// Persists the exposure cap for `token` (0 disables the cap).
+pub fn save_max_exposure(env: &Env, token: &Address, max_exposure: i128) {
+    let key = DataKey::MaxExposure(token.clone());
+    if max_exposure > 0 {
+        set_persistent(env, &key, &max_exposure);
+    } else {
+        env.storage().persistent().remove(&key);
+    }
+}
+
+/// Returns the fee-rate override for `token`, or `None` to use the global rate.
+pub fn load_token_fee_bps(env: &Env, token: &Address) -> Option<u32> {
+    get_persistent(env, &DataKey::TokenFeeBps(token.clone()))
+}
+
+/// Sets (`Some`) or clears (`None`) the fee-rate override for `token`.
+pub fn save_token_fee_bps(env: &Env, token: &Address, fee_bps: Option<u32>) {
+    let key = DataKey::TokenFeeBps(token.clone());
+    match fee_bps {
+        Some(bps) => set_persistent(env, &key, &bps),
+        None => env.storage().persistent().remove(&key),
+    }
+}
+
+/// Returns the earnings-event step for `token`, or 0 if disabled.
+pub fn load_earnings_threshold(env: &Env, token: &Address) -> i128 {
+    get_persistent(env, &DataKey::EarningsThreshold(token.clone())).unwrap_or(0)
+}
+
+/// Persists the earnings-event step for `token` (0 disables the events).
+pub fn save_earnings_threshold(env: &Env, token: &Address, threshold: i128) {
+    let key = DataKey::EarningsThreshold(token.clone());
+    if threshold > 0 {
+        set_persistent(env, &key, &threshold);
+    } else {
+        env.storage().persistent().remove(&key);
+    }
+}
+
+/// Returns the dust threshold for `token` (0 = disabled).
+pub fn load_dust_threshold(env: &Env, token: &Address) -> i128 {
+    get_persistent(env, &DataKey::DustThreshold(token.clone())).unwrap_or(0)
+}
+
+/// Persists the dust threshold for `token` (0 disables sweeping).
+pub fn save_dust_threshold(env: &Env, token: &Address, threshold: i128) {
+    let key = DataKey::DustThreshold(token.clone());
+    if threshold > 0 {
+        set_persistent(env, &key, &threshold);
+    } else {
+        env.storage().persistent().remove(&key);
+    }
+}
+
+/// Returns the lifetime amount paid to `recipient` in `token`.
+pub fn load_recipient_earnings(env: &Env, recipient: &Address, token: &Address) -> i128 {
+    get_persistent(
+        env,
+        &DataKey::RecipientEarnings(recipient.clone(), token.clone()),
+    )
+    .unwrap_or(0)
+}
+
+/// Persists the lifetime amount paid to `recipient` in `token`.
+pub fn save_recipient_earnings(env: &Env, recipient: &Address, token: &Address, total: i128) {
+    set_persistent(
+        env,
+        &DataKey::RecipientEarnings(recipient.clone(), token.clone()),
+        &total,
+    );
+}
+
+/// Returns whether `token` has been marked defunct.
+pub fn is_token_defunct(env: &Env, token: &Address) -> bool {
+    get_persistent(env, &DataKey::DefunctToken(token.clone())).unwrap_or(false)
+}
+
+/// Marks `token` defunct. Irreversible.
+pub fn save_token_defunct(env: &Env, token: &Address) {
+    set_persistent(env, &DataKey::DefunctToken(token.clone()), &true);
+}
+
+/// Returns whether the admin currently holds the contract paused.
+pub fn is_contract_paused(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&DataKey::ContractPaused)
+        .unwrap_or(false)
+}
+
+/// Engages or releases the admin pause.
+pub fn set_contract_paused(env: &Env, paused: bool) {
+    if paused {
+        env.storage()
+            .instance()
+            .set(&DataKey::ContractPaused, &true);
+    } else {
+        env.storage().instance().remove(&DataKey::ContractPaused);
+    }
+}
+
+/// Returns whether every stream id has been checked for the legacy layout.
+pub fn is_legacy_migrated(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&DataKey::LegacyMigrated)
+        .unwrap_or(false)
+}
+
+/// Records that no stream id can still hold a legacy entry.
+pub fn set_legacy_migrated(env: &Env) {
+    env.storage()
+        .instance()
+        .set(&DataKey::LegacyMigrated, &true);
+}
+
+/// Returns the next stream id `migrate_legacy_streams` will check.
+pub fn legacy_migration_cursor(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKey::LegacyMigrationCursor)
+        .unwrap_or(1)
+}
+
+/// Persists the next stream id `migrate_legacy_streams` will check.
+pub fn save_legacy_migration_cursor(env: &Env, next_id: u64) {
+    env.storage()
+        .instance()
+        .set(&DataKey::LegacyMigrationCursor, &next_id);
+}
+
+/// Returns whether a token-transferring call currently holds the reentrancy lock.
+pub fn is_locked(env: &Env) -> bool {
+    env.storage()
+        .temporary()
+        .get(&DataKey::Locked)
+        .unwrap_or(false)
+}
+
+/// Takes or releases the reentrancy lock.
+///
+/// The lock lives in temporary storage and only ever spans one invocation.
+pub fn set_locked(env: &Env, locked: bool) {
+    if locked {
+        env.storage().temporary().set(&DataKey::Locked, &true);
+    } else {
+        env.storage().temporary().remove(&DataKey::Locked);
+    }
+}
+
+/// Returns whether the circuit breaker currently holds the protocol paused.
+pub fn is_globally_paused(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&DataKey::GlobalPaused)
+        .unwrap_or(false)
+}
+
+/// Engages or releases the global pause.
+pub fn set_globally_paused(env: &Env, paused: bool) {
+    if paused {
+        env.storage().instance().set(&DataKey::GlobalPaused, &true);
+    } else {
+        env.storage().instance().remove(&DataKey::GlobalPaused);
+    }
+}
+
+/// Returns the circuit-breaker limit for `token`, if one is configured.
+pub fn load_circuit_breaker(env: &Env, token: &Address) -> Option<CircuitBreakerConfig> {
+    get_persistent(env, &DataKey::CircuitBreaker(token.clone()))
+}
+
+/// Persists the circuit-breaker limit for `token` (`None` disables it).
+pub fn save_circuit_breaker(env: &Env, token: &Address, config: Option<CircuitBreakerConfig>) {
+    let key = DataKey::CircuitBreaker(token.clone());
+    match config {
+        Some(config) => set_persistent(env, &key, &config),
+        None => env.storage().persistent().remove(&key),
+    }
+}
+
+/// Returns the withdrawal volume in `token`'s current breaker window, if any.
+pub fn load_withdrawal_window(env: &Env, token: &Address) -> Option<WithdrawalWindow> {
+    get_persistent(env, &DataKey::WithdrawalWindow(token.clone()))
+}
+
+/// Persists the withdrawal volume in `token`'s current breaker window.
+pub fn save_withdrawal_window(env: &Env, token: &Address, window: &WithdrawalWindow) {
+    set_persistent(env, &DataKey::WithdrawalWindow(token.clone()), window);
+}
+
+/// Returns the registered asset kind for `token`, defaulting to `Plain`.
+pub fn load_asset_kind(env: &Env, token: &Address) -> StreamAssetKind {
+    get_persistent(env, &DataKey::AssetKind(token.clone())).unwrap_or(StreamAssetKind::Plain)
+}
+
+/// Persists the asset kind for `token`.
+pub fn save_asset_kind(env: &Env, token: &Address, kind: StreamAssetKind) {
+    set_persistent(env, &DataKey::AssetKind(token.clone()), &kind);
+}
+
+/// Returns `true` if `address` has been marked fee-exempt by the admin.
+pub fn load_fee_exempt(env: &Env, address: &Address) -> bool {
+    get_persistent(env, &DataKey::FeeExempt(address.clone())).unwrap_or(false)
+}
+
+/// Sets or clears the fee exemption for `address`.
+///
+/// Clearing removes the entry entirely so non-exempt addresses cost no storage.
+pub fn save_fee_exempt(env: &Env, address: &Address, exempt: bool) {
+    let key = DataKey::FeeExempt(address.clone());
+    if exempt {
+        set_persistent(env, &key, &true);
+    } else {
+        env.storage().persistent().remove(&key);
+    }
+}
+
+// ─── Protocol Aggregates ──────────────────────────────────────────────────────
+
+/// Returns the number of currently active streams.
+pub fn active_stream_count(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKey::ActiveStreamCount)
+        .unwrap_or(0)
+}
+
+/// Increments the active stream counter. Called once per created stream, and
+/// once per active legacy stream as it is migrated.
+pub fn increment_active_streams(env: &Env) {
+    let count = active_stream_count(env) + 1;
+    env.storage()
+        .instance()
+        .set(&DataKey::ActiveStreamCount, &count);
+}
+
+/// Decrements the active stream counter. Called exactly once when a stream
+/// transitions to `Cancelled` or `Completed`.
+pub fn decrement_active_streams(env: &Env) {
+    let count = active_stream_count(env).saturating_sub(1);
+    env.storage()
+        .instance()
+        .set(&DataKey::ActiveStreamCount, &count);
+}
+
+/// Returns how many distinct tokens have ever backed a stream.
+pub fn tracked_token_count(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::TrackedTokenCount)
+        .unwrap_or(0)
+}
+
+/// Returns the `index`-th token to back a stream, in first-seen order.
+pub fn tracked_token(env: &Env, index: u32) -> Option<Address> {
+    get_persistent(env, &DataKey::TrackedToken(index))
+}
+
+/// Loads the running totals for `token`, defaulting to zero.
+pub fn load_token_totals(env: &Env, token: &Address) -> TokenTotals {
+    get_persistent(env, &DataKey::TokenTotals(token.clone())).unwrap_or_default()
+}
+
+/// Persists the running totals for `token`, registering it as the next
+/// `TrackedToken` the first time it is seen.
+pub fn save_token_totals(env: &Env, token: &Address, totals: &TokenTotals) {
+    let key = DataKey::TokenTotals(token.clone());
+    if !env.storage().persistent().has(&key) {
+        let index = tracked_token_count(env);
+        set_persistent(env, &DataKey::TrackedToken(index), token);
+        env.storage()
+            .instance()
+            .set(&DataKey::TrackedTokenCount, &(index + 1));
+    }
+    set_persistent(env, &key, totals);
+}
+
+/// Adjusts the locked (TVL) total for `token` by `delta` (negative on outflow).
+pub fn add_token_locked(env: &Env, token: &Address, delta: i128) {
+    let mut totals = load_token_totals(env, token);
+    totals.total_locked += delta;
+    save_token_totals(env, token, &totals);
+}
+
+/// Records `fee` as collected for `token`.
+pub fn add_token_fees(env: &Env, token: &Address, fee: i128) {
+    let mut totals = load_token_totals(env, token);
+    totals.total_fees_collected += fee;
+    save_token_totals(env, token, &totals);
+}
+
+// ─── Stream Indexes ───────────────────────────────────────────────────────────
+//
+// Lists of stream ids, sorted ascending. Ids are allocated monotonically, so
+// creation appends; only a recipient transfer or a legacy migration inserts
+// an id out of order.
+
+fn load_id_index(env: &Env, key: &DataKey) -> Vec<u64> {
+    get_persistent(env, key).unwrap_or(Vec::new(env))
+}
+
+fn append_id_index(env: &Env, key: DataKey, stream_id: u64) {
+    let mut ids = load_id_index(env, &key);
+    ids.push_back(stream_id);
+    set_persistent(env, &key, &ids);
+}
+
+/// Inserts `stream_id` at its sorted position; a no-op if already present.
+fn insert_id_index(env: &Env, key: DataKey, stream_id: u64) {
+    let mut ids = load_id_index(env, &key);
+    if let Err(at) = ids.binary_search(stream_id) {
+        ids.insert(at, stream_id);
+        set_persistent(env, &key, &ids);
+    }
+}
+
+/// Returns the ids of all streams created for `token`, oldest first.
+pub fn token_stream_ids(env: &Env, token: &Address) -> Vec<u64> {
+    load_id_index(env, &DataKey::TokenStreams(token.clone()))
+}
+
+/// Appends `stream_id` to the index for `token` and bumps the index TTL.
+pub fn index_token_stream(env: &Env, token: &Address, stream_id: u64) {
+    append_id_index(env, DataKey::TokenStreams(token.clone()), stream_id);
+}
+
+/// Returns the ids of all streams sent by `sender`, oldest first.
+pub fn sender_stream_ids(env: &Env, sender: &Address) -> Vec<u64> {
+    load_id_index(env, &DataKey::SenderStreams(sender.clone()))
+}
+
+/// Returns the ids of all streams paying `recipient`, oldest first.
+pub fn recipient_stream_ids(env: &Env, recipient: &Address) -> Vec<u64> {
+    load_id_index(env, &DataKey::RecipientStreams(recipient.clone()))
+}
+
+/// Moves `stream_id` from `old_recipient`'s index into `new_recipient`'s,
+/// keeping both sorted.
+pub fn reindex_recipient_stream(
+    env: &Env,
+    old_recipient: &Address,
+    new_recipient: &Address,
+    stream_id: u64,
+) {
+    move_id_index(
+        env,
+        DataKey::RecipientStreams(old_recipient.clone()),
+        DataKey::RecipientStreams(new_recipient.clone()),
+        stream_id,
+    );
+}
+
+/// Returns the ids of all private streams sent or received by `party`,
+/// oldest first.
+pub fn private_stream_ids(env: &Env, party: &Address) -> Vec<u64> {
+    load_id_index(env, &DataKey::PrivateStreams(party.clone()))
+}
+
+/// Appends a private `stream_id` to both parties' private indexes.
+pub fn index_private_streams(env: &Env, sender: &Address, recipient: &Address, stream_id: u64) {
+    append_id_index(env, DataKey::PrivateStreams(sender.clone()), stream_id);
+    append_id_index(env, DataKey::PrivateStreams(recipient.clone()), stream_id);
+}
+
+/// Moves a private `stream_id` from `old_recipient`'s private index into
+/// `new_recipient`'s, keeping both sorted.
+pub fn reindex_private_stream(
+    env: &Env,
+    old_recipient: &Address,
+    new_recipient: &Address,
+    stream_id: u64,
+) {
+    move_id_index(
+        env,
+        DataKey::PrivateStreams(old_recipient.clone()),
+        DataKey::PrivateStreams(new_recipient.clone()),
+        stream_id,
+    );
+}
+
+/// Removes `stream_id` from the index at `old_key` and inserts it into the
+/// one at `new_key`.
+fn move_id_index(env: &Env, old_key: DataKey, new_key: DataKey, stream_id: u64) {
+    let mut old_ids = load_id_index(env, &old_key);
+    if let Some(i) = old_ids.first_index_of(stream_id) {
+        old_ids.remove(i);
+        set_persistent(env, &old_key, &old_ids);
+    }
+    insert_id_index(env, new_key, stream_id);
+}
+
+/// Appends `stream_id` to the sender and recipient indexes.
+pub fn index_party_streams(env: &Env, sender: &Address, recipient: &Address, stream_id: u64) {
+    append_id_index(env, DataKey::SenderStreams(sender.clone()), stream_id);
+    append_id_index(env, DataKey::RecipientStreams(recipient.clone()), stream_id);
+}
+
+// ─── Bundles ──────────────────────────────────────────────────────────────────
+
+/// Returns the next bundle ID and persists the updated counter. IDs start at 1.
+pub fn next_bundle_id(env: &Env) -> u64 {
+    let id: u64 = env
+        .storage()
+        .instance()
+        .get(&DataKey::BundleCounter)
+        .unwrap_or(0)
+        + 1;
+    env.storage().instance().set(&DataKey::BundleCounter, &id);
+    id
+}
+
+/// Returns the stream ids in bundle `bundle_id`, or an empty vector.
+pub fn load_bundle(env: &Env, bundle_id: u64) -> Vec<u64> {
+    load_id_index(env, &DataKey::Bundle(bundle_id))
+}
+
+/// Persists the stream ids of bundle `bundle_id` and bumps its TTL.
+pub fn save_bundle(env: &Env, bundle_id: u64, stream_ids: &Vec<u64>) {
+    set_persistent(env, &DataKey::Bundle(bundle_id), stream_ids);
+}
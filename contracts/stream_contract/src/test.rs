@@ -3,16 +3,22 @@ extern crate std;
 use super::*;
 use soroban_sdk::{
     testutils::{Address as _, Events, Ledger},
-    token, xdr, Address, Env, Symbol, TryFromVal,
+    token, xdr, Address, BytesN, Env, Symbol, TryFromVal,
 };
 
 use errors::StreamError;
 use events::{
-    AdminTransferredEvent, FeeCollectedEvent, FeeConfigUpdatedEvent, InitializedEvent,
-    StreamCancelledEvent, StreamCompletedEvent, StreamCreatedEvent, StreamPausedEvent,
-    StreamResumedEvent, StreamToppedUpEvent, TokensWithdrawnEvent,
+    AdminTransferredEvent, CircuitBreakerTrippedEvent, ContractPauseUpdatedEvent,
+    FeeCollectedEvent, FeeConfigUpdatedEvent, FeeExemptionUpdatedEvent, ForceClosedEvent,
+    InitializedEvent, RecipientEarningsEvent, RecipientTransferredEvent, StreamCancelledEvent,
+    StreamClosedEvent, StreamCompletedEvent, StreamCreatedEvent, StreamPausedEvent,
+    StreamResumedEvent, StreamSettledEvent, StreamToppedUpEvent, StreamWindingDownEvent,
+    TokensWithdrawnEvent, TopUpParkedEvent,
+};
+use types::{
+    AttentionReason, CancelMode, CancellerRole, CircuitBreakerConfig, DataKey, RateUnit, Stream,
+    StreamAssetKind, StreamFlags, StreamOptions, StreamStatus, Tithe,
 };
-use types::{DataKey, Stream, StreamStatus};
 
 // ─── Test Helpers ─────────────────────────────────────────────────────────────
 
@@ -35,6 +41,84 @@ fn mint(env: &Env, token_address: &Address, recipient: &Address, amount: i128) {
     asset.mint(recipient, &amount);
 }
 
+/// Mocks all auths and registers a fresh token. Returns
+/// `(token, sender, recipient)` with `balance` minted to the sender.
+fn funded_parties(env: &Env, balance: i128) -> (Address, Address, Address) {
+    env.mock_all_auths();
+    let (token, _) = create_token(env);
+    let sender = Address::generate(env);
+    let recipient = Address::generate(env);
+    mint(env, &token, &sender, balance);
+    (token, sender, recipient)
+}
+
+/// `funded_parties` plus a freshly registered contract. Returns
+/// `(client, token, sender, recipient)`.
+fn setup(env: &Env, balance: i128) -> (StreamContractClient<'_>, Address, Address, Address) {
+    let (token, sender, recipient) = funded_parties(env, balance);
+    (create_contract(env), token, sender, recipient)
+}
+
+/// Creates a 1 000-token, 100 s stream with `options`, returning the
+/// contract error instead of panicking.
+fn try_create_with_options(
+    client: &StreamContractClient<'_>,
+    token: &Address,
+    sender: &Address,
+    recipient: &Address,
+    options: &StreamOptions,
+) -> Result<u64, StreamError> {
+    client
+        .try_create_stream_with_options(sender, recipient, token, &1_000, &100, options)
+        .map(|r| r.unwrap())
+        .map_err(|e| e.unwrap())
+}
+
+/// A `Stream` as `create_stream` would build it at `start_time = 0` with random
+/// parties and no fee. Override fields with struct-update syntax.
+fn test_stream(env: &Env, rate_per_second: i128, deposited_amount: i128) -> Stream {
+    Stream {
+        sender: Address::generate(env),
+        recipient: Address::generate(env),
+        token_address: Address::generate(env),
+        rate_per_second,
+        deposited_amount,
+        withdrawn_amount: 0,
+        accrued_checkpoint: 0,
+        start_time: 0,
+        last_update_time: 0,
+        is_active: true,
+        paused: false,
+        paused_at: None,
+        winding_down: false,
+        status: StreamStatus::Active,
+        created_by: Symbol::new(env, "direct"),
+        recipient_must_ack_topups: false,
+        first_withdraw_delay: 0,
+        withdraw_gate: None,
+        total_paused_duration: 0,
+        is_public: true,
+        finalized: false,
+        initial_unlock: 0,
+        milestones: None,
+        end_time: (deposited_amount / rate_per_second.max(1)) as u64,
+        cliff_time: 0,
+        memo: None,
+        resume_at: None,
+        rate_unit: RateUnit::PerSecond,
+    }
+}
+
+/// Persists `stream` under a fresh id without moving tokens or touching
+/// counters and indexes. Returns the id.
+fn persist_stream(env: &Env, client: &StreamContractClient<'_>, stream: &Stream) -> u64 {
+    env.as_contract(&client.address, || {
+        let id = storage::next_stream_id(env);
+        storage::save_stream(env, id, stream);
+        id
+    })
+}
+
 // ─── DataKey Serialization ────────────────────────────────────────────────────
 
 #[test]
@@ -58,18 +142,11 @@ fn test_datakey_stream_serializes_deterministically() {
 
     // Confirm persistent storage round-trip inside the contract context.
     let stream = Stream {
-        sender: Address::generate(&env),
-        recipient: Address::generate(&env),
-        token_address: Address::generate(&env),
-        rate_per_second: 100,
-        deposited_amount: 1_000,
-        withdrawn_amount: 0,
         start_time: 1,
         last_update_time: 1,
-        is_active: true,
-        paused: false,
-        paused_at: None,
-        status: StreamStatus::Active,
+        end_time: 11,
+        cliff_time: 1,
+        ..test_stream(&env, 100, 1_000)
     };
     env.as_contract(&contract_id, || {
         env.storage().persistent().set(&key, &stream);
@@ -86,6 +163,29 @@ fn test_datakey_stream_counter_serializes_deterministically() {
     assert_eq!(scval_a, scval_b);
 }
 
+#[test]
+fn test_persist_stream_helper_round_trips_through_views() {
+    let env = Env::default();
+    let client = create_contract(&env);
+    let stream = Stream {
+        withdrawn_amount: 200,
+        accrued_checkpoint: 200,
+        last_update_time: 20,
+        ..test_stream(&env, 10, 1_000)
+    };
+
+    let id = persist_stream(&env, &client, &stream);
+    assert_eq!(client.get_stream(&id), Some(stream));
+    assert_eq!(
+        persist_stream(&env, &client, &test_stream(&env, 1, 1)),
+        id + 1
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 50);
+    assert_eq!(client.get_claimable_amount(&id), Some(300));
+    assert_eq!(client.preview_stream(&id).unwrap().end_time, 100);
+}
+
 // ─── Protocol Initialization ──────────────────────────────────────────────────
 
 #[test]
@@ -119,6 +219,36 @@ fn test_initialize_rejects_second_call() {
     assert_eq!(result, Err(Ok(StreamError::AlreadyInitialized)));
 }
 
+#[test]
+fn test_initialize_second_call_does_not_overwrite_config() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = create_contract(&env);
+
+    let admin = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    client.initialize(&admin, &treasury, &100);
+
+    // A different would-be admin cannot take over by re-initializing.
+    let intruder = Address::generate(&env);
+    assert_eq!(
+        client.try_initialize(&intruder, &intruder, &1_000),
+        Err(Ok(StreamError::AlreadyInitialized))
+    );
+    let cfg = client.get_fee_config().unwrap();
+    assert_eq!(cfg.admin, admin);
+    assert_eq!(cfg.treasury, treasury);
+    assert_eq!(cfg.fee_rate_bps, 100);
+
+    // Legitimate changes go through the admin-gated reconfigure path.
+    let new_treasury = Address::generate(&env);
+    client.update_fee_config(&admin, &new_treasury, &200);
+    let cfg = client.get_fee_config().unwrap();
+    assert_eq!(cfg.admin, admin);
+    assert_eq!(cfg.treasury, new_treasury);
+    assert_eq!(cfg.fee_rate_bps, 200);
+}
+
 #[test]
 fn test_initialize_rejects_invalid_fee_rate() {
     let env = Env::default();
@@ -255,13 +385,7 @@ fn test_update_fee_config_emits_event() {
 #[test]
 fn test_create_stream_persists_state() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let stream_id = client.create_stream(&sender, &recipient, &token, &500, &100);
     assert_eq!(stream_id, 1);
 
@@ -278,12 +402,7 @@ fn test_create_stream_persists_state() {
 #[test]
 fn test_create_multiple_streams_increments_id() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 2_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 2_000);
     let id1 = client.create_stream(&sender, &Address::generate(&env), &token, &500, &100);
     let id2 = client.create_stream(&sender, &Address::generate(&env), &token, &500, &100);
     assert_eq!(id1, 1);
@@ -293,45 +412,35 @@ fn test_create_multiple_streams_increments_id() {
 #[test]
 fn test_create_stream_rejects_zero_amount() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
 
-    let result = client.try_create_stream(
-        &Address::generate(&env),
-        &Address::generate(&env),
-        &token,
-        &0,
-        &100,
-    );
+    let result = client.try_create_stream(&sender, &Address::generate(&env), &token, &0, &100);
     assert_eq!(result, Err(Ok(StreamError::InvalidAmount)));
+
+    // No tokens moved.
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&sender), 1_000);
+    assert_eq!(token_client.balance(&client.address), 0);
 }
 
 #[test]
 fn test_create_stream_rejects_negative_amount() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
 
-    let result = client.try_create_stream(
-        &Address::generate(&env),
-        &Address::generate(&env),
-        &token,
-        &-1,
-        &100,
-    );
+    let result = client.try_create_stream(&sender, &Address::generate(&env), &token, &-1, &100);
     assert_eq!(result, Err(Ok(StreamError::InvalidAmount)));
+
+    // No tokens moved.
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&sender), 1_000);
+    assert_eq!(token_client.balance(&client.address), 0);
 }
 
 #[test]
 fn test_create_stream_rejects_zero_duration() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
 
     let result = client.try_create_stream(&sender, &Address::generate(&env), &token, &500, &0);
     assert_eq!(result, Err(Ok(StreamError::InvalidDuration)));
@@ -358,13 +467,7 @@ fn test_create_stream_rejects_invalid_token_address() {
 #[test]
 fn test_create_stream_emits_event() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let stream_id = client.create_stream(&sender, &recipient, &token, &500, &100);
 
     let events = env.events().all();
@@ -389,12 +492,7 @@ fn test_create_stream_emits_event() {
 #[test]
 fn test_top_up_increases_deposited_amount() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 20_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 20_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &10_000, &100);
     client.top_up_stream(&sender, &id, &5_000);
 
@@ -405,12 +503,7 @@ fn test_top_up_increases_deposited_amount() {
 #[test]
 fn test_top_up_rejects_zero_amount() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 20_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 20_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &10_000, &100);
 
     assert_eq!(
@@ -422,12 +515,7 @@ fn test_top_up_rejects_zero_amount() {
 #[test]
 fn test_top_up_rejects_negative_amount() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 20_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 20_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &10_000, &100);
 
     assert_eq!(
@@ -451,13 +539,8 @@ fn test_top_up_rejects_nonexistent_stream() {
 #[test]
 fn test_top_up_rejects_unauthorized_sender() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
+    let (client, token, sender, _) = setup(&env, 20_000);
     let attacker = Address::generate(&env);
-    mint(&env, &token, &sender, 20_000);
-
-    let client = create_contract(&env);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &10_000, &100);
 
     assert_eq!(
@@ -469,12 +552,7 @@ fn test_top_up_rejects_unauthorized_sender() {
 #[test]
 fn test_top_up_rejects_inactive_stream() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 20_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 20_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &10_000, &100);
     client.cancel_stream(&sender, &id);
 
@@ -487,12 +565,7 @@ fn test_top_up_rejects_inactive_stream() {
 #[test]
 fn test_top_up_emits_event() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 20_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 20_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &10_000, &100);
     client.top_up_stream(&sender, &id, &5_000);
 
@@ -514,13 +587,7 @@ fn test_top_up_emits_event() {
 #[test]
 fn test_top_up_preserves_already_accrued_claimable() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 2_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 2_000);
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
     // Recipient vests 900 tokens (rate 1/sec) before the top-up.
@@ -536,13 +603,7 @@ fn test_top_up_preserves_already_accrued_claimable() {
 #[test]
 fn test_top_up_then_cancel_pays_pre_topup_accrued() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 2_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 2_000);
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
     env.ledger().with_mut(|l| l.timestamp += 900);
@@ -563,13 +624,7 @@ fn test_top_up_then_cancel_pays_pre_topup_accrued() {
 #[test]
 fn test_withdraw_transfers_tokens_to_recipient() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let token_client = token::Client::new(&env, &token);
     let id = client.create_stream(&sender, &recipient, &token, &500, &100);
 
@@ -593,13 +648,8 @@ fn test_withdraw_transfers_tokens_to_recipient() {
 #[test]
 fn test_withdraw_rejects_non_recipient() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let attacker = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &500, &100);
 
     assert_eq!(
@@ -623,13 +673,7 @@ fn test_withdraw_rejects_missing_stream() {
 #[test]
 fn test_withdraw_rejects_inactive_stream() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &recipient, &token, &500, &100);
     client.cancel_stream(&sender, &id);
 
@@ -642,13 +686,7 @@ fn test_withdraw_rejects_inactive_stream() {
 #[test]
 fn test_withdraw_emits_event() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &recipient, &token, &500, &100);
 
     // Advance time by 100 seconds to allow full withdrawal (500 tokens / 100 seconds = 5 tokens/sec)
@@ -671,6 +709,102 @@ fn test_withdraw_emits_event() {
     assert_eq!(payload.stream_id, id);
     assert_eq!(payload.recipient, recipient);
     assert_eq!(payload.amount, 500);
+    assert_eq!(payload.remaining, 0);
+}
+
+#[test]
+fn test_withdraw_return_value_and_event_remaining_across_withdrawals() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let token_client = token::Client::new(&env, &token);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    // A full withdrawal leaves nothing withdrawable; taking 200 of the 450
+    // accrued by t=75 leaves 250.
+    for (at, partial, expected_remaining) in [(30, None, 0), (75, Some(200), 250)] {
+        env.ledger().with_mut(|l| l.timestamp = at);
+        let before = token_client.balance(&recipient);
+        let amount = match partial {
+            Some(amount) => client.withdraw_amount(&recipient, &id, &amount),
+            None => client.withdraw(&recipient, &id),
+        };
+        let ev = env
+            .events()
+            .all()
+            .iter()
+            .find(|e| {
+                Symbol::try_from_val(&env, &e.1.get(0).unwrap()).unwrap()
+                    == Symbol::new(&env, "tokens_withdrawn")
+            })
+            .unwrap();
+        let payload = TokensWithdrawnEvent::try_from_val(&env, &ev.2).unwrap();
+
+        assert_eq!(amount, token_client.balance(&recipient) - before);
+        assert_eq!(payload.amount, amount);
+        assert_eq!(payload.remaining, expected_remaining);
+    }
+}
+
+#[test]
+fn test_withdraw_to_pays_destination_not_recipient() {
+    let env = Env::default();
+    let (client, token, sender, recipient, id) = setup_split_stream(&env);
+    let cold_wallet = Address::generate(&env);
+    let token_client = token::Client::new(&env, &token);
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    assert_eq!(client.withdraw_to(&recipient, &id, &cold_wallet), 400);
+    assert_eq!(token_client.balance(&cold_wallet), 400);
+    assert_eq!(token_client.balance(&recipient), 0);
+    assert_eq!(client.get_stream(&id).unwrap().withdrawn_amount, 400);
+    assert_eq!(client.get_recipient_earnings(&recipient, &token), 400);
+
+    assert_eq!(
+        client.try_withdraw_to(&sender, &id, &cold_wallet),
+        Err(Ok(StreamError::Unauthorized))
+    );
+    assert_eq!(
+        client.try_withdraw_to(&recipient, &id, &client.address),
+        Err(Ok(StreamError::InvalidRecipient))
+    );
+}
+
+#[test]
+fn test_withdraw_to_honors_allowed_destinations() {
+    let env = Env::default();
+    let (client, token, sender, recipient, id) = setup_split_stream(&env);
+    let custody = Address::generate(&env);
+    let stranger = Address::generate(&env);
+    let token_client = token::Client::new(&env, &token);
+
+    client.set_allowed_destinations(&recipient, &id, &vec![&env, custody.clone()]);
+    assert_eq!(
+        client.get_allowed_destinations(&id),
+        vec![&env, custody.clone()]
+    );
+    assert_eq!(
+        client.try_set_allowed_destinations(&sender, &id, &Vec::new(&env)),
+        Err(Ok(StreamError::Unauthorized))
+    );
+
+    env.ledger().with_mut(|l| l.timestamp += 20);
+    assert_eq!(
+        client.try_withdraw_to(&recipient, &id, &stranger),
+        Err(Ok(StreamError::DestinationNotAllowed))
+    );
+    assert_eq!(client.withdraw_to(&recipient, &id, &custody), 200);
+    assert_eq!(token_client.balance(&custody), 200);
+
+    // Plain withdraw always pays the recipient and ignores the allow-list.
+    env.ledger().with_mut(|l| l.timestamp += 10);
+    assert_eq!(client.withdraw(&recipient, &id), 100);
+
+    // An empty list lifts the restriction.
+    client.set_allowed_destinations(&recipient, &id, &Vec::new(&env));
+    assert_eq!(client.get_allowed_destinations(&id).len(), 0);
+    env.ledger().with_mut(|l| l.timestamp += 10);
+    assert_eq!(client.withdraw_to(&recipient, &id, &stranger), 100);
+    assert_eq!(token_client.balance(&stranger), 100);
 }
 
 // ─── cancel_stream ────────────────────────────────────────────────────────────
@@ -678,12 +812,7 @@ fn test_withdraw_emits_event() {
 #[test]
 fn test_cancel_stream_refunds_unspent_balance() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let token_client = token::Client::new(&env, &token);
 
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &500, &100);
@@ -701,13 +830,8 @@ fn test_cancel_stream_refunds_unspent_balance() {
 #[test]
 fn test_cancel_stream_rejects_non_sender() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let attacker = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &500, &100);
 
     assert_eq!(
@@ -731,12 +855,7 @@ fn test_cancel_stream_rejects_missing_stream() {
 #[test]
 fn test_cancel_stream_rejects_already_inactive() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &500, &100);
     client.cancel_stream(&sender, &id);
 
@@ -749,13 +868,7 @@ fn test_cancel_stream_rejects_already_inactive() {
 #[test]
 fn test_cancel_stream_emits_event_with_refund_amount() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &recipient, &token, &500, &100);
     client.cancel_stream(&sender, &id);
 
@@ -781,14 +894,9 @@ fn test_cancel_stream_emits_event_with_refund_amount() {
 #[test]
 fn test_create_stream_with_fee_deduction() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let treasury = Address::generate(&env);
     let admin = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
     let token_client = token::Client::new(&env, &token);
 
     // 2% fee (200 bps). Gross: 500, fee: 10, net: 490.
@@ -804,14 +912,9 @@ fn test_create_stream_with_fee_deduction() {
 #[test]
 fn test_top_up_with_fee_deduction() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
+    let (client, token, sender, _) = setup(&env, 2_000);
     let treasury = Address::generate(&env);
     let admin = Address::generate(&env);
-    mint(&env, &token, &sender, 2_000);
-
-    let client = create_contract(&env);
     let token_client = token::Client::new(&env, &token);
 
     // 1% fee (100 bps). Create: gross 1 000, fee 10, net 990.
@@ -830,14 +933,9 @@ fn test_top_up_with_fee_deduction() {
 #[test]
 fn test_fee_collected_event_emitted_on_create() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let treasury = Address::generate(&env);
     let admin = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
 
     // 5% fee (500 bps). Gross: 1 000, fee: 50.
     client.initialize(&admin, &treasury, &500);
@@ -861,14 +959,9 @@ fn test_fee_collected_event_emitted_on_create() {
 #[test]
 fn test_no_fee_event_when_fee_rate_is_zero() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let admin = Address::generate(&env);
     let treasury = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
 
     // 0 bps fee — no fee_collected event must be emitted.
     client.initialize(&admin, &treasury, &0);
@@ -888,14 +981,9 @@ fn test_no_fee_event_when_fee_rate_is_zero() {
 #[test]
 fn test_no_fee_transfer_or_event_when_fee_rounds_to_zero() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let admin = Address::generate(&env);
     let treasury = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
     let token_client = token::Client::new(&env, &token);
 
     // Non-zero fee rate, but tiny amount => fee rounds down to 0:
@@ -922,13 +1010,8 @@ fn test_no_fee_transfer_or_event_when_fee_rounds_to_zero() {
 #[test]
 fn test_no_fee_without_protocol_config() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
+    let (client, token, sender, _) = setup(&env, 1_000);
     // No `initialize` call — fee collection is a silent no-op.
-    let client = create_contract(&env);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &500, &100);
 
     let s = client.get_stream(&id).unwrap();
@@ -938,14 +1021,7 @@ fn test_no_fee_without_protocol_config() {
 #[test]
 fn test_withdraw_time_based_calculation() {
     let env = Env::default();
-    env.mock_all_auths();
-
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let _token_client = token::Client::new(&env, &token);
 
     // Create stream: 1000 tokens over 1000 seconds = 1 token/second
@@ -980,14 +1056,7 @@ fn test_withdraw_time_based_calculation() {
 #[test]
 fn test_withdraw_caps_at_remaining_balance() {
     let env = Env::default();
-    env.mock_all_auths();
-
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let _token_client = token::Client::new(&env, &token);
 
     // Create stream: 100 tokens over 100 seconds = 1 token/second
@@ -1099,16 +1168,28 @@ fn test_cancel_stream_after_partial_withdrawal() {
 }
 
 #[test]
-fn test_claimable_max_i128_rate_overflow() {
+fn test_cancel_settles_unwithdrawn_accrual_to_recipient() {
     let env = Env::default();
-    env.mock_all_auths();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let token_client = token::Client::new(&env, &token);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, i128::MAX);
+    // The recipient never withdraws; cancel pays the frozen accrual itself.
+    env.ledger().with_mut(|l| l.timestamp += 300);
+    client.cancel_stream(&sender, &id);
+    assert_eq!(token_client.balance(&recipient), 300);
 
-    let client = create_contract(&env);
+    assert_eq!(client.get_stream(&id).unwrap().withdrawn_amount, 300);
+    assert_eq!(
+        client.try_withdraw(&recipient, &id),
+        Err(Ok(StreamError::StreamInactive))
+    );
+}
+
+#[test]
+fn test_claimable_max_i128_rate_overflow() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, i128::MAX);
 
     // Create stream with near-max i128 rate
     let max_rate = i128::MAX / 2;
@@ -1142,14 +1223,7 @@ fn test_claimable_max_i128_rate_overflow() {
 #[test]
 fn test_calculate_claimable_underflow_returns_zero() {
     let env = Env::default();
-    env.mock_all_auths();
-
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let stream_id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
     // Forcibly set withdrawn_amount > deposited_amount to exercise the underflow guard.
@@ -1172,12 +1246,7 @@ fn test_calculate_claimable_underflow_returns_zero() {
 #[test]
 fn test_create_stream_minimum_amount() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &1, &1);
     let s = client.get_stream(&id).unwrap();
     assert_eq!(s.deposited_amount, 1);
@@ -1187,12 +1256,7 @@ fn test_create_stream_minimum_amount() {
 #[test]
 fn test_create_stream_minimum_duration() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 100);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 100);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &100, &1);
     let s = client.get_stream(&id).unwrap();
     assert_eq!(s.rate_per_second, 100);
@@ -1240,9 +1304,7 @@ fn test_create_stream_invalid_token() {
 }
 
 #[test]
-fn test_create_stream_self_stream() {
-    // sender == recipient is allowed by the contract (no explicit guard),
-    // but the stream must be created successfully and state must be consistent.
+fn test_create_stream_rejects_self_stream() {
     let env = Env::default();
     env.mock_all_auths();
     let (token, _) = create_token(&env);
@@ -1250,36 +1312,45 @@ fn test_create_stream_self_stream() {
     mint(&env, &token, &actor, 1_000);
 
     let client = create_contract(&env);
-    let id = client.create_stream(&actor, &actor, &token, &1_000, &100);
-    let s = client.get_stream(&id).unwrap();
-    assert_eq!(s.sender, actor);
-    assert_eq!(s.recipient, actor);
+    let result = client.try_create_stream(&actor, &actor, &token, &1_000, &100);
+    assert_eq!(result, Err(Ok(StreamError::InvalidRecipient)));
+
+    // Rejected before the deposit is pulled.
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&actor), 1_000);
+    assert_eq!(token_client.balance(&client.address), 0);
+    assert!(client.get_stream(&1).is_none());
 }
 
 #[test]
 fn test_create_stream_zero_rate() {
     // amount < duration → rate_per_second rounds to 0; must now be rejected.
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1);
     let result = client.try_create_stream(&sender, &Address::generate(&env), &token, &1, &1_000);
     assert_eq!(result, Err(Ok(StreamError::InvalidRate)));
 }
 
+#[test]
+fn test_create_stream_rejects_amount_below_duration() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    // 50 over 100 s would truncate to a zero rate.
+    let result = client.try_create_stream(&sender, &Address::generate(&env), &token, &50, &100);
+    assert_eq!(result, Err(Ok(StreamError::InvalidRate)));
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&sender), 1_000);
+
+    // A shorter duration for the same amount is accepted.
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &50, &50);
+    assert_eq!(client.get_stream(&id).unwrap().rate_per_second, 1);
+}
+
 #[test]
 fn test_create_stream_rate_exactly_one_succeeds() {
     // amount == duration → rate = 1, which is the smallest valid rate.
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 100);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 100);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &100, &100);
     let s = client.get_stream(&id).unwrap();
     assert_eq!(s.rate_per_second, 1);
@@ -1289,13 +1360,7 @@ fn test_create_stream_rate_exactly_one_succeeds() {
 #[test]
 fn test_stream_id_uniqueness() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 2_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 2_000);
     let id1 = client.create_stream(&sender, &recipient, &token, &1_000, &100);
     let id2 = client.create_stream(&sender, &recipient, &token, &1_000, &100);
     assert_ne!(id1, id2);
@@ -1310,13 +1375,7 @@ fn test_stream_id_uniqueness() {
 #[test]
 fn test_withdraw_accrued_amount() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let token_client = token::Client::new(&env, &token);
     // 1_000 tokens / 1_000 s = 1 token/s
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
@@ -1331,13 +1390,7 @@ fn test_withdraw_accrued_amount() {
 fn test_withdraw_zero_balance() {
     // Withdraw before any time elapses → InvalidAmount.
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
     assert_eq!(
@@ -1349,13 +1402,7 @@ fn test_withdraw_zero_balance() {
 #[test]
 fn test_withdraw_full_balance() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 500);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 500);
     let token_client = token::Client::new(&env, &token);
     let id = client.create_stream(&sender, &recipient, &token, &500, &100);
 
@@ -1373,13 +1420,7 @@ fn test_withdraw_full_balance() {
 #[test]
 fn test_withdraw_rejects_double_withdraw_after_completion() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 500);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 500);
     let id = client.create_stream(&sender, &recipient, &token, &500, &100);
 
     // Fully drain the stream via withdraw.
@@ -1402,12 +1443,7 @@ fn test_withdraw_rejects_double_withdraw_after_completion() {
 #[test]
 fn test_top_up_extends_stream() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 2_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 2_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
 
     client.top_up_stream(&sender, &id, &1_000);
@@ -1422,13 +1458,7 @@ fn test_top_up_extends_stream() {
 #[test]
 fn test_top_up_on_completed_stream() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &recipient, &token, &500, &100);
 
     // Drain the stream.
@@ -1446,12 +1476,7 @@ fn test_top_up_on_completed_stream() {
 #[test]
 fn test_cancel_refunds_sender() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let token_client = token::Client::new(&env, &token);
     // 1_000 tokens / 1_000 s = 1 token/s
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &1_000);
@@ -1466,12 +1491,7 @@ fn test_cancel_refunds_sender() {
 #[test]
 fn test_cancel_by_non_sender() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &1_000);
 
     assert_eq!(
@@ -1483,13 +1503,7 @@ fn test_cancel_by_non_sender() {
 #[test]
 fn test_cancel_after_completion() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 500);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 500);
     let id = client.create_stream(&sender, &recipient, &token, &500, &100);
 
     env.ledger().with_mut(|l| l.timestamp += 200);
@@ -1506,13 +1520,7 @@ fn test_cancel_after_completion() {
 #[test]
 fn test_pause_stops_accrual() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     // 1_000 tokens / 1_000 s = 1 token/s
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
@@ -1531,13 +1539,7 @@ fn test_pause_stops_accrual() {
 #[test]
 fn test_resume_adjusts_end_time() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
     env.ledger().with_mut(|l| l.timestamp += 100);
@@ -1560,12 +1562,7 @@ fn test_resume_adjusts_end_time() {
 #[test]
 fn test_pause_emits_event() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &1_000);
     client.pause_stream(&sender, &id);
 
@@ -1586,12 +1583,7 @@ fn test_pause_emits_event() {
 #[test]
 fn test_resume_emits_event() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &1_000);
     client.pause_stream(&sender, &id);
     env.ledger().with_mut(|l| l.timestamp += 100);
@@ -1614,12 +1606,7 @@ fn test_resume_emits_event() {
 #[test]
 fn test_pause_by_non_sender_fails() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &1_000);
 
     assert_eq!(
@@ -1631,12 +1618,7 @@ fn test_pause_by_non_sender_fails() {
 #[test]
 fn test_resume_non_paused_stream_fails() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &1_000);
 
     assert_eq!(
@@ -1648,13 +1630,7 @@ fn test_resume_non_paused_stream_fails() {
 #[test]
 fn test_withdraw_on_paused_stream_fails() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
     env.ledger().with_mut(|l| l.timestamp += 100);
@@ -1672,13 +1648,7 @@ fn test_withdraw_on_paused_stream_fails() {
 #[test]
 fn test_final_withdrawal_transitions_to_completed() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 500);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 500);
     let id = client.create_stream(&sender, &recipient, &token, &500, &100);
 
     env.ledger().with_mut(|l| l.timestamp += 200);
@@ -1692,13 +1662,7 @@ fn test_final_withdrawal_transitions_to_completed() {
 #[test]
 fn test_is_stream_completed_helper() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 500);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 500);
     let id = client.create_stream(&sender, &recipient, &token, &500, &100);
 
     assert!(!client.is_stream_completed(&id));
@@ -1712,13 +1676,7 @@ fn test_is_stream_completed_helper() {
 #[test]
 fn test_completed_event_emitted_on_final_withdrawal() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 500);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 500);
     let id = client.create_stream(&sender, &recipient, &token, &500, &100);
 
     env.ledger().with_mut(|l| l.timestamp += 200);
@@ -1742,13 +1700,7 @@ fn test_completed_event_emitted_on_final_withdrawal() {
 #[test]
 fn test_partial_withdrawal_does_not_complete() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
     env.ledger().with_mut(|l| l.timestamp += 200);
@@ -1763,13 +1715,7 @@ fn test_partial_withdrawal_does_not_complete() {
 #[test]
 fn test_withdraw_on_paused_then_resume() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 10_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 10_000);
     let id = client.create_stream(&sender, &recipient, &token, &10_000, &100);
 
     env.ledger().with_mut(|l| l.timestamp += 50);
@@ -1787,13 +1733,7 @@ fn test_withdraw_on_paused_then_resume() {
 #[test]
 fn test_multiple_pause_resume_preserves_state() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 10_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 10_000);
     let id = client.create_stream(&sender, &recipient, &token, &10_000, &50);
 
     for _ in 0..3 {
@@ -1811,13 +1751,7 @@ fn test_multiple_pause_resume_preserves_state() {
 #[test]
 fn test_cancel_while_paused_keeps_inactive() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
 
     env.ledger().with_mut(|l| l.timestamp += 300);
@@ -1832,65 +1766,53 @@ fn test_cancel_while_paused_keeps_inactive() {
 }
 
 #[test]
-fn test_top_up_while_paused_increases_deposited() {
+fn test_top_up_while_paused_is_rejected() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 2_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 2_000);
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
 
-    env.ledger().with_mut(|l| l.timestamp += 500);
+    env.ledger().with_mut(|l| l.timestamp += 50);
     client.pause_stream(&sender, &id);
 
-    let old_deposited = client.get_stream(&id).unwrap().deposited_amount;
-    client.top_up_stream(&sender, &id, &1_000);
-    let new_deposited = client.get_stream(&id).unwrap().deposited_amount;
-
-    assert!(new_deposited > old_deposited);
-}
+    assert_eq!(
+        client.try_top_up_stream(&sender, &id, &1_000),
+        Err(Ok(StreamError::StreamPaused))
+    );
+    assert_eq!(
+        client.try_preview_topup(&id, &1_000),
+        Err(Ok(StreamError::StreamPaused))
+    );
+    assert_eq!(client.get_stream(&id).unwrap().deposited_amount, 1_000);
+
+    client.resume_stream(&sender, &id);
+    client.top_up_stream(&sender, &id, &1_000);
+    assert_eq!(client.get_stream(&id).unwrap().deposited_amount, 2_000);
+}
 
 #[test]
-fn test_top_up_while_paused_does_not_advance_last_update_time() {
+fn test_rejected_top_up_while_paused_keeps_accrual() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 2_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 2_000);
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
     // Accrue 300s, then pause.
     env.ledger().with_mut(|l| l.timestamp += 300);
     client.pause_stream(&sender, &id);
 
-    // More ledger time passes while paused; top up during this window.
+    // More ledger time passes while paused; the top-up attempt is refused.
     env.ledger().with_mut(|l| l.timestamp += 200);
-    client.top_up_stream(&sender, &id, &100);
+    assert!(client.try_top_up_stream(&sender, &id, &100).is_err());
 
     let stream = client.get_stream(&id).unwrap();
     assert!(stream.last_update_time <= stream.paused_at.unwrap());
-
-    // Claimable should still reflect the 300s accrued before the pause, not be
-    // wiped out by the top-up pushing last_update_time past paused_at.
     assert_eq!(client.get_claimable_amount(&id), Some(300));
+    assert_eq!(token::Client::new(&env, &token).balance(&sender), 1_000);
 }
 
 #[test]
 fn test_withdraw_after_long_stream_runtime_is_bounded() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 5_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 5_000);
     let id = client.create_stream(&sender, &recipient, &token, &5_000, &10);
 
     env.ledger().with_mut(|l| l.timestamp += 10_000);
@@ -2127,12 +2049,8 @@ fn test_fuzz_claimable_overflow_and_cancel_invariants() {
             sender: sender.clone(),
             recipient: recipient.clone(),
             token_address: token_address.clone(),
-            rate_per_second,
-            deposited_amount: deposited,
             withdrawn_amount: withdrawn,
-            start_time: 0,
-            last_update_time: 0,
-            is_active: true,
+            accrued_checkpoint: withdrawn,
             paused,
             paused_at: if paused {
                 Some(effective_elapsed)
@@ -2144,9 +2062,11 @@ fn test_fuzz_claimable_overflow_and_cancel_invariants() {
             } else {
                 StreamStatus::Active
             },
+            end_time: u64::MAX,
+            ..test_stream(&env, rate_per_second, deposited)
         };
 
-        let claimable = StreamContract::calculate_claimable(&stream, elapsed);
+        let claimable = StreamContract::calculate_claimable(&env, &stream, elapsed);
         let remaining = deposited - withdrawn;
         let withdrawn_after_cancel = withdrawn.saturating_add(claimable);
         let cancel_refund = deposited.saturating_sub(withdrawn_after_cancel);
@@ -2276,18 +2196,39 @@ fn test_transfer_admin_emits_event() {
     assert_eq!(payload.new_admin, new_admin);
 }
 
+#[test]
+fn test_upgrade_is_admin_only() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = create_contract(&env);
+    let hash = BytesN::from_array(&env, &[7; 32]);
+    let admin = Address::generate(&env);
+
+    assert_eq!(
+        client.try_upgrade(&admin, &hash),
+        Err(Ok(StreamError::NotInitialized))
+    );
+    client.initialize(&admin, &Address::generate(&env), &0);
+    assert_eq!(
+        client.try_upgrade(&Address::generate(&env), &hash),
+        Err(Ok(StreamError::NotAdmin))
+    );
+
+    // The admin passes the gate; the swap itself then fails because no wasm
+    // with this hash was uploaded.
+    assert!(matches!(client.try_upgrade(&admin, &hash), Err(Err(_))));
+
+    // Without the admin's signature the call is rejected outright.
+    env.mock_auths(&[]);
+    assert!(client.try_upgrade(&admin, &hash).is_err());
+}
+
 // ─── pause_stream / resume_stream (#462) ─────────────────────────────────────
 
 #[test]
 fn test_pause_stops_accrual_462() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
 
     // Stream: 1 000 tokens over 1 000 s → 1 token/s
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
@@ -2314,13 +2255,7 @@ fn test_pause_stops_accrual_462() {
 #[test]
 fn test_withdraw_on_paused_stream_returns_stream_inactive() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
     env.ledger().with_mut(|l| l.timestamp += 100);
@@ -2335,13 +2270,7 @@ fn test_withdraw_on_paused_stream_returns_stream_inactive() {
 #[test]
 fn test_resume_adjusts_last_update_time() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
     // Advance 200 s, pause, then advance 300 s while paused, then resume.
@@ -2352,9 +2281,10 @@ fn test_resume_adjusts_last_update_time() {
 
     let s = client.get_stream(&id).unwrap();
     assert!(!s.paused);
-    // last_update_time = original (0) + pause_duration (300) = 300
-    // because resume_stream shifts it by pause_duration (300).
-    assert_eq!(s.last_update_time, 300);
+    // Pausing checkpoints at 200, and resume_stream shifts that checkpoint
+    // by the pause duration (300): last_update_time = 500.
+    assert_eq!(s.last_update_time, 500);
+    assert_eq!(s.accrued_checkpoint, 200);
 
     // Advance 100 s after resume and withdraw; expect 300 tokens
     // (200 pre-pause + 100 post-resume, since nothing was withdrawn yet).
@@ -2370,13 +2300,7 @@ fn test_resume_adjusts_last_update_time() {
 #[test]
 fn test_cancel_paused_stream_settles_at_paused_at() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 2_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 2_000);
     let token_client = token::Client::new(&env, &token);
 
     // Stream: 1 000 tokens over 1 000 s → 1 token/s
@@ -2405,13 +2329,7 @@ fn test_cancel_paused_stream_settles_at_paused_at() {
 #[test]
 fn test_cancel_paused_stream_emits_correct_event() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
 
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
     env.ledger().with_mut(|l| l.timestamp += 400);
@@ -2439,13 +2357,7 @@ fn test_cancel_paused_stream_emits_correct_event() {
 #[test]
 fn test_resume_then_cancel_settles_across_pause_boundary() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 2_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 2_000);
     let token_client = token::Client::new(&env, &token);
 
     // Stream: 1 000 tokens / 1 000 s → 1 token/s
@@ -2473,12 +2385,7 @@ fn test_resume_then_cancel_settles_across_pause_boundary() {
 #[test]
 fn test_pause_stream_emits_event() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &1_000);
 
     env.ledger().with_mut(|l| l.timestamp += 50);
@@ -2502,18 +2409,13 @@ fn test_pause_stream_emits_event() {
 #[test]
 fn test_resume_stream_emits_event() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, _) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &1_000);
 
     env.ledger().with_mut(|l| l.timestamp += 100);
     client.pause_stream(&sender, &id);
     env.ledger().with_mut(|l| l.timestamp += 50);
-    client.resume_stream(&sender, &id);
+    let new_end_time = client.resume_stream(&sender, &id);
 
     let events = env.events().all();
     let ev = events
@@ -2527,7 +2429,12 @@ fn test_resume_stream_emits_event() {
     let payload: StreamResumedEvent = StreamResumedEvent::try_from_val(&env, &ev.2).unwrap();
     assert_eq!(payload.stream_id, id);
     assert_eq!(payload.sender, sender);
-    assert_eq!(payload.new_end_time, 1150);
+    // 900 left after the 100 s accrued before the pause, from 150 at 1/s.
+    assert_eq!(payload.new_end_time, 1050);
+    assert_eq!(new_end_time, payload.new_end_time);
+    let (_, _, summary_end) = client.get_stream_summary(&id).unwrap();
+    assert_eq!(summary_end, new_end_time);
+    assert_eq!(client.preview_stream(&id).unwrap().end_time, new_end_time);
 }
 
 // ─── CEI / reentrancy regression (#789) ──────────────────────────────────────
@@ -2538,13 +2445,7 @@ fn test_resume_stream_emits_event() {
 #[test]
 fn test_withdraw_state_committed_before_transfer_prevents_double_payout() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     // 1 000 tokens / 1 000 s = 1 token/s
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
@@ -2574,13 +2475,7 @@ fn test_withdraw_state_committed_before_transfer_prevents_double_payout() {
 #[test]
 fn test_cancel_state_committed_before_transfers_prevents_double_cancel() {
     let env = Env::default();
-    env.mock_all_auths();
-    let (token, _) = create_token(&env);
-    let sender = Address::generate(&env);
-    let recipient = Address::generate(&env);
-    mint(&env, &token, &sender, 1_000);
-
-    let client = create_contract(&env);
+    let (client, token, sender, recipient) = setup(&env, 1_000);
     let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
 
     env.ledger().with_mut(|l| l.timestamp += 200);
@@ -2602,3 +2497,5195 @@ fn test_cancel_state_committed_before_transfers_prevents_double_cancel() {
         s.deposited_amount
     );
 }
+
+// ─── get_protocol_stats ───────────────────────────────────────────────────────
+
+#[test]
+fn test_protocol_stats_empty_before_any_stream() {
+    let env = Env::default();
+    let client = create_contract(&env);
+
+    let stats = client.get_protocol_stats();
+    assert_eq!(stats.total_streams, 0);
+    assert_eq!(stats.active_streams, 0);
+    assert_eq!(stats.token_count, 0);
+    assert!(client.get_token_stats(&0, &50).is_empty());
+}
+
+#[test]
+fn test_protocol_stats_track_sequence_of_operations() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token_a, _) = create_token(&env);
+    let (token_b, _) = create_token(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    mint(&env, &token_a, &sender, 10_000);
+    mint(&env, &token_b, &sender, 10_000);
+
+    let client = create_contract(&env);
+    // 1% fee so fee totals are non-trivial.
+    client.initialize(&Address::generate(&env), &Address::generate(&env), &100);
+
+    // token_a: two streams of 1 000 gross → 990 net each.
+    let a1 = client.create_stream(&sender, &recipient, &token_a, &1_000, &99);
+    let a2 = client.create_stream(&sender, &recipient, &token_a, &1_000, &99);
+    // token_b: one stream of 2 000 gross → 1 980 net, then a 1 000 top-up → +990.
+    let b1 = client.create_stream(&sender, &recipient, &token_b, &2_000, &99);
+    client.top_up_stream(&sender, &b1, &1_000);
+
+    env.ledger().with_mut(|l| l.timestamp += 10);
+    let withdrawn_a1 = client.withdraw(&recipient, &a1); // 10 * 10 = 100
+    client.cancel_stream(&sender, &a2); // fully drained from TVL
+
+    let stats = client.get_protocol_stats();
+    assert_eq!(stats.total_streams, 3);
+    assert_eq!(stats.active_streams, 2);
+
+    let totals_a = client
+        .get_token_stats(&0, &50)
+        .get(token_a.clone())
+        .unwrap();
+    assert_eq!(totals_a.total_locked, 990 - withdrawn_a1);
+    assert_eq!(totals_a.total_fees_collected, 20);
+
+    let totals_b = client
+        .get_token_stats(&0, &50)
+        .get(token_b.clone())
+        .unwrap();
+    assert_eq!(totals_b.total_locked, 1_980 + 990);
+    assert_eq!(totals_b.total_fees_collected, 30);
+
+    // TVL must agree with the contract's actual token balances.
+    let token_a_client = token::Client::new(&env, &token_a);
+    let token_b_client = token::Client::new(&env, &token_b);
+    assert_eq!(
+        token_a_client.balance(&client.address),
+        totals_a.total_locked
+    );
+    assert_eq!(
+        token_b_client.balance(&client.address),
+        totals_b.total_locked
+    );
+}
+
+#[test]
+fn test_protocol_stats_active_count_drops_on_completion() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    assert_eq!(client.get_protocol_stats().active_streams, 1);
+
+    env.ledger().with_mut(|l| l.timestamp += 100);
+    client.withdraw(&recipient, &id);
+
+    let stats = client.get_protocol_stats();
+    assert_eq!(stats.active_streams, 0);
+    assert_eq!(
+        client
+            .get_token_stats(&0, &50)
+            .get(token)
+            .unwrap()
+            .total_locked,
+        0
+    );
+}
+
+#[test]
+fn test_protocol_stats_page_through_tokens() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let client = create_contract(&env);
+
+    let mut tokens = std::vec::Vec::new();
+    for _ in 0..3 {
+        let (token, _) = create_token(&env);
+        mint(&env, &token, &sender, 1_000);
+        client.create_stream(&sender, &recipient, &token, &1_000, &100);
+        tokens.push(token);
+    }
+
+    let stats = client.get_protocol_stats();
+    assert_eq!(stats.token_count, 3);
+    assert_eq!(stats.total_streams, 3);
+
+    let first = client.get_token_stats(&0, &2);
+    assert_eq!(first.len(), 2);
+    assert!(first.contains_key(tokens[0].clone()));
+    assert!(first.contains_key(tokens[1].clone()));
+
+    let rest = client.get_token_stats(&2, &2);
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest.get(tokens[2].clone()).unwrap().total_locked, 1_000);
+    assert!(client.get_token_stats(&3, &2).is_empty());
+}
+
+// ─── accrued_checkpoint ───────────────────────────────────────────────────────
+
+#[test]
+fn test_checkpoint_starts_at_zero() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+
+    let s = client.get_stream(&id).unwrap();
+    assert_eq!(s.accrued_checkpoint, 0);
+    assert_eq!(s.last_update_time, s.start_time);
+}
+
+#[test]
+fn test_checkpoint_advances_on_each_withdrawal() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 10_000);
+    // 10 tokens/s over 1 000 s.
+    let id = client.create_stream(&sender, &recipient, &token, &10_000, &1_000);
+    let start = client.get_stream(&id).unwrap().start_time;
+
+    let mut total_withdrawn = 0_i128;
+    for step in [7_u64, 50, 133, 1, 300] {
+        env.ledger().with_mut(|l| l.timestamp += step);
+        total_withdrawn += client.withdraw(&recipient, &id);
+
+        let now = env.ledger().timestamp();
+        let s = client.get_stream(&id).unwrap();
+        // Checkpoint must equal a from-scratch computation from start_time.
+        let from_scratch = (now - start) as i128 * s.rate_per_second;
+        assert_eq!(s.accrued_checkpoint, from_scratch);
+        assert_eq!(s.withdrawn_amount, total_withdrawn);
+        assert_eq!(s.last_update_time, now);
+    }
+}
+
+#[test]
+fn test_checkpoint_with_views_matches_from_scratch_total() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 10_000);
+    let id = client.create_stream(&sender, &recipient, &token, &10_000, &1_000);
+
+    env.ledger().with_mut(|l| l.timestamp += 120);
+    client.withdraw(&recipient, &id);
+
+    // Claimable is computed as a delta from the checkpoint.
+    env.ledger().with_mut(|l| l.timestamp += 80);
+    assert_eq!(client.get_claimable_amount(&id).unwrap(), 800);
+
+    // Run past the end: withdrawn + claimable must equal the full deposit.
+    env.ledger().with_mut(|l| l.timestamp += 5_000);
+    let s = client.get_stream(&id).unwrap();
+    let claimable = client.get_claimable_amount(&id).unwrap();
+    assert_eq!(s.withdrawn_amount + claimable, 10_000);
+}
+
+#[test]
+fn test_checkpoint_freezes_while_paused() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+
+    env.ledger().with_mut(|l| l.timestamp += 100);
+    client.withdraw(&recipient, &id);
+    env.ledger().with_mut(|l| l.timestamp += 50);
+    client.pause_stream(&sender, &id);
+    env.ledger().with_mut(|l| l.timestamp += 400);
+    client.cancel_stream(&sender, &id);
+
+    // Cancel settles at paused_at: 150 s of accrual in total.
+    let s = client.get_stream(&id).unwrap();
+    assert_eq!(s.accrued_checkpoint, 150);
+    assert_eq!(s.withdrawn_amount, 150);
+}
+
+// ─── settle ───────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_settle_updates_checkpoint_without_moving_tokens() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+    let token_client = token::Client::new(&env, &token);
+    let contract_before = token_client.balance(&client.address);
+
+    env.ledger().with_mut(|l| l.timestamp += 250);
+    let checkpoint = client.settle(&id);
+    assert_eq!(checkpoint, 250);
+
+    let s = client.get_stream(&id).unwrap();
+    assert_eq!(s.accrued_checkpoint, 250);
+    assert_eq!(s.last_update_time, 250);
+    assert_eq!(s.withdrawn_amount, 0);
+
+    // No tokens moved.
+    assert_eq!(token_client.balance(&client.address), contract_before);
+    assert_eq!(token_client.balance(&recipient), 0);
+
+    // Settled accrual is still fully claimable later.
+    env.ledger().with_mut(|l| l.timestamp += 50);
+    assert_eq!(client.get_claimable_amount(&id).unwrap(), 300);
+    assert_eq!(client.withdraw(&recipient, &id), 300);
+}
+
+#[test]
+fn test_settle_emits_event() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &1_000);
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    client.settle(&id);
+
+    let events = env.events().all();
+    let ev = events
+        .iter()
+        .find(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap()).unwrap()
+                == Symbol::new(&env, "stream_settled")
+        })
+        .expect("stream_settled event not found");
+
+    let payload: StreamSettledEvent = StreamSettledEvent::try_from_val(&env, &ev.2).unwrap();
+    assert_eq!(payload.stream_id, id);
+    assert_eq!(payload.accrued_checkpoint, 40);
+    assert_eq!(payload.settled_at, 40);
+}
+
+#[test]
+fn test_settle_while_paused_preserves_pause_accounting() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+
+    env.ledger().with_mut(|l| l.timestamp += 100);
+    client.pause_stream(&sender, &id);
+    env.ledger().with_mut(|l| l.timestamp += 200);
+    assert_eq!(client.settle(&id), 100);
+    env.ledger().with_mut(|l| l.timestamp += 100);
+    client.resume_stream(&sender, &id);
+    env.ledger().with_mut(|l| l.timestamp += 50);
+
+    // 100 s before the pause + 50 s after resume.
+    assert_eq!(client.withdraw(&recipient, &id), 150);
+}
+
+#[test]
+fn test_settle_rejects_missing_and_inactive_streams() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    assert_eq!(client.try_settle(&99), Err(Ok(StreamError::StreamNotFound)));
+
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &1_000);
+    client.cancel_stream(&sender, &id);
+    assert_eq!(client.try_settle(&id), Err(Ok(StreamError::StreamInactive)));
+}
+
+#[test]
+fn test_batch_settle_returns_only_changed_streams() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 4_000);
+    let moving = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+    let paused = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+    let cancelled = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+    client.pause_stream(&sender, &paused);
+    client.cancel_stream(&sender, &cancelled);
+
+    env.ledger().with_mut(|l| l.timestamp += 10);
+    let fresh = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+
+    let ids = vec![&env, moving, paused, cancelled, fresh, 99];
+    assert_eq!(client.batch_settle(&ids), vec![&env, moving]);
+    assert_eq!(client.get_stream(&moving).unwrap().accrued_checkpoint, 10);
+
+    // Nothing elapsed since, so a second pass changes nothing.
+    assert_eq!(client.batch_settle(&ids).len(), 0);
+}
+
+#[test]
+fn test_batch_settle_rejects_oversized_batch() {
+    let env = Env::default();
+    let client = create_contract(&env);
+    let mut ids = Vec::new(&env);
+    for id in 0..51u64 {
+        ids.push_back(id);
+    }
+    assert_eq!(
+        client.try_batch_settle(&ids),
+        Err(Ok(StreamError::BatchTooLarge))
+    );
+}
+
+// ─── Fee exemptions ───────────────────────────────────────────────────────────
+
+#[test]
+fn test_set_fee_exempt_by_admin() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = create_contract(&env);
+    let admin = Address::generate(&env);
+    let partner = Address::generate(&env);
+    client.initialize(&admin, &Address::generate(&env), &500);
+
+    assert!(!client.is_fee_exempt(&partner));
+    client.set_fee_exempt(&admin, &partner, &true);
+    assert!(client.is_fee_exempt(&partner));
+    client.set_fee_exempt(&admin, &partner, &false);
+    assert!(!client.is_fee_exempt(&partner));
+}
+
+#[test]
+fn test_set_fee_exempt_rejects_non_admin() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = create_contract(&env);
+    let admin = Address::generate(&env);
+    let attacker = Address::generate(&env);
+    client.initialize(&admin, &Address::generate(&env), &500);
+
+    let result = client.try_set_fee_exempt(&attacker, &attacker, &true);
+    assert_eq!(result, Err(Ok(StreamError::NotAdmin)));
+}
+
+#[test]
+fn test_set_fee_exempt_rejects_not_initialized() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = create_contract(&env);
+    let admin = Address::generate(&env);
+
+    let result = client.try_set_fee_exempt(&admin, &Address::generate(&env), &true);
+    assert_eq!(result, Err(Ok(StreamError::NotInitialized)));
+}
+
+#[test]
+fn test_set_fee_exempt_emits_event() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = create_contract(&env);
+    let admin = Address::generate(&env);
+    let partner = Address::generate(&env);
+    client.initialize(&admin, &Address::generate(&env), &500);
+    client.set_fee_exempt(&admin, &partner, &true);
+
+    let events = env.events().all();
+    let ev = events
+        .iter()
+        .find(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap()).unwrap()
+                == Symbol::new(&env, "fee_exemption_updated")
+        })
+        .expect("fee_exemption_updated event not found");
+
+    let payload: FeeExemptionUpdatedEvent =
+        FeeExemptionUpdatedEvent::try_from_val(&env, &ev.2).unwrap();
+    assert_eq!(payload.admin, admin);
+    assert_eq!(payload.address, partner);
+    assert!(payload.exempt);
+}
+
+#[test]
+fn test_exempt_recipient_pays_no_fee() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 20_000);
+    let treasury = Address::generate(&env);
+    let admin = Address::generate(&env);
+    client.initialize(&admin, &treasury, &500);
+    client.set_fee_exempt(&admin, &recipient, &true);
+
+    let id = client.create_stream(&sender, &recipient, &token, &10_000, &100);
+    client.top_up_stream(&sender, &id, &5_000);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&treasury), 0);
+    assert_eq!(client.get_stream(&id).unwrap().deposited_amount, 15_000);
+}
+
+#[test]
+fn test_exempt_sender_pays_no_fee() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 10_000);
+    let treasury = Address::generate(&env);
+    let admin = Address::generate(&env);
+    client.initialize(&admin, &treasury, &500);
+    client.set_fee_exempt(&admin, &sender, &true);
+
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &10_000, &100);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&treasury), 0);
+    assert_eq!(client.get_stream(&id).unwrap().deposited_amount, 10_000);
+}
+
+#[test]
+fn test_non_exempt_stream_still_pays_fee() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 10_000);
+    let treasury = Address::generate(&env);
+    let admin = Address::generate(&env);
+    client.initialize(&admin, &treasury, &500);
+    // Exempt an unrelated partner only.
+    client.set_fee_exempt(&admin, &Address::generate(&env), &true);
+
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &10_000, &100);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&treasury), 500);
+    assert_eq!(client.get_stream(&id).unwrap().deposited_amount, 9_500);
+}
+
+// ─── max_duration ─────────────────────────────────────────────────────────────
+
+#[test]
+fn test_max_duration_defaults_to_unlimited() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, i128::MAX);
+    assert_eq!(client.get_max_duration(), 0);
+
+    // ~100 years is accepted when no limit is configured.
+    let century = 100 * 365 * 24 * 60 * 60_u64;
+    let id = client.create_stream(
+        &sender,
+        &Address::generate(&env),
+        &token,
+        &(century as i128),
+        &century,
+    );
+    assert_eq!(client.get_stream(&id).unwrap().rate_per_second, 1);
+}
+
+#[test]
+fn test_max_duration_accepts_boundary() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    let admin = Address::generate(&env);
+    client.initialize(&admin, &Address::generate(&env), &0);
+    client.set_max_duration(&admin, &100);
+    assert_eq!(client.get_max_duration(), 100);
+
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+    assert_eq!(id, 1);
+}
+
+#[test]
+fn test_max_duration_rejects_over_limit() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    let admin = Address::generate(&env);
+    client.initialize(&admin, &Address::generate(&env), &0);
+    client.set_max_duration(&admin, &100);
+
+    let result = client.try_create_stream(&sender, &Address::generate(&env), &token, &1_000, &101);
+    assert_eq!(result, Err(Ok(StreamError::DurationTooLong)));
+
+    // No tokens moved on rejection.
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&sender), 1_000);
+}
+
+#[test]
+fn test_max_duration_zero_disables_limit() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 10_000);
+    let admin = Address::generate(&env);
+    client.initialize(&admin, &Address::generate(&env), &0);
+    client.set_max_duration(&admin, &100);
+    client.set_max_duration(&admin, &0);
+
+    client.create_stream(&sender, &Address::generate(&env), &token, &10_000, &10_000);
+}
+
+#[test]
+fn test_set_max_duration_rejects_non_admin() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = create_contract(&env);
+    let admin = Address::generate(&env);
+    client.initialize(&admin, &Address::generate(&env), &0);
+
+    let result = client.try_set_max_duration(&Address::generate(&env), &100);
+    assert_eq!(result, Err(Ok(StreamError::NotAdmin)));
+}
+
+// ─── claimable_curve ──────────────────────────────────────────────────────────
+
+fn assert_curve_monotonic_to_deposit(curve: &soroban_sdk::Vec<(u64, i128)>, deposit: i128) {
+    let mut prev_t = 0_u64;
+    let mut prev_v = 0_i128;
+    for (i, (t, v)) in curve.iter().enumerate() {
+        if i > 0 {
+            assert!(t >= prev_t, "timestamps must be non-decreasing");
+            assert!(v >= prev_v, "accrued must be non-decreasing");
+        }
+        prev_t = t;
+        prev_v = v;
+    }
+    assert_eq!(prev_v, deposit);
+}
+
+#[test]
+fn test_claimable_curve_linear_stream() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+
+    let curve = client.claimable_curve(&id, &5);
+    assert_eq!(curve.len(), 5);
+    assert_eq!(curve.get(0).unwrap(), (0, 0));
+    assert_eq!(curve.get(2).unwrap(), (50, 500));
+    assert_eq!(curve.get(4).unwrap(), (100, 1_000));
+    assert_curve_monotonic_to_deposit(&curve, 1_000);
+}
+
+#[test]
+fn test_claimable_curve_after_withdrawal_and_top_up() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 2_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    env.ledger().with_mut(|l| l.timestamp += 30);
+    client.withdraw(&recipient, &id);
+    client.top_up_stream(&sender, &id, &777);
+
+    let curve = client.claimable_curve(&id, &17);
+    assert_eq!(curve.len(), 17);
+    assert_curve_monotonic_to_deposit(&curve, 1_777);
+    // 1 777 at 10/s ends at ceil(177.7) = 178.
+    assert_eq!(curve.get(16).unwrap().0, 178);
+}
+
+#[test]
+fn test_claimable_curve_caps_points() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+
+    assert_eq!(client.claimable_curve(&id, &10_000).len(), MAX_CURVE_POINTS);
+    assert_eq!(client.claimable_curve(&id, &0).len(), 2);
+    assert!(client.claimable_curve(&999, &10).is_empty());
+}
+
+// ─── Conservation-of-funds invariant harness ─────────────────────────────────
+
+/// Advances a 64-bit LCG and returns the new state.
+fn next_seed(seed: &mut u64) -> u64 {
+    *seed = seed
+        .wrapping_mul(6364136223846793005)
+        .wrapping_add(1442695040888963407);
+    *seed >> 1
+}
+
+/// Asserts that the contract holds exactly what live streams are owed, that the
+/// treasury holds exactly the recorded fees, and that no tokens were created or
+/// destroyed across all participants.
+fn assert_funds_conserved(
+    env: &Env,
+    client: &StreamContractClient,
+    token: &Address,
+    treasury: &Address,
+    participants: &[Address],
+    minted: i128,
+    ids: &[u64],
+) {
+    let token_client = token::Client::new(env, token);
+    let contract_balance = token_client.balance(&client.address);
+
+    let owed: i128 = ids
+        .iter()
+        .map(|id| client.get_stream(id).unwrap())
+        .filter(|s| s.is_active)
+        .map(|s| s.deposited_amount - s.withdrawn_amount)
+        .sum();
+    assert_eq!(
+        contract_balance, owed,
+        "contract balance != live liabilities"
+    );
+
+    let totals = client
+        .get_token_stats(&0, &50)
+        .get(token.clone())
+        .unwrap_or_default();
+    assert_eq!(totals.total_locked, contract_balance, "TVL counter drifted");
+    assert_eq!(
+        token_client.balance(treasury),
+        totals.total_fees_collected,
+        "treasury != recorded fees"
+    );
+
+    let held: i128 = participants.iter().map(|a| token_client.balance(a)).sum();
+    assert_eq!(
+        held + contract_balance + token_client.balance(treasury),
+        minted,
+        "tokens created or destroyed"
+    );
+}
+
+#[test]
+fn test_invariant_conservation_of_funds_randomized() {
+    const SEQUENCES: u64 = 16;
+    const OPS_PER_SEQUENCE: u32 = 30;
+
+    for sequence in 0..SEQUENCES {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (token, _) = create_token(&env);
+        let client = create_contract(&env);
+        let treasury = Address::generate(&env);
+
+        let mut seed = 0x9e37_79b9_7f4a_7c15_u64 ^ sequence;
+        // Alternate between no fee and a non-trivial fee.
+        let fee_bps = (next_seed(&mut seed) % 3) as u32 * 150;
+        client.initialize(&Address::generate(&env), &treasury, &fee_bps);
+
+        let senders = [Address::generate(&env), Address::generate(&env)];
+        let recipients = [Address::generate(&env), Address::generate(&env)];
+        let participants = [
+            senders[0].clone(),
+            senders[1].clone(),
+            recipients[0].clone(),
+            recipients[1].clone(),
+        ];
+        let per_sender = 1_000_000_i128;
+        for s in senders.iter() {
+            mint(&env, &token, s, per_sender);
+        }
+        let minted = per_sender * senders.len() as i128;
+
+        let mut ids: std::vec::Vec<u64> = std::vec::Vec::new();
+        for _ in 0..OPS_PER_SEQUENCE {
+            let op = next_seed(&mut seed) % 5;
+            match op {
+                0 => {
+                    let sender = &senders[(next_seed(&mut seed) % 2) as usize];
+                    let recipient = &recipients[(next_seed(&mut seed) % 2) as usize];
+                    let amount = 100 + (next_seed(&mut seed) % 20_000) as i128;
+                    let duration = 1 + next_seed(&mut seed) % 500;
+                    if let Ok(Ok(id)) =
+                        client.try_create_stream(sender, recipient, &token, &amount, &duration)
+                    {
+                        ids.push(id);
+                    }
+                }
+                1 if !ids.is_empty() => {
+                    let id = ids[(next_seed(&mut seed) % ids.len() as u64) as usize];
+                    let sender = client.get_stream(&id).unwrap().sender;
+                    let amount = 1 + (next_seed(&mut seed) % 5_000) as i128;
+                    let _ = client.try_top_up_stream(&sender, &id, &amount);
+                }
+                2 if !ids.is_empty() => {
+                    let id = ids[(next_seed(&mut seed) % ids.len() as u64) as usize];
+                    let recipient = client.get_stream(&id).unwrap().recipient;
+                    let _ = client.try_withdraw(&recipient, &id);
+                }
+                3 if !ids.is_empty() => {
+                    let id = ids[(next_seed(&mut seed) % ids.len() as u64) as usize];
+                    let sender = client.get_stream(&id).unwrap().sender;
+                    let _ = client.try_cancel_stream(&sender, &id);
+                }
+                _ => {
+                    let step = next_seed(&mut seed) % 120;
+                    env.ledger().with_mut(|l| l.timestamp += step);
+                }
+            }
+
+            assert_funds_conserved(
+                &env,
+                &client,
+                &token,
+                &treasury,
+                &participants,
+                minted,
+                &ids,
+            );
+        }
+    }
+}
+
+// ─── TTL bumping ──────────────────────────────────────────────────────────────
+
+fn stream_ttl(env: &Env, client: &StreamContractClient, stream_id: u64) -> u32 {
+    use soroban_sdk::testutils::storage::Persistent as _;
+    env.as_contract(&client.address, || {
+        env.storage()
+            .persistent()
+            .get_ttl(&DataKey::Stream(stream_id))
+    })
+}
+
+/// Creates a stream and advances the ledger until its TTL is below the bump threshold.
+///
+/// New entries start with a TTL well above `STREAM_TTL_EXTEND_TO` so the
+/// contract and token instances survive the ledger jump.
+fn setup_stream_with_low_ttl(env: &Env) -> (StreamContractClient<'_>, u64) {
+    let initial_ttl = storage::STREAM_TTL_EXTEND_TO * 2;
+    env.ledger().with_mut(|l| {
+        l.min_persistent_entry_ttl = initial_ttl;
+        l.max_entry_ttl = initial_ttl * 2;
+    });
+    let (client, token, sender, recipient) = setup(env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    let advance = initial_ttl - storage::STREAM_TTL_THRESHOLD + 1;
+    env.ledger().with_mut(|l| l.sequence_number += advance);
+    assert!(stream_ttl(env, &client, id) < storage::STREAM_TTL_THRESHOLD);
+
+    (client, id)
+}
+
+#[test]
+fn test_save_stream_extends_ttl() {
+    let env = Env::default();
+    let (client, id) = setup_stream_with_low_ttl(&env);
+
+    client.settle(&id);
+    assert_eq!(stream_ttl(&env, &client, id), storage::STREAM_TTL_EXTEND_TO);
+}
+
+#[test]
+fn test_per_token_config_ttl_is_extended_when_read() {
+    use soroban_sdk::testutils::storage::Persistent as _;
+    let env = Env::default();
+    env.mock_all_auths();
+    let initial_ttl = storage::STREAM_TTL_EXTEND_TO * 2;
+    env.ledger().with_mut(|l| {
+        l.min_persistent_entry_ttl = initial_ttl;
+        l.max_entry_ttl = initial_ttl * 2;
+    });
+    let (token, _) = create_token(&env);
+    let sender = Address::generate(&env);
+    let admin = Address::generate(&env);
+    mint(&env, &token, &sender, 1_000);
+
+    let client = create_contract(&env);
+    client.initialize(&admin, &Address::generate(&env), &0);
+    client.set_max_exposure_per_token(&admin, &token, &5_000);
+    let key = DataKey::MaxExposure(token.clone());
+    let ttl = || env.as_contract(&client.address, || env.storage().persistent().get_ttl(&key));
+
+    let advance = initial_ttl - storage::STREAM_TTL_THRESHOLD + 1;
+    env.ledger().with_mut(|l| l.sequence_number += advance);
+    assert!(ttl() < storage::STREAM_TTL_THRESHOLD);
+
+    // Creating a stream checks the cap, which keeps the entry alive.
+    client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+    assert_eq!(ttl(), storage::STREAM_TTL_EXTEND_TO);
+}
+
+#[test]
+fn test_preview_stream_never_writes_storage() {
+    let env = Env::default();
+    let (client, id) = setup_stream_with_low_ttl(&env);
+    env.ledger().with_mut(|l| l.timestamp += 40);
+
+    let before = env.to_ledger_snapshot();
+    let view = client.preview_stream(&id).unwrap();
+    assert_eq!(env.to_ledger_snapshot(), before);
+
+    assert_eq!(view.stream, client.get_stream(&id).unwrap());
+    assert_eq!((view.vested, view.claimable), (400, 400));
+    assert_eq!(view.end_time, view.stream.start_time + 100);
+    assert_eq!(view.pending_topup, 0);
+    assert!(client.preview_stream(&42).is_none());
+}
+
+#[test]
+fn test_stream_summary_matches_detailed_views() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_split_stream(&env);
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    let view = client.preview_stream(&id).unwrap();
+    assert_eq!(
+        client.get_stream_summary(&id),
+        Some((0, view.claimable, view.end_time))
+    );
+    assert_eq!(
+        client.get_stream_summary(&id).unwrap().1,
+        client.get_withdrawable_amount(&id)
+    );
+
+    client.pause_stream(&sender, &id);
+    assert_eq!(client.get_stream_summary(&id).unwrap().0, 1);
+    client.resume_stream(&sender, &id);
+
+    env.ledger().with_mut(|l| l.timestamp += 1_000);
+    client.withdraw(&recipient, &id);
+    assert_eq!(client.get_stream_summary(&id).unwrap().0, 3);
+    assert_eq!(client.get_stream_summary(&id).unwrap().1, 0);
+    assert_eq!(client.get_stream_summary(&42), None);
+
+    // Before its cliff an active stream reports Pending.
+    let (_, _, _, cliff_id) = create_cliff_stream(&env, &client, 40).unwrap();
+    let cliff_end = client.preview_stream(&cliff_id).unwrap().end_time;
+    assert_eq!(
+        client.get_stream_summary(&cliff_id),
+        Some((4, 0, cliff_end))
+    );
+    assert_eq!(client.get_stream_status(&cliff_id), StreamStatus::Pending);
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    assert_eq!(client.get_stream_summary(&cliff_id).unwrap().0, 0);
+}
+
+#[test]
+fn test_get_stream_flags_reflects_stored_state() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_split_stream(&env);
+    assert_eq!(client.get_stream_flags(&(id + 1)), None);
+
+    let fresh = StreamFlags {
+        is_active: true,
+        paused: false,
+        winding_down: false,
+        finalized: false,
+        is_public: true,
+        recipient_must_ack_topups: false,
+        cancelable: true,
+        transferable: true,
+    };
+    assert_eq!(client.get_stream_flags(&id), Some(fresh.clone()));
+
+    client.pause_stream(&sender, &id);
+    client.set_topup_ack_required(&recipient, &id, &true);
+    assert_eq!(
+        client.get_stream_flags(&id),
+        Some(StreamFlags {
+            paused: true,
+            recipient_must_ack_topups: true,
+            ..fresh.clone()
+        })
+    );
+    client.resume_stream(&sender, &id);
+
+    client.cancel_stream_with_mode(&sender, &id, &CancelMode::Graceful);
+    assert_eq!(
+        client.get_stream_flags(&id),
+        Some(StreamFlags {
+            winding_down: true,
+            recipient_must_ack_topups: true,
+            cancelable: false,
+            ..fresh.clone()
+        })
+    );
+
+    env.ledger().with_mut(|l| l.timestamp += 1_000);
+    client.withdraw(&recipient, &id);
+    client.finalize_stream(&sender, &id);
+    assert_eq!(
+        client.get_stream_flags(&id),
+        Some(StreamFlags {
+            is_active: false,
+            winding_down: true,
+            finalized: true,
+            recipient_must_ack_topups: true,
+            cancelable: false,
+            transferable: false,
+            ..fresh
+        })
+    );
+}
+
+#[test]
+fn test_get_stream_does_not_change_ttl() {
+    let env = Env::default();
+    let (client, id) = setup_stream_with_low_ttl(&env);
+
+    let before = stream_ttl(&env, &client, id);
+    client.get_stream(&id).unwrap();
+    assert_eq!(stream_ttl(&env, &client, id), before);
+}
+
+#[test]
+fn test_bump_stream_ttl_extends_low_ttl() {
+    let env = Env::default();
+    let (client, id) = setup_stream_with_low_ttl(&env);
+
+    client.bump_stream_ttl(&id);
+    assert_eq!(stream_ttl(&env, &client, id), storage::STREAM_TTL_EXTEND_TO);
+    assert_eq!(
+        client.try_bump_stream_ttl(&42),
+        Err(Ok(StreamError::StreamNotFound))
+    );
+}
+
+#[test]
+fn test_get_stream_missing_returns_none() {
+    let env = Env::default();
+    let client = create_contract(&env);
+    assert!(client.get_stream(&42).is_none());
+}
+
+#[test]
+fn test_extend_ttls_keeps_listed_streams_alive() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let initial_ttl = storage::STREAM_TTL_EXTEND_TO * 2;
+    env.ledger().with_mut(|l| {
+        l.min_persistent_entry_ttl = initial_ttl;
+        l.max_entry_ttl = initial_ttl * 2;
+    });
+    let (token, _) = create_token(&env);
+    let sender = Address::generate(&env);
+    mint(&env, &token, &sender, 3_000);
+
+    let client = create_contract(&env);
+    let mut ids = Vec::new(&env);
+    for _ in 0..3 {
+        ids.push_back(client.create_stream(
+            &sender,
+            &Address::generate(&env),
+            &token,
+            &1_000,
+            &100,
+        ));
+    }
+
+    let advance = initial_ttl - storage::STREAM_TTL_THRESHOLD + 1;
+    env.ledger().with_mut(|l| l.sequence_number += advance);
+
+    let skipped = ids.get(2).unwrap();
+    let mut listed = Vec::new(&env);
+    listed.push_back(ids.get(0).unwrap());
+    listed.push_back(ids.get(1).unwrap());
+    listed.push_back(999);
+    assert_eq!(client.extend_ttls(&listed), 2);
+
+    let later = storage::STREAM_TTL_THRESHOLD / 2;
+    env.ledger().with_mut(|l| l.sequence_number += later);
+    for id in [ids.get(0).unwrap(), ids.get(1).unwrap()] {
+        assert_eq!(
+            stream_ttl(&env, &client, id),
+            storage::STREAM_TTL_EXTEND_TO - later
+        );
+        assert!(client.get_stream(&id).is_some());
+    }
+    assert!(stream_ttl(&env, &client, skipped) < storage::STREAM_TTL_THRESHOLD / 2);
+}
+
+#[test]
+fn test_extend_ttls_rejects_oversized_batch() {
+    let env = Env::default();
+    let client = create_contract(&env);
+
+    let mut ids = Vec::new(&env);
+    for id in 0..=MAX_BATCH_SIZE as u64 {
+        ids.push_back(id);
+    }
+    assert_eq!(
+        client.try_extend_ttls(&ids),
+        Err(Ok(StreamError::BatchTooLarge))
+    );
+}
+
+// ─── withdraw_amount ──────────────────────────────────────────────────────────
+
+#[test]
+fn test_withdraw_amount_withdraws_exactly_the_requested_amount() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+
+    env.ledger().with_mut(|l| l.timestamp += 100);
+    assert_eq!(client.withdraw_amount(&recipient, &id, &40), 40);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&recipient), 40);
+    let stream = client.get_stream(&id).unwrap();
+    assert_eq!(stream.withdrawn_amount, 40);
+    assert!(stream.is_active);
+    // The unwithdrawn 60 is still claimable.
+    assert_eq!(client.get_claimable_amount(&id), Some(60));
+}
+
+#[test]
+fn test_withdraw_amount_rejects_over_request_and_non_positive() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+    env.ledger().with_mut(|l| l.timestamp += 100);
+
+    for amount in [101_i128, 0, -5] {
+        assert_eq!(
+            client.try_withdraw_amount(&recipient, &id, &amount),
+            Err(Ok(StreamError::InvalidAmount))
+        );
+    }
+    assert_eq!(client.get_stream(&id).unwrap().withdrawn_amount, 0);
+}
+
+#[test]
+fn test_withdraw_amount_sequential_partials_drain_the_stream() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+
+    env.ledger().with_mut(|l| l.timestamp += 300);
+    assert_eq!(client.withdraw_amount(&recipient, &id, &100), 100);
+    assert_eq!(client.withdraw_amount(&recipient, &id, &150), 150);
+    // Only 50 of the first 300 is left.
+    assert_eq!(
+        client.try_withdraw_amount(&recipient, &id, &51),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+
+    env.ledger().with_mut(|l| l.timestamp += 700);
+    assert_eq!(client.withdraw_amount(&recipient, &id, &750), 750);
+
+    let stream = client.get_stream(&id).unwrap();
+    assert_eq!(stream.withdrawn_amount, 1_000);
+    assert!(!stream.is_active);
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&recipient), 1_000);
+}
+
+#[test]
+fn test_get_withdrawn_amount_tracks_withdrawals() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+    assert_eq!(client.get_withdrawn_amount(&id), Some(0));
+    assert_eq!(client.get_withdrawn_amount(&(id + 1)), None);
+
+    env.ledger().with_mut(|l| l.timestamp += 100);
+    client.withdraw_amount(&recipient, &id, &30);
+    assert_eq!(client.get_withdrawn_amount(&id), Some(30));
+
+    env.ledger().with_mut(|l| l.timestamp += 200);
+    client.withdraw(&recipient, &id);
+    assert_eq!(client.get_withdrawn_amount(&id), Some(300));
+    assert_eq!(
+        client.get_withdrawn_amount(&id),
+        Some(client.get_stream(&id).unwrap().withdrawn_amount)
+    );
+}
+
+// ─── withdraw_min ─────────────────────────────────────────────────────────────
+
+#[test]
+fn test_withdraw_min_rejects_below_minimum() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+
+    env.ledger().with_mut(|l| l.timestamp += 49);
+    let result = client.try_withdraw_min(&recipient, &id, &50);
+    assert_eq!(result, Err(Ok(StreamError::BelowMinimum)));
+
+    // Nothing moved and nothing was settled.
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&recipient), 0);
+    assert_eq!(client.get_stream(&id).unwrap().withdrawn_amount, 0);
+}
+
+#[test]
+fn test_withdraw_min_withdraws_all_when_above_minimum() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+
+    env.ledger().with_mut(|l| l.timestamp += 120);
+    // Above the minimum: the full claimable balance is withdrawn, not just 50.
+    assert_eq!(client.withdraw_min(&recipient, &id, &50), 120);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&recipient), 120);
+}
+
+#[test]
+fn test_withdraw_min_accepts_exact_minimum() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+
+    env.ledger().with_mut(|l| l.timestamp += 50);
+    assert_eq!(client.withdraw_min(&recipient, &id, &50), 50);
+}
+
+#[test]
+fn test_withdraw_min_rejects_negative_minimum_and_non_recipient() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+    env.ledger().with_mut(|l| l.timestamp += 50);
+
+    assert_eq!(
+        client.try_withdraw_min(&recipient, &id, &-1),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_withdraw_min(&sender, &id, &0),
+        Err(Ok(StreamError::Unauthorized))
+    );
+}
+
+// ─── StreamAssetKind / rebasing tokens ────────────────────────────────────────
+
+/// Minimal rebasing token: `rebase` scales every holder's balance.
+mod rebasing_token {
+    use soroban_sdk::{contract, contractimpl, contracttype, Address, Env, Vec};
+
+    #[contracttype]
+    enum Key {
+        Balance(Address),
+        Holders,
+    }
+
+    #[contract]
+    pub struct RebasingToken;
+
+    fn set_balance(env: &Env, id: &Address, amount: i128) {
+        let mut holders: Vec<Address> = env
+            .storage()
+            .instance()
+            .get(&Key::Holders)
+            .unwrap_or(Vec::new(env));
+        if !holders.contains(id) {
+            holders.push_back(id.clone());
+            env.storage().instance().set(&Key::Holders, &holders);
+        }
+        env.storage()
+            .instance()
+            .set(&Key::Balance(id.clone()), &amount);
+    }
+
+    #[contractimpl]
+    impl RebasingToken {
+        pub fn decimals(_env: Env) -> u32 {
+            7
+        }
+
+        pub fn balance(env: Env, id: Address) -> i128 {
+            env.storage().instance().get(&Key::Balance(id)).unwrap_or(0)
+        }
+
+        pub fn mint(env: Env, to: Address, amount: i128) {
+            let current = Self::balance(env.clone(), to.clone());
+            set_balance(&env, &to, current + amount);
+        }
+
+        pub fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+            from.require_auth();
+            let from_balance = Self::balance(env.clone(), from.clone());
+            assert!(from_balance >= amount, "insufficient balance");
+            set_balance(&env, &from, from_balance - amount);
+            let to_balance = Self::balance(env.clone(), to.clone());
+            set_balance(&env, &to, to_balance + amount);
+        }
+
+        /// Scales every holder's balance by `num / den`.
+        pub fn rebase(env: Env, num: i128, den: i128) {
+            let holders: Vec<Address> = env
+                .storage()
+                .instance()
+                .get(&Key::Holders)
+                .unwrap_or(Vec::new(&env));
+            for holder in holders.iter() {
+                let current = Self::balance(env.clone(), holder.clone());
+                set_balance(&env, &holder, current * num / den);
+            }
+        }
+    }
+}
+
+fn create_rebasing_token(env: &Env) -> rebasing_token::RebasingTokenClient<'_> {
+    let id = env.register(rebasing_token::RebasingToken, ());
+    rebasing_token::RebasingTokenClient::new(env, &id)
+}
+
+#[test]
+fn test_asset_kind_defaults_to_plain_and_is_admin_only() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = create_contract(&env);
+    let admin = Address::generate(&env);
+    let token = Address::generate(&env);
+    client.initialize(&admin, &Address::generate(&env), &0);
+
+    assert_eq!(client.get_asset_kind(&token), StreamAssetKind::Plain);
+    assert_eq!(
+        client.try_set_asset_kind(&Address::generate(&env), &token, &StreamAssetKind::Rebasing),
+        Err(Ok(StreamError::NotAdmin))
+    );
+
+    client.set_asset_kind(&admin, &token, &StreamAssetKind::Rebasing);
+    assert_eq!(client.get_asset_kind(&token), StreamAssetKind::Rebasing);
+}
+
+#[test]
+fn test_rebasing_token_withdrawals_receive_rebased_share() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let rebasing = create_rebasing_token(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let admin = Address::generate(&env);
+    rebasing.mint(&sender, &1_000);
+
+    let client = create_contract(&env);
+    client.initialize(&admin, &Address::generate(&env), &0);
+    client.set_asset_kind(&admin, &rebasing.address, &StreamAssetKind::Rebasing);
+
+    let id = client.create_stream(&sender, &recipient, &rebasing.address, &1_000, &1_000);
+
+    // The contract's holding doubles through a positive rebase.
+    rebasing.rebase(&2, &1);
+    assert_eq!(rebasing.balance(&client.address), 2_000);
+
+    env.ledger().with_mut(|l| l.timestamp += 500);
+    // Accounting stays nominal...
+    assert_eq!(client.withdraw(&recipient, &id), 500);
+    assert_eq!(client.get_stream(&id).unwrap().withdrawn_amount, 500);
+    // ...while the payout carries the rebase.
+    assert_eq!(rebasing.balance(&recipient), 1_000);
+
+    env.ledger().with_mut(|l| l.timestamp += 500);
+    client.withdraw(&recipient, &id);
+    assert_eq!(rebasing.balance(&recipient), 2_000);
+    assert_eq!(rebasing.balance(&client.address), 0);
+}
+
+#[test]
+fn test_rebasing_token_cancel_splits_rebased_balance() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let rebasing = create_rebasing_token(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let admin = Address::generate(&env);
+    rebasing.mint(&sender, &1_000);
+
+    let client = create_contract(&env);
+    client.initialize(&admin, &Address::generate(&env), &0);
+    client.set_asset_kind(&admin, &rebasing.address, &StreamAssetKind::Rebasing);
+
+    let id = client.create_stream(&sender, &recipient, &rebasing.address, &1_000, &1_000);
+    env.ledger().with_mut(|l| l.timestamp += 250);
+    // A negative rebase halves the contract's holding.
+    rebasing.rebase(&1, &2);
+
+    client.cancel_stream(&sender, &id);
+    assert_eq!(rebasing.balance(&recipient), 125);
+    assert_eq!(rebasing.balance(&sender), 375);
+    assert_eq!(rebasing.balance(&client.address), 0);
+}
+
+#[test]
+fn test_plain_token_ignores_balance_drift() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let rebasing = create_rebasing_token(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    rebasing.mint(&sender, &1_000);
+
+    let client = create_contract(&env);
+    // Not registered as rebasing: payouts stay 1:1 with accounting.
+    let id = client.create_stream(&sender, &recipient, &rebasing.address, &1_000, &1_000);
+    rebasing.rebase(&2, &1);
+
+    env.ledger().with_mut(|l| l.timestamp += 500);
+    client.withdraw(&recipient, &id);
+    assert_eq!(rebasing.balance(&recipient), 500);
+    assert_eq!(rebasing.balance(&client.address), 1_500);
+}
+
+// ─── cancel_stream_with_mode ──────────────────────────────────────────────────
+
+#[test]
+fn test_cancel_immediate_mode_refunds_sender() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+    env.ledger().with_mut(|l| l.timestamp += 300);
+
+    client.cancel_stream_with_mode(&sender, &id, &CancelMode::Immediate);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&recipient), 300);
+    assert_eq!(token_client.balance(&sender), 700);
+
+    let s = client.get_stream(&id).unwrap();
+    assert!(!s.is_active);
+    assert!(!s.winding_down);
+    assert_eq!(s.status, StreamStatus::Cancelled);
+}
+
+#[test]
+fn test_cancel_graceful_mode_keeps_paying_without_clawback() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &1_000);
+    env.ledger().with_mut(|l| l.timestamp += 300);
+
+    client.cancel_stream_with_mode(&sender, &id, &CancelMode::Graceful);
+
+    // No refund and the stream stays live.
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&sender), 0);
+    let s = client.get_stream(&id).unwrap();
+    assert!(s.is_active);
+    assert!(s.winding_down);
+    assert_eq!(s.status, StreamStatus::Active);
+
+    // Recipient keeps accruing normally until the end.
+    env.ledger().with_mut(|l| l.timestamp += 700);
+    assert_eq!(client.withdraw(&recipient, &id), 1_000);
+    assert_eq!(token_client.balance(&recipient), 1_000);
+    assert!(client.is_stream_completed(&id));
+}
+
+#[test]
+fn test_cancel_graceful_mode_blocks_control_changes() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 2_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &1_000);
+    client.cancel_stream_with_mode(&sender, &id, &CancelMode::Graceful);
+
+    assert_eq!(
+        client.try_top_up_stream(&sender, &id, &500),
+        Err(Ok(StreamError::StreamWindingDown))
+    );
+    assert_eq!(
+        client.try_pause_stream(&sender, &id),
+        Err(Ok(StreamError::StreamWindingDown))
+    );
+    assert_eq!(
+        client.try_cancel_stream(&sender, &id),
+        Err(Ok(StreamError::StreamWindingDown))
+    );
+    assert_eq!(
+        client.try_cancel_stream_with_mode(&sender, &id, &CancelMode::Graceful),
+        Err(Ok(StreamError::StreamWindingDown))
+    );
+}
+
+#[test]
+fn test_cancel_graceful_mode_rejects_paused_stream() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &1_000);
+    client.pause_stream(&sender, &id);
+
+    assert_eq!(
+        client.try_cancel_stream_with_mode(&sender, &id, &CancelMode::Graceful),
+        Err(Ok(StreamError::StreamPaused))
+    );
+}
+
+#[test]
+fn test_cancel_graceful_mode_emits_event() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    client.cancel_stream_with_mode(&sender, &id, &CancelMode::Graceful);
+
+    let events = env.events().all();
+    let ev = events
+        .iter()
+        .find(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap()).unwrap()
+                == Symbol::new(&env, "stream_winding_down")
+        })
+        .expect("stream_winding_down event not found");
+
+    let payload: StreamWindingDownEvent =
+        StreamWindingDownEvent::try_from_val(&env, &ev.2).unwrap();
+    assert_eq!(payload.stream_id, id);
+    assert_eq!(payload.sender, sender);
+    assert_eq!(payload.recipient, recipient);
+    assert_eq!(payload.end_time, 100);
+}
+
+// ─── created_by ───────────────────────────────────────────────────────────────
+
+#[test]
+fn test_create_stream_tags_direct_creation_path() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+
+    let events = env.events().all();
+    let ev = events
+        .iter()
+        .find(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap()).unwrap()
+                == Symbol::new(&env, "stream_created")
+        })
+        .expect("stream_created event not found");
+    let payload: StreamCreatedEvent = StreamCreatedEvent::try_from_val(&env, &ev.2).unwrap();
+    assert_eq!(payload.created_by, Symbol::new(&env, "direct"));
+
+    let s = client.get_stream(&id).unwrap();
+    assert_eq!(s.created_by, Symbol::new(&env, "direct"));
+}
+
+// ─── Top-up acknowledgment ────────────────────────────────────────────────────
+
+/// Creates a 1 000-token, 10 tokens/s stream whose recipient requires top-up acks.
+fn setup_ack_required_stream(
+    env: &Env,
+) -> (StreamContractClient<'_>, Address, Address, Address, u64) {
+    let (client, token, sender, recipient) = setup(env, 10_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    client.set_topup_ack_required(&recipient, &id, &true);
+    (client, token, sender, recipient, id)
+}
+
+#[test]
+fn test_topup_parked_when_ack_required() {
+    let env = Env::default();
+    let (client, token, sender, _, id) = setup_ack_required_stream(&env);
+
+    client.top_up_stream(&sender, &id, &500);
+
+    // Funds moved into the contract but the stream's terms are unchanged.
+    let s = client.get_stream(&id).unwrap();
+    assert!(s.recipient_must_ack_topups);
+    assert_eq!(s.deposited_amount, 1_000);
+    assert_eq!(client.get_pending_topup(&id), 500);
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&client.address), 1_500);
+}
+
+#[test]
+fn test_topup_parked_emits_event() {
+    let env = Env::default();
+    let (client, _, sender, _, id) = setup_ack_required_stream(&env);
+
+    client.top_up_stream(&sender, &id, &300);
+
+    let events = env.events().all();
+    let ev = events
+        .iter()
+        .find(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap()).unwrap()
+                == Symbol::new(&env, "topup_parked")
+        })
+        .expect("topup_parked event not found");
+    let payload: TopUpParkedEvent = TopUpParkedEvent::try_from_val(&env, &ev.2).unwrap();
+    assert_eq!(payload.stream_id, id);
+    assert_eq!(payload.amount, 300);
+    assert_eq!(payload.pending_amount, 300);
+}
+
+#[test]
+fn test_ack_topup_credits_all_parked_amounts() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_ack_required_stream(&env);
+
+    client.top_up_stream(&sender, &id, &200);
+    client.top_up_stream(&sender, &id, &300);
+    assert_eq!(client.ack_topup(&recipient, &id), 500);
+
+    assert_eq!(client.get_stream(&id).unwrap().deposited_amount, 1_500);
+    assert_eq!(client.get_pending_topup(&id), 0);
+    assert_eq!(
+        client.try_ack_topup(&recipient, &id),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+}
+
+#[test]
+fn test_ack_topup_rejects_non_recipient() {
+    let env = Env::default();
+    let (client, _, sender, _, id) = setup_ack_required_stream(&env);
+
+    client.top_up_stream(&sender, &id, &200);
+    assert_eq!(
+        client.try_ack_topup(&sender, &id),
+        Err(Ok(StreamError::Unauthorized))
+    );
+    assert_eq!(
+        client.try_set_topup_ack_required(&sender, &id, &false),
+        Err(Ok(StreamError::Unauthorized))
+    );
+}
+
+#[test]
+fn test_reclaim_topup_returns_parked_funds_after_cancel() {
+    let env = Env::default();
+    let (client, token, sender, _, id) = setup_ack_required_stream(&env);
+
+    client.top_up_stream(&sender, &id, &400);
+    client.cancel_stream(&sender, &id);
+    assert_eq!(client.reclaim_topup(&sender, &id), 400);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&sender), 10_000);
+    assert_eq!(token_client.balance(&client.address), 0);
+    assert_eq!(client.get_pending_topup(&id), 0);
+}
+
+#[test]
+fn test_topup_applies_directly_once_ack_disabled() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_ack_required_stream(&env);
+
+    client.set_topup_ack_required(&recipient, &id, &false);
+    client.top_up_stream(&sender, &id, &500);
+
+    assert_eq!(client.get_stream(&id).unwrap().deposited_amount, 1_500);
+    assert_eq!(client.get_pending_topup(&id), 0);
+}
+
+// ─── Timestamp monotonicity ───────────────────────────────────────────────────
+
+#[test]
+fn test_backwards_time_skips_accrual_without_panic() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    env.ledger().with_mut(|l| l.timestamp = 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    env.ledger().with_mut(|l| l.timestamp = 1_040);
+    assert_eq!(client.settle(&id), 400);
+
+    // Rewind the ledger behind the checkpoint: impossible on-chain, but must not panic.
+    env.ledger().with_mut(|l| l.timestamp = 1_010);
+    assert_eq!(client.get_claimable_amount(&id), Some(400));
+    assert_eq!(client.settle(&id), 400);
+
+    let s = client.get_stream(&id).unwrap();
+    assert_eq!(s.last_update_time, 1_040);
+    assert_eq!(s.accrued_checkpoint, 400);
+
+    // Withdrawal while time is behind pays only what was already checkpointed.
+    assert_eq!(client.withdraw(&recipient, &id), 400);
+
+    // Once time moves forward again, accrual resumes from the checkpoint.
+    env.ledger().with_mut(|l| l.timestamp = 1_050);
+    assert_eq!(client.get_claimable_amount(&id), Some(100));
+}
+
+// ─── Token + status filter ────────────────────────────────────────────────────
+
+#[test]
+fn test_get_streams_by_token_and_status_filters_mixed_statuses() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token, _) = create_token(&env);
+    let (other_token, _) = create_token(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    mint(&env, &token, &sender, 10_000);
+    mint(&env, &other_token, &sender, 10_000);
+
+    let client = create_contract(&env);
+    let active_a = client.create_stream(&sender, &recipient, &token, &100, &100);
+    let cancelled = client.create_stream(&sender, &recipient, &token, &100, &100);
+    let paused = client.create_stream(&sender, &recipient, &token, &100, &100);
+    let active_b = client.create_stream(&sender, &recipient, &token, &100, &100);
+    client.create_stream(&sender, &recipient, &other_token, &100, &100);
+
+    client.cancel_stream(&sender, &cancelled);
+    client.pause_stream(&sender, &paused);
+
+    let active = client.get_streams_by_token_and_status(&token, &StreamStatus::Active, &0, &10);
+    assert_eq!(active.len(), 2);
+    assert_eq!(
+        active.get(0).unwrap(),
+        client.get_stream(&active_a).unwrap()
+    );
+    assert_eq!(
+        active.get(1).unwrap(),
+        client.get_stream(&active_b).unwrap()
+    );
+
+    let cancelled_page =
+        client.get_streams_by_token_and_status(&token, &StreamStatus::Cancelled, &0, &10);
+    assert_eq!(cancelled_page.len(), 1);
+    assert_eq!(
+        cancelled_page.get(0).unwrap().status,
+        StreamStatus::Cancelled
+    );
+
+    let paused_page =
+        client.get_streams_by_token_and_status(&token, &StreamStatus::Paused, &0, &10);
+    assert_eq!(paused_page.len(), 1);
+
+    // The other token's stream never leaks into this token's results.
+    let other =
+        client.get_streams_by_token_and_status(&other_token, &StreamStatus::Active, &0, &10);
+    assert_eq!(other.len(), 1);
+    assert_eq!(other.get(0).unwrap().token_address, other_token);
+}
+
+#[test]
+fn test_get_streams_by_token_and_status_paginates_matches() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 10_000);
+    let mut active_ids = std::vec::Vec::new();
+    for i in 0..6 {
+        let id = client.create_stream(&sender, &recipient, &token, &100, &100);
+        if i % 2 == 0 {
+            client.cancel_stream(&sender, &id);
+        } else {
+            active_ids.push(id);
+        }
+    }
+
+    let first = client.get_streams_by_token_and_status(&token, &StreamStatus::Active, &0, &2);
+    let second = client.get_streams_by_token_and_status(&token, &StreamStatus::Active, &2, &2);
+    assert_eq!(first.len(), 2);
+    assert_eq!(second.len(), 1);
+    assert_eq!(
+        second.get(0).unwrap(),
+        client.get_stream(&active_ids[2]).unwrap()
+    );
+
+    assert_eq!(
+        client
+            .get_streams_by_token_and_status(&token, &StreamStatus::Active, &0, &0)
+            .len(),
+        0
+    );
+}
+
+// ─── First-withdrawal delay ───────────────────────────────────────────────────
+
+fn setup_delayed_stream(env: &Env, delay: u64) -> (StreamContractClient<'_>, Address, u64) {
+    let (client, token, sender, recipient) = setup(env, 1_000);
+    env.ledger().with_mut(|l| l.timestamp = 1_000);
+    let id = client.create_stream_with_options(
+        &sender,
+        &recipient,
+        &token,
+        &1_000,
+        &100,
+        &StreamOptions {
+            first_withdraw_delay: delay,
+            ..Default::default()
+        },
+    );
+    (client, recipient, id)
+}
+
+#[test]
+fn test_withdraw_before_first_withdraw_delay_is_too_soon() {
+    let env = Env::default();
+    let (client, recipient, id) = setup_delayed_stream(&env, 30);
+
+    env.ledger().with_mut(|l| l.timestamp = 1_029);
+    assert_eq!(
+        client.try_withdraw(&recipient, &id),
+        Err(Ok(StreamError::TooSoon))
+    );
+    // Accrual is not gated: the recipient is owed the full elapsed amount.
+    assert_eq!(client.get_claimable_amount(&id), Some(290));
+}
+
+#[test]
+fn test_withdraw_after_first_withdraw_delay_pays_full_accrual() {
+    let env = Env::default();
+    let (client, recipient, id) = setup_delayed_stream(&env, 30);
+
+    env.ledger().with_mut(|l| l.timestamp = 1_030);
+    assert_eq!(client.withdraw(&recipient, &id), 300);
+
+    let s = client.get_stream(&id).unwrap();
+    assert_eq!(s.first_withdraw_delay, 30);
+    assert_eq!(s.created_by, Symbol::new(&env, "with_options"));
+}
+
+#[test]
+fn test_zero_first_withdraw_delay_allows_immediate_withdraw() {
+    let env = Env::default();
+    let (client, recipient, id) = setup_delayed_stream(&env, 0);
+
+    env.ledger().with_mut(|l| l.timestamp = 1_001);
+    assert_eq!(client.withdraw(&recipient, &id), 10);
+}
+
+#[test]
+fn test_next_withdrawable_at_tracks_first_withdraw_delay() {
+    let env = Env::default();
+    let (client, recipient, id) = setup_delayed_stream(&env, 30);
+
+    env.ledger().with_mut(|l| l.timestamp = 1_010);
+    assert_eq!(client.next_withdrawable_at(&id), 1_030);
+
+    // Once open, a freshly-withdrawn stream can withdraw again immediately.
+    env.ledger().with_mut(|l| l.timestamp = 1_040);
+    client.withdraw(&recipient, &id);
+    assert_eq!(client.next_withdrawable_at(&id), 1_040);
+
+    env.ledger().with_mut(|l| l.timestamp = 2_000);
+    client.withdraw(&recipient, &id);
+    assert_eq!(
+        client.try_next_withdrawable_at(&id),
+        Err(Ok(StreamError::StreamInactive))
+    );
+}
+
+#[test]
+fn test_next_withdrawable_at_waits_for_cliff_after_delay() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let options = StreamOptions {
+        first_withdraw_delay: 10,
+        cliff_duration: 50,
+        ..Default::default()
+    };
+    let id = try_create_with_options(&client, &token, &sender, &recipient, &options).unwrap();
+
+    env.ledger().with_mut(|l| l.timestamp = 20);
+    assert_eq!(client.next_withdrawable_at(&id), 50);
+    assert_eq!(
+        client.try_withdraw(&recipient, &id),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 50);
+    assert_eq!(client.next_withdrawable_at(&id), 50);
+    assert_eq!(client.withdraw(&recipient, &id), 500);
+}
+
+// ─── Top-up return value ──────────────────────────────────────────────────────
+
+#[test]
+fn test_top_up_stream_returns_updated_stream() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 10_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    env.ledger().with_mut(|l| l.timestamp += 25);
+    let returned = client.top_up_stream(&sender, &id, &500);
+
+    assert_eq!(returned.deposited_amount, 1_500);
+    assert_eq!(returned, client.get_stream(&id).unwrap());
+}
+
+// ─── Per-token exposure cap ───────────────────────────────────────────────────
+
+fn setup_exposure_capped(env: &Env, cap: i128) -> (StreamContractClient<'_>, Address, Address) {
+    let (client, token, sender, _) = setup(env, 10_000);
+    let admin = Address::generate(env);
+    client.initialize(&admin, &Address::generate(env), &0);
+    client.set_max_exposure_per_token(&admin, &token, &cap);
+    (client, token, sender)
+}
+
+#[test]
+fn test_exposure_cap_accepts_exactly_at_cap() {
+    let env = Env::default();
+    let (client, token, sender) = setup_exposure_capped(&env, 1_500);
+    assert_eq!(client.get_max_exposure_per_token(&token), 1_500);
+
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+    client.top_up_stream(&sender, &id, &500);
+
+    assert_eq!(
+        client
+            .get_token_stats(&0, &50)
+            .get(token)
+            .unwrap()
+            .total_locked,
+        1_500
+    );
+}
+
+#[test]
+fn test_exposure_cap_rejects_create_over_cap() {
+    let env = Env::default();
+    let (client, token, sender) = setup_exposure_capped(&env, 1_500);
+
+    client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+    assert_eq!(
+        client.try_create_stream(&sender, &Address::generate(&env), &token, &501, &100),
+        Err(Ok(StreamError::ExposureCapReached))
+    );
+}
+
+#[test]
+fn test_exposure_cap_rejects_top_up_over_cap_and_frees_on_withdraw() {
+    let env = Env::default();
+    let (client, token, sender) = setup_exposure_capped(&env, 1_000);
+    let recipient = Address::generate(&env);
+
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    assert_eq!(
+        client.try_top_up_stream(&sender, &id, &1),
+        Err(Ok(StreamError::ExposureCapReached))
+    );
+
+    // Payouts release exposure, making room for new deposits.
+    env.ledger().with_mut(|l| l.timestamp += 10);
+    client.withdraw(&recipient, &id);
+    client.top_up_stream(&sender, &id, &100);
+}
+
+#[test]
+fn test_set_max_exposure_rejects_non_admin_and_negative() {
+    let env = Env::default();
+    let (client, token, sender) = setup_exposure_capped(&env, 0);
+    let admin = client.get_fee_config().unwrap().admin;
+
+    assert_eq!(
+        client.try_set_max_exposure_per_token(&sender, &token, &100),
+        Err(Ok(StreamError::NotAdmin))
+    );
+    assert_eq!(
+        client.try_set_max_exposure_per_token(&admin, &token, &-1),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+}
+
+// ─── Claim on behalf ──────────────────────────────────────────────────────────
+
+#[test]
+fn test_claim_on_behalf_pays_recipient() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    assert_eq!(client.claim_on_behalf(&sender, &id), 400);
+
+    let events = env.events().all();
+    let ev = events
+        .iter()
+        .find(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap()).unwrap()
+                == Symbol::new(&env, "tokens_withdrawn")
+        })
+        .expect("tokens_withdrawn event not found");
+    let payload: TokensWithdrawnEvent = TokensWithdrawnEvent::try_from_val(&env, &ev.2).unwrap();
+    assert_eq!(payload.recipient, recipient);
+    assert_eq!(payload.amount, 400);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&recipient), 400);
+    assert_eq!(token_client.balance(&sender), 0);
+    assert_eq!(client.get_stream(&id).unwrap().withdrawn_amount, 400);
+}
+
+#[test]
+fn test_claim_on_behalf_rejects_non_sender() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    let attacker = Address::generate(&env);
+    assert_eq!(
+        client.try_claim_on_behalf(&attacker, &id),
+        Err(Ok(StreamError::Unauthorized))
+    );
+    assert_eq!(
+        client.try_claim_on_behalf(&recipient, &id),
+        Err(Ok(StreamError::Unauthorized))
+    );
+}
+
+// ─── Views before initialize ──────────────────────────────────────────────────
+
+#[test]
+fn test_views_before_initialize_return_defaults() {
+    let env = Env::default();
+    let (token, _) = create_token(&env);
+    let client = create_contract(&env);
+    let anyone = Address::generate(&env);
+
+    assert_eq!(client.get_fee_config(), None);
+    assert!(!client.is_fee_exempt(&anyone));
+    assert_eq!(client.get_max_duration(), 0);
+    assert_eq!(client.get_max_exposure_per_token(&token), 0);
+    assert_eq!(client.get_asset_kind(&token), StreamAssetKind::Plain);
+    assert_eq!(client.get_stream(&1), None);
+    assert_eq!(client.get_stream(&1), None);
+    assert!(!client.is_stream_completed(&1));
+    assert_eq!(client.get_claimable_amount(&1), None);
+    assert_eq!(client.claimable_curve(&1, &10).len(), 0);
+    assert_eq!(client.get_pending_topup(&1), 0);
+    assert_eq!(
+        client
+            .get_streams_by_token_and_status(&token, &StreamStatus::Active, &0, &10)
+            .len(),
+        0
+    );
+
+    let stats = client.get_protocol_stats();
+    assert_eq!(stats.total_streams, 0);
+    assert_eq!(stats.active_streams, 0);
+    assert_eq!(client.get_token_stats(&0, &50).len(), 0);
+}
+
+#[test]
+fn test_views_on_uninitialized_stream_reflect_zero_fee() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+
+    env.ledger().with_mut(|l| l.timestamp += 50);
+    assert_eq!(client.get_stream(&id).unwrap().deposited_amount, 1_000);
+    assert_eq!(client.get_claimable_amount(&id), Some(500));
+
+    let totals = client.get_token_stats(&0, &50).get(token).unwrap();
+    assert_eq!(totals.total_locked, 1_000);
+    assert_eq!(totals.total_fees_collected, 0);
+}
+
+// ─── Average rate ─────────────────────────────────────────────────────────────
+
+/// Checkpoints a stream and then overwrites its rate, mimicking a rate change.
+fn set_stream_rate(env: &Env, client: &StreamContractClient<'_>, stream_id: u64, rate: i128) {
+    client.settle(&stream_id);
+    env.as_contract(&client.address, || {
+        let mut stream = storage::load_stream(env, stream_id).unwrap();
+        stream.rate_per_second = rate;
+        storage::save_stream(env, stream_id, &stream);
+    });
+}
+
+#[test]
+fn test_average_rate_weights_periods_by_length() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    env.ledger().with_mut(|l| l.timestamp = 1_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+    assert_eq!(client.get_average_rate(&id), 0);
+
+    // 20 s at 10/s, then 20 s at 25/s: (200 + 500) / 40 = 17.
+    env.ledger().with_mut(|l| l.timestamp = 1_020);
+    assert_eq!(client.get_average_rate(&id), 10);
+    set_stream_rate(&env, &client, id, 25);
+    env.ledger().with_mut(|l| l.timestamp = 1_040);
+    assert_eq!(client.get_average_rate(&id), 17);
+}
+
+#[test]
+fn test_average_rate_after_cancel_counts_only_vested() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_split_stream(&env);
+
+    // 100 vested at 10/s; the sender hands the unvested 900 to the recipient.
+    env.ledger().with_mut(|l| l.timestamp = 10);
+    client.cancel_with_split(&sender, &recipient, &id, &0);
+    assert_eq!(client.get_withdrawn_amount(&id), Some(1_000));
+
+    env.ledger().with_mut(|l| l.timestamp = 50);
+    assert_eq!(client.get_average_rate(&id), 10);
+}
+
+#[test]
+fn test_average_rate_of_missing_stream_is_zero() {
+    let env = Env::default();
+    let client = create_contract(&env);
+    assert_eq!(client.get_average_rate(&42), 0);
+}
+
+#[test]
+fn test_rate_per_day_and_month_convert_per_second_rate() {
+    let env = Env::default();
+    let (client, _, _, _, id) = setup_split_stream(&env);
+
+    // 1_000 over 100 s streams at 10 per second.
+    let stream = client.get_stream(&id).unwrap();
+    assert_eq!(stream.rate_unit, RateUnit::PerSecond);
+    assert_eq!(client.get_rate_per_day(&id), 864_000);
+    assert_eq!(client.get_rate_per_month(&id), 25_920_000);
+
+    assert_eq!(client.get_rate_per_day(&(id + 1)), 0);
+    assert_eq!(client.get_rate_per_month(&(id + 1)), 0);
+}
+
+// ─── Funded runway ────────────────────────────────────────────────────────────
+
+#[test]
+fn test_seconds_funded_shrinks_after_rate_increase() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+    assert_eq!(client.seconds_funded(&id), 100);
+
+    env.ledger().with_mut(|l| l.timestamp += 20);
+    assert_eq!(client.seconds_funded(&id), 80);
+
+    // 800 left at 40/s lasts 20 s, well short of the nominal 80 s.
+    set_stream_rate(&env, &client, id, 40);
+    assert_eq!(client.seconds_funded(&id), 20);
+
+    env.ledger().with_mut(|l| l.timestamp += 20);
+    assert_eq!(client.seconds_funded(&id), 0);
+}
+
+#[test]
+fn test_seconds_funded_zero_rate_and_missing_stream() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+    set_stream_rate(&env, &client, id, 0);
+
+    assert_eq!(client.seconds_funded(&id), 0);
+    assert_eq!(client.seconds_funded(&999), 0);
+}
+
+// ─── Negotiated cancel split ──────────────────────────────────────────────────
+
+fn setup_split_stream(env: &Env) -> (StreamContractClient<'_>, Address, Address, Address, u64) {
+    let (client, token, sender, recipient) = setup(env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    (client, token, sender, recipient, id)
+}
+
+#[test]
+fn test_cancel_with_split_divides_unvested_balance() {
+    let env = Env::default();
+    let (client, token, sender, recipient, id) = setup_split_stream(&env);
+
+    // 400 vested, 600 unvested; sender takes back 25% of the unvested part.
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    client.cancel_with_split(&sender, &recipient, &id, &2_500);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&sender), 150);
+    assert_eq!(token_client.balance(&recipient), 850);
+    assert_eq!(token_client.balance(&client.address), 0);
+
+    let s = client.get_stream(&id).unwrap();
+    assert_eq!(s.status, StreamStatus::Cancelled);
+    assert_eq!(s.withdrawn_amount, 850);
+}
+
+#[test]
+fn test_cancel_with_split_extremes_match_full_and_zero_refund() {
+    let env = Env::default();
+    let (client, token, sender, recipient, id) = setup_split_stream(&env);
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    client.cancel_with_split(&sender, &recipient, &id, &10_000);
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&sender), 600);
+    assert_eq!(token_client.balance(&recipient), 400);
+
+    let env = Env::default();
+    let (client, token, sender, recipient, id) = setup_split_stream(&env);
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    client.cancel_with_split(&sender, &recipient, &id, &0);
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&sender), 0);
+    assert_eq!(token_client.balance(&recipient), 1_000);
+}
+
+#[test]
+fn test_cancel_with_split_conserves_large_uneven_amounts() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token, _) = create_token(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    // Large enough that unvested * bps would overflow i128.
+    let deposit = 100_000_000_000_000_000_000_000_000_000_000_007_i128;
+    mint(&env, &token, &sender, deposit);
+
+    let client = create_contract(&env);
+    let id = client.create_stream(&sender, &recipient, &token, &deposit, &100);
+
+    env.ledger().with_mut(|l| l.timestamp += 30);
+    client.cancel_with_split(&sender, &recipient, &id, &3_333);
+
+    let token_client = token::Client::new(&env, &token);
+    let refunded = token_client.balance(&sender);
+    let paid = token_client.balance(&recipient);
+    let unvested = deposit - 30 * (deposit / 100);
+    // The truncated share goes to the sender; the recipient keeps the remainder.
+    assert_eq!(
+        refunded,
+        unvested / 10_000 * 3_333 + unvested % 10_000 * 3_333 / 10_000
+    );
+    assert_eq!(paid + refunded, deposit);
+    assert_eq!(token_client.balance(&client.address), 0);
+}
+
+#[test]
+fn test_cancel_pays_out_only_in_the_stream_token() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token_a, _) = create_token(&env);
+    let (token_b, _) = create_token(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    mint(&env, &token_a, &sender, 1_000);
+    mint(&env, &token_b, &sender, 1_000);
+
+    let client = create_contract(&env);
+    let id_a = client.create_stream(&sender, &recipient, &token_a, &1_000, &100);
+    // The same parties also hold a stream in another token, so the contract
+    // has both tokens on hand when the first stream is cancelled.
+    let id_b = client.create_stream(&sender, &recipient, &token_b, &1_000, &100);
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    client.cancel_with_split(&sender, &recipient, &id_a, &5_000);
+
+    let a = token::Client::new(&env, &token_a);
+    let b = token::Client::new(&env, &token_b);
+    assert_eq!(a.balance(&recipient), 700);
+    assert_eq!(a.balance(&sender), 300);
+    assert_eq!(b.balance(&recipient), 0);
+    assert_eq!(b.balance(&sender), 0);
+    assert_eq!(b.balance(&client.address), 1_000);
+    assert!(client.get_stream(&id_b).unwrap().is_active);
+}
+
+#[test]
+fn test_cancel_with_split_rejects_wrong_recipient_and_bad_bps() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_split_stream(&env);
+
+    assert_eq!(
+        client.try_cancel_with_split(&sender, &Address::generate(&env), &id, &5_000),
+        Err(Ok(StreamError::Unauthorized))
+    );
+    assert_eq!(
+        client.try_cancel_with_split(&sender, &recipient, &id, &10_001),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+    assert!(client.get_stream(&id).unwrap().is_active);
+}
+
+// ─── Event replay ─────────────────────────────────────────────────────────────
+
+/// Folds the events of the last invocation into `state`, as an indexer would.
+fn replay_events(env: &Env, state: &mut Option<Stream>) {
+    for (_, topics, data) in env.events().all().iter() {
+        let Ok(topic) = Symbol::try_from_val(env, &topics.get(0).unwrap()) else {
+            continue;
+        };
+        if topic == Symbol::new(env, "stream_created") {
+            let ev = StreamCreatedEvent::try_from_val(env, &data).unwrap();
+            *state = Some(Stream {
+                sender: ev.sender,
+                recipient: ev.recipient,
+                token_address: ev.token_address,
+                start_time: ev.start_time,
+                last_update_time: ev.start_time,
+                created_by: ev.created_by,
+                end_time: ev.end_time,
+                cliff_time: ev.cliff_time,
+                memo: ev.memo,
+                ..test_stream(env, ev.rate_per_second, ev.deposited_amount)
+            });
+        } else if topic == Symbol::new(env, "stream_topped_up") {
+            let ev = StreamToppedUpEvent::try_from_val(env, &data).unwrap();
+            let s = state.as_mut().unwrap();
+            s.deposited_amount = ev.new_deposited_amount;
+            s.end_time = ev.new_end_time;
+        } else if topic == Symbol::new(env, "tokens_withdrawn") {
+            let ev = TokensWithdrawnEvent::try_from_val(env, &data).unwrap();
+            let s = state.as_mut().unwrap();
+            s.withdrawn_amount += ev.amount;
+            s.accrued_checkpoint = s.withdrawn_amount;
+            s.last_update_time = ev.timestamp;
+        } else if topic == Symbol::new(env, "stream_cancelled") {
+            let ev = StreamCancelledEvent::try_from_val(env, &data).unwrap();
+            let s = state.as_mut().unwrap();
+            s.withdrawn_amount = ev.amount_withdrawn;
+            s.accrued_checkpoint = ev.amount_withdrawn;
+            s.last_update_time = ev.cancelled_at;
+            s.is_active = false;
+            s.status = StreamStatus::Cancelled;
+        }
+    }
+}
+
+#[test]
+fn test_stream_state_reconstructs_from_events_alone() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 10_000);
+    let mut replayed = None;
+
+    env.ledger().with_mut(|l| l.timestamp = 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    replay_events(&env, &mut replayed);
+    assert_eq!(replayed, client.get_stream(&id));
+
+    env.ledger().with_mut(|l| l.timestamp = 1_030);
+    client.withdraw(&recipient, &id);
+    replay_events(&env, &mut replayed);
+
+    env.ledger().with_mut(|l| l.timestamp = 1_045);
+    client.top_up_stream(&sender, &id, &500);
+    replay_events(&env, &mut replayed);
+    assert_eq!(replayed, client.get_stream(&id));
+
+    env.ledger().with_mut(|l| l.timestamp = 1_070);
+    client.withdraw(&recipient, &id);
+    replay_events(&env, &mut replayed);
+    assert_eq!(replayed, client.get_stream(&id));
+
+    env.ledger().with_mut(|l| l.timestamp = 1_090);
+    client.cancel_stream(&sender, &id);
+    replay_events(&env, &mut replayed);
+    assert_eq!(replayed, client.get_stream(&id));
+}
+
+// ─── Reserve check on cancel ──────────────────────────────────────────────────
+
+#[test]
+fn test_cancel_with_balance_shortfall_returns_insufficient_reserve() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    // Simulate accounting drift: the contract loses tokens it believes it holds.
+    let token_client = token::Client::new(&env, &token);
+    env.as_contract(&client.address, || {
+        token_client.transfer(&client.address, &Address::generate(&env), &1);
+    });
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    assert_eq!(
+        client.try_cancel_stream(&sender, &id),
+        Err(Ok(StreamError::InsufficientReserve))
+    );
+
+    // Nothing moved and the stream is still live.
+    assert!(client.get_stream(&id).unwrap().is_active);
+    assert_eq!(token_client.balance(&client.address), 999);
+    assert_eq!(token_client.balance(&sender), 0);
+}
+
+#[test]
+fn test_cancel_payouts_conserve_unpaid_balance_at_any_time() {
+    for (withdraw_at, cancel_at) in [(0, 0), (0, 37), (20, 55), (50, 100), (90, 250)] {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (token, _) = create_token(&env);
+        let sender = Address::generate(&env);
+        let recipient = Address::generate(&env);
+        mint(&env, &token, &sender, 1_003);
+        let token_client = token::Client::new(&env, &token);
+
+        let client = create_contract(&env);
+        let id = client.create_stream(&sender, &recipient, &token, &1_003, &100);
+        if withdraw_at > 0 {
+            env.ledger().with_mut(|l| l.timestamp = withdraw_at);
+            client.withdraw(&recipient, &id);
+        }
+        let previously_withdrawn = client.get_stream(&id).unwrap().withdrawn_amount;
+
+        env.ledger().with_mut(|l| l.timestamp = cancel_at);
+        client.cancel_stream(&sender, &id);
+
+        let recipient_payout = token_client.balance(&recipient) - previously_withdrawn;
+        let sender_refund = token_client.balance(&sender);
+        assert_eq!(
+            recipient_payout + sender_refund,
+            1_003 - previously_withdrawn
+        );
+        assert_eq!(token_client.balance(&client.address), 0);
+    }
+}
+
+#[test]
+fn test_cancel_with_inconsistent_accounting_returns_accounting_error() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+
+    // Corrupt the record so more is marked withdrawn than was ever deposited.
+    env.as_contract(&client.address, || {
+        let mut stream = storage::load_stream(&env, id).unwrap();
+        stream.withdrawn_amount = 1_200;
+        storage::save_stream(&env, id, &stream);
+    });
+
+    assert_eq!(
+        client.try_cancel_stream(&sender, &id),
+        Err(Ok(StreamError::AccountingError))
+    );
+    assert!(client.get_stream(&id).unwrap().is_active);
+}
+
+// ─── Payout schedule ──────────────────────────────────────────────────────────
+
+#[test]
+fn test_payout_schedule_for_linear_stream_is_two_endpoints() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_500);
+    env.ledger().with_mut(|l| l.timestamp = 1_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+
+    let schedule = client.get_payout_schedule(&id);
+    assert_eq!(schedule.len(), 2);
+    assert_eq!(schedule.get(0).unwrap(), (1_000, 0));
+    assert_eq!(schedule.get(1).unwrap(), (1_100, 1_000));
+
+    // A top-up extends the final release point.
+    client.top_up_stream(&sender, &id, &500);
+    assert_eq!(
+        client.get_payout_schedule(&id).get(1).unwrap(),
+        (1_150, 1_500)
+    );
+
+    assert_eq!(client.get_payout_schedule(&999).len(), 0);
+}
+
+#[test]
+fn test_payout_schedule_and_curve_show_initial_unlock_and_cliff() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 2_000);
+    let unlock = StreamOptions {
+        initial_unlock: 500,
+        ..Default::default()
+    };
+    let id = try_create_with_options(&client, &token, &sender, &recipient, &unlock).unwrap();
+    assert_eq!(client.get_claimable_amount(&id), Some(500));
+    assert_eq!(
+        client.get_payout_schedule(&id),
+        vec![&env, (0u64, 500i128), (100, 1_000)]
+    );
+    assert_eq!(
+        client.claimable_curve(&id, &3),
+        vec![&env, (0u64, 500i128), (50, 750), (100, 1_000)]
+    );
+
+    let cliff = StreamOptions {
+        cliff_duration: 50,
+        ..Default::default()
+    };
+    let id = try_create_with_options(&client, &token, &sender, &recipient, &cliff).unwrap();
+    assert_eq!(
+        client.get_payout_schedule(&id),
+        vec![&env, (0u64, 0i128), (50, 500), (100, 1_000)]
+    );
+    assert_eq!(
+        client.claimable_curve(&id, &5),
+        vec![
+            &env,
+            (0u64, 0i128),
+            (25, 0),
+            (50, 500),
+            (75, 750),
+            (100, 1_000)
+        ]
+    );
+}
+
+// ─── Withdraw gate ────────────────────────────────────────────────────────────
+
+mod withdraw_gate {
+    use soroban_sdk::{contract, contractimpl, contracttype, Address, Env};
+
+    #[contracttype]
+    enum Key {
+        Open,
+        Broken,
+    }
+
+    /// Releases only once opened; traps instead of answering while broken.
+    #[contract]
+    pub struct MockGate;
+
+    #[contractimpl]
+    impl MockGate {
+        pub fn set_open(env: Env, open: bool) {
+            env.storage().instance().set(&Key::Open, &open);
+        }
+
+        pub fn set_broken(env: Env, broken: bool) {
+            env.storage().instance().set(&Key::Broken, &broken);
+        }
+
+        pub fn can_release(env: Env, _stream_id: u64, _recipient: Address, _amount: i128) -> bool {
+            let broken: bool = env.storage().instance().get(&Key::Broken).unwrap_or(false);
+            assert!(!broken, "gate unavailable");
+            env.storage().instance().get(&Key::Open).unwrap_or(false)
+        }
+    }
+}
+
+fn setup_gated_stream(
+    env: &Env,
+) -> (
+    StreamContractClient<'_>,
+    withdraw_gate::MockGateClient<'_>,
+    Address,
+    Address,
+    u64,
+) {
+    let (client, token, sender, recipient) = setup(env, 1_000);
+    let gate = withdraw_gate::MockGateClient::new(env, &env.register(withdraw_gate::MockGate, ()));
+    let options = StreamOptions {
+        withdraw_gate: Some(gate.address.clone()),
+        ..Default::default()
+    };
+    let id = try_create_with_options(&client, &token, &sender, &recipient, &options).unwrap();
+    (client, gate, token, recipient, id)
+}
+
+#[test]
+fn test_withdraw_gate_blocks_until_conditions_met() {
+    let env = Env::default();
+    let (client, gate, token, recipient, id) = setup_gated_stream(&env);
+
+    env.ledger().with_mut(|l| l.timestamp += 20);
+    assert_eq!(
+        client.try_withdraw(&recipient, &id),
+        Err(Ok(StreamError::GateRejected))
+    );
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&recipient), 0);
+
+    gate.set_open(&true);
+    assert_eq!(client.withdraw(&recipient, &id), 200);
+    assert_eq!(token_client.balance(&recipient), 200);
+}
+
+#[test]
+fn test_withdraw_gate_failure_is_a_rejection_not_a_trap() {
+    let env = Env::default();
+    let (client, gate, token, recipient, id) = setup_gated_stream(&env);
+    gate.set_open(&true);
+    gate.set_broken(&true);
+
+    env.ledger().with_mut(|l| l.timestamp += 20);
+    assert_eq!(
+        client.try_withdraw(&recipient, &id),
+        Err(Ok(StreamError::GateRejected))
+    );
+    assert_eq!(token::Client::new(&env, &token).balance(&recipient), 0);
+    assert_eq!(client.get_stream(&id).unwrap().withdrawn_amount, 0);
+}
+
+// ─── Multi-cycle pause accounting ─────────────────────────────────────────────
+
+#[test]
+fn test_multiple_pause_cycles_exclude_every_paused_window() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    env.ledger().with_mut(|l| l.timestamp = 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    // (pause_at, resume_at) windows totalling 10 + 15 + 7 = 32 seconds.
+    for (pause_at, resume_at) in [(1_010, 1_020), (1_035, 1_050), (1_051, 1_058)] {
+        env.ledger().with_mut(|l| l.timestamp = pause_at);
+        client.pause_stream(&sender, &id);
+
+        // Nothing accrues while paused, however long the gap.
+        let frozen = client.get_claimable_amount(&id).unwrap();
+        env.ledger().with_mut(|l| l.timestamp = resume_at);
+        assert_eq!(client.get_claimable_amount(&id), Some(frozen));
+        client.resume_stream(&sender, &id);
+    }
+
+    env.ledger().with_mut(|l| l.timestamp = 1_080);
+    let s = client.get_stream(&id).unwrap();
+    assert_eq!(s.total_paused_duration, 32);
+    // 80 s elapsed minus 32 s paused = 48 s at 10/s.
+    assert_eq!(client.get_claimable_amount(&id), Some(480));
+    assert_eq!(client.withdraw(&recipient, &id), 480);
+}
+
+#[test]
+fn test_pseudo_random_pause_cycles_match_linear_minus_paused() {
+    let mut seed = 229u64;
+    for _ in 0..8 {
+        let env = Env::default();
+        env.mock_all_auths();
+        let (token, _) = create_token(&env);
+        let sender = Address::generate(&env);
+        let recipient = Address::generate(&env);
+        mint(&env, &token, &sender, 100_000);
+
+        let client = create_contract(&env);
+        let start = 10_000;
+        env.ledger().with_mut(|l| l.timestamp = start);
+        let id = client.create_stream(&sender, &recipient, &token, &100_000, &10_000);
+
+        let mut now = start;
+        let mut paused_total = 0;
+        for _ in 0..(1 + next_seed(&mut seed) % 5) {
+            now += 1 + next_seed(&mut seed) % 200;
+            env.ledger().with_mut(|l| l.timestamp = now);
+            client.pause_stream(&sender, &id);
+
+            let gap = next_seed(&mut seed) % 300;
+            now += gap;
+            paused_total += gap;
+            env.ledger().with_mut(|l| l.timestamp = now);
+            client.resume_stream(&sender, &id);
+        }
+
+        now += next_seed(&mut seed) % 200;
+        env.ledger().with_mut(|l| l.timestamp = now);
+
+        let expected = ((now - start - paused_total) as i128 * 10).min(100_000);
+        assert_eq!(
+            client.get_stream(&id).unwrap().total_paused_duration,
+            paused_total
+        );
+        assert_eq!(client.get_claimable_amount(&id), Some(expected));
+    }
+}
+
+// ─── Timed pauses ─────────────────────────────────────────────────────────────
+
+#[test]
+fn test_pause_until_resumes_accrual_automatically() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_split_stream(&env);
+
+    env.ledger().with_mut(|l| l.timestamp = 20);
+    client.pause_until(&sender, &id, &50);
+    assert_eq!(client.get_stream_status(&id), StreamStatus::Paused);
+
+    env.ledger().with_mut(|l| l.timestamp = 40);
+    assert_eq!(client.get_claimable_amount(&id), Some(200));
+    env.ledger().with_mut(|l| l.timestamp = 50);
+    assert_eq!(client.get_claimable_amount(&id), Some(200));
+
+    // No transaction needed: at resume_at the stream reads as resumed.
+    let stream = client.get_stream(&id).unwrap();
+    assert!(!stream.paused);
+    assert_eq!(stream.status, StreamStatus::Active);
+    assert_eq!(stream.resume_at, None);
+    assert_eq!(stream.total_paused_duration, 30);
+    assert_eq!(stream.end_time, 130);
+
+    env.ledger().with_mut(|l| l.timestamp = 60);
+    assert_eq!(client.get_claimable_amount(&id), Some(300));
+    assert_eq!(client.withdraw(&recipient, &id), 300);
+
+    env.ledger().with_mut(|l| l.timestamp = 130);
+    assert_eq!(client.withdraw(&recipient, &id), 700);
+}
+
+#[test]
+fn test_pause_until_validates_and_allows_early_resume() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_split_stream(&env);
+    env.ledger().with_mut(|l| l.timestamp = 20);
+
+    assert_eq!(
+        client.try_pause_until(&sender, &id, &20),
+        Err(Ok(StreamError::InvalidDuration))
+    );
+    assert_eq!(
+        client.try_pause_until(&recipient, &id, &50),
+        Err(Ok(StreamError::Unauthorized))
+    );
+
+    client.pause_until(&sender, &id, &1_000);
+    let ev = env
+        .events()
+        .all()
+        .iter()
+        .find(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap()).unwrap()
+                == Symbol::new(&env, "stream_paused")
+        })
+        .expect("stream_paused event not found");
+    let payload = StreamPausedEvent::try_from_val(&env, &ev.2).unwrap();
+    assert_eq!(payload.resume_at, Some(1_000));
+
+    // The sender can lift the pause before it expires.
+    env.ledger().with_mut(|l| l.timestamp = 30);
+    client.resume_stream(&sender, &id);
+    let stream = client.get_stream(&id).unwrap();
+    assert!(!stream.paused);
+    assert_eq!(stream.resume_at, None);
+    env.ledger().with_mut(|l| l.timestamp = 40);
+    assert_eq!(client.get_claimable_amount(&id), Some(300));
+}
+
+// ─── Per-token fee override ───────────────────────────────────────────────────
+
+fn setup_token_fee(env: &Env) -> (StreamContractClient<'_>, Address, Address, Address, Address) {
+    let (client, token, sender, _) = setup(env, 100_000);
+    let admin = Address::generate(env);
+    let treasury = Address::generate(env);
+    client.initialize(&admin, &treasury, &100);
+    (client, token, sender, admin, treasury)
+}
+
+#[test]
+fn test_token_fee_override_replaces_global_rate() {
+    let env = Env::default();
+    let (client, token, sender, admin, treasury) = setup_token_fee(&env);
+
+    client.set_token_fee_bps(&admin, &token, &500);
+    assert_eq!(client.get_token_fee_bps(&token), Some(500));
+
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &10_000, &100);
+    assert_eq!(client.get_stream(&id).unwrap().deposited_amount, 9_500);
+    client.top_up_stream(&sender, &id, &1_000);
+    assert_eq!(client.get_stream(&id).unwrap().deposited_amount, 10_450);
+    assert_eq!(token::Client::new(&env, &token).balance(&treasury), 550);
+}
+
+#[test]
+fn test_token_fee_override_of_zero_and_fallback_to_global() {
+    let env = Env::default();
+    let (client, token, sender, admin, _) = setup_token_fee(&env);
+    let (other_token, _) = create_token(&env);
+    mint(&env, &other_token, &sender, 10_000);
+
+    client.set_token_fee_bps(&admin, &token, &0);
+    let free = client.create_stream(&sender, &Address::generate(&env), &token, &10_000, &100);
+    assert_eq!(client.get_stream(&free).unwrap().deposited_amount, 10_000);
+
+    // Tokens without an override pay the global 1%.
+    let global = client.create_stream(
+        &sender,
+        &Address::generate(&env),
+        &other_token,
+        &10_000,
+        &100,
+    );
+    assert_eq!(client.get_stream(&global).unwrap().deposited_amount, 9_900);
+
+    client.clear_token_fee_bps(&admin, &token);
+    assert_eq!(client.get_token_fee_bps(&token), None);
+    let cleared = client.create_stream(&sender, &Address::generate(&env), &token, &10_000, &100);
+    assert_eq!(client.get_stream(&cleared).unwrap().deposited_amount, 9_900);
+}
+
+#[test]
+fn test_set_token_fee_bps_validates_admin_and_rate() {
+    let env = Env::default();
+    let (client, token, sender, admin, _) = setup_token_fee(&env);
+
+    assert_eq!(
+        client.try_set_token_fee_bps(&sender, &token, &100),
+        Err(Ok(StreamError::NotAdmin))
+    );
+    assert_eq!(
+        client.try_set_token_fee_bps(&admin, &token, &1_001),
+        Err(Ok(StreamError::InvalidFeeRate))
+    );
+    assert_eq!(
+        client.try_clear_token_fee_bps(&sender, &token),
+        Err(Ok(StreamError::NotAdmin))
+    );
+}
+
+// ─── Counterparties ───────────────────────────────────────────────────────────
+
+#[test]
+fn test_get_counterparties_matches_creation() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    assert_eq!(
+        client.get_counterparties(&id),
+        Some((sender, recipient, token))
+    );
+    assert_eq!(client.get_counterparties(&999), None);
+}
+
+// ─── Private streams ──────────────────────────────────────────────────────────
+
+#[test]
+fn test_private_stream_excluded_from_enumeration_but_reachable_by_id() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 2_000);
+    let public_id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    let private_id = client.create_stream_with_options(
+        &sender,
+        &recipient,
+        &token,
+        &1_000,
+        &100,
+        &StreamOptions {
+            is_private: true,
+            ..Default::default()
+        },
+    );
+
+    let listed = client.get_streams_by_token_and_status(&token, &StreamStatus::Active, &0, &10);
+    assert_eq!(listed.len(), 1);
+    assert_eq!(
+        listed.get(0).unwrap(),
+        client.get_stream(&public_id).unwrap()
+    );
+
+    let private = client.get_stream(&private_id).unwrap();
+    assert!(!private.is_public);
+    assert!(client.get_stream(&public_id).unwrap().is_public);
+
+    // Still fully operable by id.
+    env.ledger().with_mut(|l| l.timestamp += 10);
+    assert_eq!(client.withdraw(&recipient, &private_id), 100);
+}
+
+// ─── Withdrawal memo ──────────────────────────────────────────────────────────
+
+#[test]
+fn test_withdraw_with_memo_round_trips_in_event() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    let withdrawn_event = |env: &Env| {
+        let events = env.events().all();
+        let ev = events
+            .iter()
+            .find(|e| {
+                Symbol::try_from_val(env, &e.1.get(0).unwrap()).unwrap()
+                    == Symbol::new(env, "tokens_withdrawn")
+            })
+            .expect("tokens_withdrawn event not found");
+        TokensWithdrawnEvent::try_from_val(env, &ev.2).unwrap()
+    };
+
+    env.ledger().with_mut(|l| l.timestamp += 10);
+    let memo = Symbol::new(&env, "invoice_2024_07");
+    assert_eq!(client.withdraw_with_memo(&recipient, &id, &memo), 100);
+    let ev = withdrawn_event(&env);
+    assert_eq!(ev.memo, Some(memo));
+    assert_eq!(ev.amount, 100);
+
+    env.ledger().with_mut(|l| l.timestamp += 10);
+    client.withdraw(&recipient, &id);
+    assert_eq!(withdrawn_event(&env).memo, None);
+}
+
+// ─── Finalization ─────────────────────────────────────────────────────────────
+
+fn setup_finalized_stream(env: &Env) -> (StreamContractClient<'_>, Address, Address, u64) {
+    let (client, token, sender, recipient) = setup(env, 2_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    client.cancel_stream(&sender, &id);
+    client.finalize_stream(&recipient, &id);
+    (client, sender, recipient, id)
+}
+
+#[test]
+fn test_finalize_requires_settled_stream_and_party() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    assert_eq!(
+        client.try_finalize_stream(&sender, &id),
+        Err(Ok(StreamError::StreamNotSettled))
+    );
+    client.cancel_stream(&sender, &id);
+    assert_eq!(
+        client.try_finalize_stream(&Address::generate(&env), &id),
+        Err(Ok(StreamError::Unauthorized))
+    );
+
+    client.finalize_stream(&sender, &id);
+    let s = client.get_stream(&id).unwrap();
+    assert!(s.finalized);
+    assert_eq!(s.status, StreamStatus::Cancelled);
+}
+
+#[test]
+fn test_finalized_stream_rejects_mutations() {
+    let env = Env::default();
+    let (client, sender, recipient, id) = setup_finalized_stream(&env);
+    let finalized = Some(Ok(StreamError::StreamFinalized));
+
+    assert_eq!(client.try_finalize_stream(&sender, &id).err(), finalized);
+    assert_eq!(
+        client.try_top_up_stream(&sender, &id, &100).err(),
+        finalized
+    );
+    assert_eq!(client.try_withdraw(&recipient, &id).err(), finalized);
+    assert_eq!(client.try_claim_on_behalf(&sender, &id).err(), finalized);
+    assert_eq!(client.try_cancel_stream(&sender, &id).err(), finalized);
+    assert_eq!(
+        client
+            .try_cancel_with_split(&sender, &recipient, &id, &5_000)
+            .err(),
+        finalized
+    );
+    assert_eq!(client.try_pause_stream(&sender, &id).err(), finalized);
+    assert_eq!(client.try_resume_stream(&sender, &id).err(), finalized);
+    assert_eq!(client.try_settle(&id).err(), finalized);
+    assert_eq!(client.try_reclaim_topup(&sender, &id).err(), finalized);
+    assert_eq!(client.try_ack_topup(&recipient, &id).err(), finalized);
+    assert_eq!(
+        client
+            .try_set_topup_ack_required(&recipient, &id, &true)
+            .err(),
+        finalized
+    );
+
+    // The record stays readable.
+    assert_eq!(client.get_stream(&id).unwrap().withdrawn_amount, 400);
+}
+
+// ─── Stream age ───────────────────────────────────────────────────────────────
+
+#[test]
+fn test_stream_age_grows_with_ledger_time() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    env.ledger().with_mut(|l| l.timestamp = 5_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+    assert_eq!(client.get_stream_age(&id), Some(0));
+
+    env.ledger().with_mut(|l| l.timestamp = 5_000 + 3 * 86_400);
+    assert_eq!(client.get_stream_age(&id), Some(3 * 86_400));
+
+    // A ledger behind start_time reports age 0 rather than underflowing.
+    env.ledger().with_mut(|l| l.timestamp = 4_000);
+    assert_eq!(client.get_stream_age(&id), Some(0));
+
+    assert_eq!(client.get_stream_age(&999), None);
+}
+
+// ─── Recipient earnings events ────────────────────────────────────────────────
+
+fn earnings_events(env: &Env) -> std::vec::Vec<RecipientEarningsEvent> {
+    env.events()
+        .all()
+        .iter()
+        .filter(|e| {
+            Symbol::try_from_val(env, &e.1.get(0).unwrap()).unwrap()
+                == Symbol::new(env, "recipient_earnings")
+        })
+        .map(|e| RecipientEarningsEvent::try_from_val(env, &e.2).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_recipient_earnings_event_fires_on_threshold_crossing() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 2_000);
+    let admin = Address::generate(&env);
+    client.initialize(&admin, &Address::generate(&env), &0);
+    client.set_earnings_threshold(&admin, &token, &500);
+
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    let other = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    env.ledger().with_mut(|l| l.timestamp += 30);
+    client.withdraw(&recipient, &id);
+    assert!(earnings_events(&env).is_empty());
+
+    // Lifetime earnings aggregate across streams: 300 + 300 = 600 crosses 500.
+    client.withdraw(&recipient, &other);
+    let events = earnings_events(&env);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].lifetime_withdrawn, 600);
+    assert_eq!(events[0].threshold_crossed, 500);
+    assert_eq!(client.get_recipient_earnings(&recipient, &token), 600);
+
+    // Cancellation payouts count too: +400 reaches 1 000.
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    client.cancel_stream(&sender, &id);
+    let events = earnings_events(&env);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].threshold_crossed, 1_000);
+}
+
+#[test]
+fn test_recipient_earnings_events_disabled_by_default() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    assert_eq!(client.get_earnings_threshold(&token), 0);
+
+    env.ledger().with_mut(|l| l.timestamp += 100);
+    client.withdraw(&recipient, &id);
+    assert!(earnings_events(&env).is_empty());
+    assert_eq!(client.get_recipient_earnings(&recipient, &token), 1_000);
+}
+
+// ─── Streams needing attention ────────────────────────────────────────────────
+
+#[test]
+fn test_streams_needing_attention_reports_each_reason() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token, _) = create_token(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    mint(&env, &token, &sender, 10_000_000);
+
+    let day = 86_400_u64;
+    let client = create_contract(&env);
+    env.ledger().with_mut(|l| l.timestamp = 1_000);
+    let healthy = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &(100 * day as i128),
+        &(100 * day),
+    );
+    let near = client.create_stream(&sender, &recipient, &token, &(2 * day as i128), &(2 * day));
+    let done = client.create_stream(&sender, &recipient, &token, &(day as i128), &day);
+
+    env.ledger()
+        .with_mut(|l| l.timestamp = 1_000 + day + day / 2);
+    let flagged = client.get_streams_needing_attention(&0, &10);
+    assert_eq!(flagged.len(), 2);
+    assert_eq!(
+        flagged.get(0).unwrap(),
+        (near, AttentionReason::NearDepleted)
+    );
+    assert_eq!(
+        flagged.get(1).unwrap(),
+        (done, AttentionReason::CompletedNotClosed)
+    );
+
+    // Left untouched for over half the TTL threshold, even a healthy stream is flagged.
+    env.ledger().with_mut(|l| l.timestamp = 1_000 + 16 * day);
+    let flagged = client.get_streams_needing_attention(&0, &1);
+    assert_eq!(flagged.get(0).unwrap(), (healthy, AttentionReason::LowTtl));
+
+    client.settle(&healthy);
+    assert_eq!(client.get_streams_needing_attention(&0, &1).len(), 0);
+}
+
+#[test]
+fn test_streams_needing_attention_scans_id_window() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 3_000);
+    for _ in 0..3 {
+        client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+    }
+
+    // Every 100 s stream is near depletion; offset/limit select by id.
+    let window = client.get_streams_needing_attention(&1, &1);
+    assert_eq!(window.len(), 1);
+    assert_eq!(window.get(0).unwrap().0, 2);
+    assert_eq!(client.get_streams_needing_attention(&3, &10).len(), 0);
+}
+
+// ─── Initial unlock ───────────────────────────────────────────────────────────
+
+#[test]
+fn test_initial_unlock_is_withdrawable_immediately() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = try_create_with_options(
+        &client,
+        &token,
+        &sender,
+        &recipient,
+        &StreamOptions {
+            initial_unlock: 200,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    let s = client.get_stream(&id).unwrap();
+    assert_eq!(s.initial_unlock, 200);
+    assert_eq!(s.rate_per_second, 8);
+    assert_eq!(client.get_claimable_amount(&id), Some(200));
+    assert_eq!(client.withdraw(&recipient, &id), 200);
+
+    // The remainder streams linearly: 8/s after the bonus.
+    env.ledger().with_mut(|l| l.timestamp += 50);
+    assert_eq!(client.withdraw(&recipient, &id), 400);
+    env.ledger().with_mut(|l| l.timestamp += 50);
+    assert_eq!(client.withdraw(&recipient, &id), 400);
+    assert!(client.is_stream_completed(&id));
+}
+
+#[test]
+fn test_initial_unlock_rejects_out_of_range_amounts() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    assert_eq!(
+        try_create_with_options(
+            &client,
+            &token,
+            &sender,
+            &recipient,
+            &StreamOptions {
+                initial_unlock: -1,
+                ..Default::default()
+            }
+        ),
+        Err(StreamError::InvalidAmount)
+    );
+    assert_eq!(
+        try_create_with_options(
+            &client,
+            &token,
+            &sender,
+            &recipient,
+            &StreamOptions {
+                initial_unlock: 1_001,
+                ..Default::default()
+            }
+        ),
+        Err(StreamError::InvalidAmount)
+    );
+}
+
+// ─── Streams involving an address ─────────────────────────────────────────────
+
+#[test]
+fn test_get_streams_involving_merges_sorted_and_unique() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token, _) = create_token(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    mint(&env, &token, &alice, 10_000);
+    mint(&env, &token, &bob, 10_000);
+    let carol = Address::generate(&env);
+    mint(&env, &token, &carol, 10_000);
+
+    let client = create_contract(&env);
+    let a_to_b = client.create_stream(&alice, &bob, &token, &1_000, &100);
+    let b_to_a = client.create_stream(&bob, &alice, &token, &1_000, &100);
+    let c_to_a = client.create_stream(&carol, &alice, &token, &1_000, &100);
+    let a_to_b_2 = client.create_stream(&alice, &bob, &token, &1_000, &100);
+
+    let involving = client.get_streams_involving(&alice, &0, &10);
+    assert_eq!(involving, vec![&env, a_to_b, b_to_a, c_to_a, a_to_b_2]);
+
+    let page = client.get_streams_involving(&alice, &1, &2);
+    assert_eq!(page, vec![&env, b_to_a, c_to_a]);
+    assert_eq!(client.get_streams_involving(&alice, &10, &10).len(), 0);
+
+    assert_eq!(
+        client.get_streams_involving(&bob, &0, &10),
+        vec![&env, a_to_b, b_to_a, a_to_b_2]
+    );
+}
+
+#[test]
+fn test_get_my_streams_lists_callers_streams_and_requires_auth() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token, _) = create_token(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let carol = Address::generate(&env);
+    mint(&env, &token, &alice, 10_000);
+    mint(&env, &token, &bob, 10_000);
+
+    let client = create_contract(&env);
+    let a_to_b = client.create_stream(&alice, &bob, &token, &1_000, &100);
+    let b_to_c = client.create_stream(&bob, &carol, &token, &1_000, &100);
+    let b_to_a = client.create_stream(&bob, &alice, &token, &1_000, &100);
+
+    assert_eq!(client.get_my_streams(&alice), vec![&env, a_to_b, b_to_a]);
+    assert_eq!(env.auths()[0].0, alice);
+    assert_eq!(client.get_my_streams(&carol), vec![&env, b_to_c]);
+
+    env.mock_auths(&[]);
+    assert!(client.try_get_my_streams(&alice).is_err());
+}
+
+#[test]
+fn test_get_my_streams_includes_private_streams() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 2_000);
+    let public = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    let options = StreamOptions {
+        is_private: true,
+        ..Default::default()
+    };
+    let private = try_create_with_options(&client, &token, &sender, &recipient, &options).unwrap();
+
+    // Public indexes never list the private stream.
+    assert_eq!(
+        client.get_streams_by_recipient(&recipient),
+        vec![&env, public]
+    );
+    assert_eq!(
+        client.get_my_streams(&recipient),
+        vec![&env, public, private]
+    );
+    assert_eq!(client.get_my_streams(&sender), vec![&env, public, private]);
+
+    // The private stream follows a recipient transfer.
+    let new_recipient = Address::generate(&env);
+    client.transfer_recipient(&recipient, &private, &new_recipient);
+    assert_eq!(client.get_my_streams(&recipient), vec![&env, public]);
+    assert_eq!(client.get_my_streams(&new_recipient), vec![&env, private]);
+    assert_eq!(client.get_my_streams(&sender), vec![&env, public, private]);
+}
+
+#[test]
+fn test_get_streams_by_sender_is_disjoint_and_ordered() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token, _) = create_token(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    mint(&env, &token, &alice, 10_000);
+    mint(&env, &token, &bob, 10_000);
+
+    let client = create_contract(&env);
+    let a1 = client.create_stream(&alice, &recipient, &token, &1_000, &100);
+    let b1 = client.create_stream(&bob, &recipient, &token, &1_000, &100);
+    let a2 = client.create_stream(&alice, &recipient, &token, &1_000, &100);
+    let b2 = client.create_stream(&bob, &recipient, &token, &1_000, &100);
+
+    assert_eq!(client.get_streams_by_sender(&alice), vec![&env, a1, a2]);
+    assert_eq!(client.get_streams_by_sender(&bob), vec![&env, b1, b2]);
+}
+
+#[test]
+fn test_get_streams_by_recipient_lists_each_recipients_streams() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 10_000);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let a1 = client.create_stream(&sender, &alice, &token, &1_000, &100);
+    let b1 = client.create_stream(&sender, &bob, &token, &1_000, &100);
+    let a2 = client.create_stream(&sender, &alice, &token, &1_000, &100);
+    client.cancel_stream(&sender, &a1);
+
+    assert_eq!(client.get_streams_by_recipient(&alice), vec![&env, a1, a2]);
+    assert_eq!(client.get_streams_by_recipient(&bob), vec![&env, b1]);
+    assert_eq!(
+        client.get_streams_by_recipient(&Address::generate(&env)),
+        Vec::new(&env)
+    );
+}
+
+// ─── Create simulation ────────────────────────────────────────────────────────
+
+#[test]
+fn test_simulate_create_matches_actual_create() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 10_000);
+    client.initialize(&Address::generate(&env), &Address::generate(&env), &250);
+    env.ledger().with_mut(|l| l.timestamp = 7_000);
+
+    // 1 003 - 25 fee = 978 net over 97 s: rate 10 truncated, end at the duration.
+    let (rate, end_time) = client.simulate_create(&1_003, &97);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_003, &97);
+
+    let s = client.get_stream(&id).unwrap();
+    assert_eq!(rate, s.rate_per_second);
+    assert_eq!(rate, 10);
+    assert_eq!(end_time, 7_097);
+    assert_eq!(client.get_payout_schedule(&id).get(1).unwrap().0, end_time);
+}
+
+#[test]
+fn test_simulate_create_rejects_same_terms_as_create() {
+    let env = Env::default();
+    let client = create_contract(&env);
+
+    assert_eq!(
+        client.try_simulate_create(&0, &100),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_simulate_create(&100, &0),
+        Err(Ok(StreamError::InvalidDuration))
+    );
+    assert_eq!(
+        client.try_simulate_create(&99, &100),
+        Err(Ok(StreamError::InvalidRate))
+    );
+}
+
+// ─── Milestone release ────────────────────────────────────────────────────────
+
+#[test]
+fn test_milestones_release_by_ledger_sequence() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token, _) = create_token(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    mint(&env, &token, &sender, 1_000);
+    env.ledger().with_mut(|l| l.sequence_number = 5);
+
+    let client = create_contract(&env);
+    let schedule = vec![&env, (10u32, 300i128), (20, 600), (30, 1_000)];
+    let id = try_create_with_options(
+        &client,
+        &token,
+        &sender,
+        &recipient,
+        &StreamOptions {
+            milestones: Some(schedule),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    // Time alone releases nothing before the first milestone.
+    env.ledger().with_mut(|l| l.timestamp += 1_000);
+    assert_eq!(client.get_claimable_amount(&id), Some(0));
+
+    env.ledger().with_mut(|l| l.sequence_number = 10);
+    assert_eq!(client.get_claimable_amount(&id), Some(300));
+    assert_eq!(client.withdraw(&recipient, &id), 300);
+
+    env.ledger().with_mut(|l| l.sequence_number = 25);
+    assert_eq!(client.get_claimable_amount(&id), Some(300));
+
+    env.ledger().with_mut(|l| l.sequence_number = 30);
+    assert_eq!(client.withdraw(&recipient, &id), 700);
+    assert!(client.is_stream_completed(&id));
+}
+
+#[test]
+fn test_milestone_reached_before_pause_goes_to_recipient_on_cancel() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token, _) = create_token(&env);
+    let token_client = token::Client::new(&env, &token);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    mint(&env, &token, &sender, 1_000);
+    env.ledger().with_mut(|l| l.sequence_number = 5);
+
+    let client = create_contract(&env);
+    let schedule = vec![&env, (10u32, 400i128), (20, 1_000)];
+    let id = try_create_with_options(
+        &client,
+        &token,
+        &sender,
+        &recipient,
+        &StreamOptions {
+            milestones: Some(schedule),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    env.ledger().with_mut(|l| {
+        l.sequence_number = 10;
+        l.timestamp += 10;
+    });
+    client.pause_stream(&sender, &id);
+    assert_eq!(client.get_claimable_amount(&id), Some(400));
+
+    client.cancel_stream(&sender, &id);
+    assert_eq!(token_client.balance(&recipient), 400);
+    assert_eq!(token_client.balance(&sender), 600);
+}
+
+#[test]
+fn test_milestones_reject_invalid_schedules() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let invalid = [
+        Vec::new(&env),
+        vec![&env, (10u32, 500i128), (10, 1_000)],
+        vec![&env, (10u32, 600i128), (20, 500), (30, 1_000)],
+        vec![&env, (10u32, 500i128), (20, 900)],
+        vec![&env, (10u32, -1i128), (20, 1_000)],
+    ];
+    for schedule in invalid {
+        assert_eq!(
+            try_create_with_options(
+                &client,
+                &token,
+                &sender,
+                &recipient,
+                &StreamOptions {
+                    milestones: Some(schedule),
+                    ..Default::default()
+                }
+            ),
+            Err(StreamError::InvalidMilestones)
+        );
+    }
+}
+
+#[test]
+fn test_payout_schedule_lists_milestones() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let schedule = vec![&env, (10u32, 400i128), (20, 1_000)];
+    let id = try_create_with_options(
+        &client,
+        &token,
+        &sender,
+        &recipient,
+        &StreamOptions {
+            milestones: Some(schedule),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    assert_eq!(
+        client.get_payout_schedule(&id),
+        vec![&env, (10u64, 400i128), (20, 1_000)]
+    );
+}
+
+#[test]
+fn test_milestone_stream_time_views_follow_milestones() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    env.ledger().with_mut(|l| l.sequence_number = 5);
+    let schedule = vec![&env, (10u32, 300i128), (20, 600), (30, 1_000)];
+    let id = try_create_with_options(
+        &client,
+        &token,
+        &sender,
+        &recipient,
+        &StreamOptions {
+            milestones: Some(schedule),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    // Sequences 10, 20 and 30 are 5, 15 and 25 ledgers (x5 s) away.
+    assert_eq!(
+        client.claimable_curve(&id, &10),
+        vec![&env, (0u64, 0i128), (25, 300), (75, 600), (125, 1_000)]
+    );
+    assert_eq!(client.seconds_funded(&id), 125);
+    assert_eq!(client.get_stream_summary(&id), Some((0, 0, 125)));
+    assert_eq!(client.preview_stream(&id).unwrap().end_time, 125);
+
+    // The estimate holds as the ledger advances at the expected pace.
+    env.ledger().with_mut(|l| {
+        l.sequence_number = 20;
+        l.timestamp = 75;
+    });
+    assert_eq!(client.seconds_funded(&id), 50);
+    assert_eq!(client.get_stream_summary(&id), Some((0, 600, 125)));
+    assert_eq!(client.claimable_curve(&id, &2).get(3), Some((125, 1_000)));
+}
+
+// ─── Circuit breaker ──────────────────────────────────────────────────────────
+
+fn setup_circuit_breaker(
+    env: &Env,
+    max_volume: i128,
+    window_secs: u64,
+) -> (StreamContractClient<'_>, Address, Address, Address) {
+    let (client, token, sender, recipient) = setup(env, 10_000);
+    let admin = Address::generate(env);
+    client.initialize(&admin, &Address::generate(env), &0);
+    client.set_circuit_breaker(&admin, &token, &max_volume, &window_secs);
+    client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    (client, token, recipient, admin)
+}
+
+#[test]
+fn test_circuit_breaker_trips_and_blocks_withdrawals() {
+    let env = Env::default();
+    let (client, token, recipient, admin) = setup_circuit_breaker(&env, 500, 100);
+    assert_eq!(
+        client.get_circuit_breaker(&token),
+        Some(CircuitBreakerConfig {
+            max_volume: 500,
+            window_secs: 100,
+        })
+    );
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    assert_eq!(client.withdraw(&recipient, &1), 400);
+    assert!(!client.is_protocol_paused());
+
+    // The crossing withdrawal completes, then the breaker engages.
+    assert_eq!(client.withdraw(&recipient, &2), 400);
+    let tripped: std::vec::Vec<CircuitBreakerTrippedEvent> = env
+        .events()
+        .all()
+        .iter()
+        .filter(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap()).unwrap()
+                == Symbol::new(&env, "circuit_breaker_tripped")
+        })
+        .map(|e| CircuitBreakerTrippedEvent::try_from_val(&env, &e.2).unwrap())
+        .collect();
+    assert_eq!(tripped.len(), 1);
+    assert_eq!(tripped[0].volume, 800);
+    assert_eq!(tripped[0].max_volume, 500);
+    assert!(client.is_protocol_paused());
+
+    env.ledger().with_mut(|l| l.timestamp += 10);
+    assert_eq!(
+        client.try_withdraw(&recipient, &1),
+        Err(Ok(StreamError::ProtocolPaused))
+    );
+
+    client.reset_circuit_breaker(&admin);
+    assert!(!client.is_protocol_paused());
+    assert_eq!(client.withdraw(&recipient, &1), 100);
+}
+
+#[test]
+fn test_circuit_breaker_window_rolls_over() {
+    let env = Env::default();
+    let (client, _, recipient, _) = setup_circuit_breaker(&env, 500, 30);
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    assert_eq!(client.withdraw(&recipient, &1), 400);
+
+    // The first window has expired, so the second withdrawal opens a new one.
+    env.ledger().with_mut(|l| l.timestamp += 30);
+    assert_eq!(client.withdraw(&recipient, &2), 700);
+    assert!(client.is_protocol_paused());
+}
+
+#[test]
+fn test_circuit_breaker_config_is_validated_and_admin_only() {
+    let env = Env::default();
+    let (client, token, recipient, admin) = setup_circuit_breaker(&env, 500, 100);
+    let outsider = Address::generate(&env);
+
+    assert_eq!(
+        client.try_set_circuit_breaker(&admin, &token, &-1, &100),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_set_circuit_breaker(&admin, &token, &500, &0),
+        Err(Ok(StreamError::InvalidDuration))
+    );
+    assert_eq!(
+        client.try_set_circuit_breaker(&outsider, &token, &500, &100),
+        Err(Ok(StreamError::NotAdmin))
+    );
+    assert_eq!(
+        client.try_reset_circuit_breaker(&outsider),
+        Err(Ok(StreamError::NotAdmin))
+    );
+
+    client.set_circuit_breaker(&admin, &token, &0, &0);
+    assert_eq!(client.get_circuit_breaker(&token), None);
+    env.ledger().with_mut(|l| l.timestamp += 100);
+    assert_eq!(client.withdraw(&recipient, &1), 1_000);
+    assert!(!client.is_protocol_paused());
+}
+
+#[test]
+fn test_cancel_is_neither_counted_nor_blocked_by_circuit_breaker() {
+    let env = Env::default();
+    let (client, token, recipient, _) = setup_circuit_breaker(&env, 500, 100);
+    let sender = client.get_stream(&1).unwrap().sender;
+    let token_client = token::Client::new(&env, &token);
+
+    env.ledger().with_mut(|l| l.timestamp = 40);
+    assert_eq!(client.withdraw(&recipient, &1), 400);
+    // Cancellation pays the recipient 400 more, but the window stays at 400.
+    client.cancel_stream(&sender, &2);
+    assert_eq!(token_client.balance(&recipient), 800);
+    assert!(!client.is_protocol_paused());
+
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    env.ledger().with_mut(|l| l.timestamp = 60);
+    assert_eq!(client.withdraw(&recipient, &1), 200);
+    assert!(client.is_protocol_paused());
+
+    // Tripped: withdrawals stop, but the sender can still exit and the
+    // recipient is still paid what had accrued.
+    assert_eq!(
+        client.try_withdraw(&recipient, &1),
+        Err(Ok(StreamError::ProtocolPaused))
+    );
+    let sender_before = token_client.balance(&sender);
+    client.cancel_stream(&sender, &id);
+    assert_eq!(token_client.balance(&recipient), 1_200);
+    assert_eq!(token_client.balance(&sender) - sender_before, 800);
+}
+
+#[test]
+fn test_circuit_breaker_and_contract_pause_are_released_independently() {
+    let env = Env::default();
+    let (client, token, recipient, admin) = setup_circuit_breaker(&env, 500, 100);
+    let sender = client.get_stream(&1).unwrap().sender;
+
+    env.ledger().with_mut(|l| l.timestamp = 60);
+    client.withdraw(&recipient, &1);
+    assert!(client.is_protocol_paused());
+    // The breaker only stops withdrawals.
+    client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    client.pause_contract(&admin);
+    // With both engaged, withdrawals report the breaker first.
+    assert_eq!(
+        client.try_withdraw(&recipient, &2),
+        Err(Ok(StreamError::ProtocolPaused))
+    );
+    client.reset_circuit_breaker(&admin);
+    assert!(client.is_contract_paused());
+    assert_eq!(
+        client.try_withdraw(&recipient, &2),
+        Err(Ok(StreamError::ContractPaused))
+    );
+    client.unpause_contract(&admin);
+    assert_eq!(client.withdraw(&recipient, &2), 600);
+}
+
+// ─── Contract pause ───────────────────────────────────────────────────────────
+
+#[test]
+fn test_pause_contract_is_admin_only() {
+    let env = Env::default();
+    let (client, _, _, admin, _) = setup_token_fee(&env);
+    let outsider = Address::generate(&env);
+
+    assert_eq!(
+        client.try_pause_contract(&outsider),
+        Err(Ok(StreamError::NotAdmin))
+    );
+    assert!(!client.is_contract_paused());
+    client.pause_contract(&admin);
+    assert!(client.is_contract_paused());
+    assert_eq!(
+        client.try_unpause_contract(&outsider),
+        Err(Ok(StreamError::NotAdmin))
+    );
+    assert!(client.is_contract_paused());
+}
+
+#[test]
+fn test_contract_pause_blocks_mutations_until_unpaused() {
+    let env = Env::default();
+    let (client, token, sender, admin, _) = setup_token_fee(&env);
+    let recipient = Address::generate(&env);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    env.ledger().with_mut(|l| l.timestamp = 10);
+
+    client.pause_contract(&admin);
+    assert_eq!(
+        client.try_create_stream(&sender, &recipient, &token, &1_000, &100),
+        Err(Ok(StreamError::ContractPaused))
+    );
+    assert_eq!(
+        client.try_top_up_stream(&sender, &id, &100),
+        Err(Ok(StreamError::ContractPaused))
+    );
+    assert_eq!(
+        client.try_withdraw(&recipient, &id),
+        Err(Ok(StreamError::ContractPaused))
+    );
+
+    client.unpause_contract(&admin);
+    assert!(!client.is_contract_paused());
+    assert_eq!(client.withdraw(&recipient, &id), 90);
+    client.top_up_stream(&sender, &id, &100);
+    client.create_stream(&sender, &recipient, &token, &1_000, &100);
+}
+
+#[test]
+fn test_contract_pause_emits_event() {
+    let env = Env::default();
+    let (client, _, _, admin, _) = setup_token_fee(&env);
+
+    client.pause_contract(&admin);
+    let ev = env.events().all().last().unwrap();
+    assert_eq!(
+        ContractPauseUpdatedEvent::try_from_val(&env, &ev.2).unwrap(),
+        ContractPauseUpdatedEvent {
+            admin,
+            paused: true,
+        }
+    );
+}
+
+// ─── Top-up preview ───────────────────────────────────────────────────────────
+
+#[test]
+fn test_preview_topup_matches_post_topup_end_time() {
+    let env = Env::default();
+    let (client, token, sender, admin, _) = setup_token_fee(&env);
+    client.set_token_fee_bps(&admin, &token, &500);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &10_000, &100);
+    env.ledger().with_mut(|l| l.timestamp += 30);
+
+    let preview = client.preview_topup(&id, &1_000);
+    assert_eq!(client.get_stream(&id).unwrap().deposited_amount, 9_500);
+
+    client.top_up_stream(&sender, &id, &1_000);
+    let schedule = client.get_payout_schedule(&id);
+    assert_eq!(schedule.get(1).unwrap().0, preview);
+    // 10 450 net at 95/s from t = 0.
+    assert_eq!(preview, 110);
+}
+
+#[test]
+fn test_preview_topup_rejects_invalid_requests() {
+    let env = Env::default();
+    let (client, token, sender, _, _) = setup_token_fee(&env);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+
+    assert_eq!(
+        client.try_preview_topup(&id, &0),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_preview_topup(&99, &100),
+        Err(Ok(StreamError::StreamNotFound))
+    );
+    client.cancel_stream(&sender, &id);
+    assert_eq!(
+        client.try_preview_topup(&id, &100),
+        Err(Ok(StreamError::StreamInactive))
+    );
+}
+
+// ─── Top-up to runway ─────────────────────────────────────────────────────────
+
+#[test]
+fn test_top_up_to_runway_meets_target_exactly() {
+    let env = Env::default();
+    let (client, token, sender, _, treasury) = setup_token_fee(&env);
+    // 1% fee: 990 net at 9/s.
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+    let token_client = token::Client::new(&env, &token);
+    let sender_before = token_client.balance(&sender);
+    let treasury_before = token_client.balance(&treasury);
+
+    env.ledger().with_mut(|l| l.timestamp = 30);
+    // 70 s funded; 130 s more at 9/s is 1 170 net, which 1 181 gross covers.
+    let pulled = client.top_up_to_runway(&sender, &id, &200);
+    assert_eq!(pulled, 1_181);
+    assert_eq!(token_client.balance(&sender), sender_before - 1_181);
+    assert_eq!(token_client.balance(&treasury), treasury_before + 11);
+
+    assert_eq!(client.preview_stream(&id).unwrap().end_time, 230);
+    assert!(client.seconds_funded(&id) >= 200);
+}
+
+#[test]
+fn test_top_up_to_runway_pulls_nothing_when_already_funded() {
+    let env = Env::default();
+    let (client, token, sender, _, _) = setup_token_fee(&env);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+    let token_client = token::Client::new(&env, &token);
+    let sender_before = token_client.balance(&sender);
+
+    env.ledger().with_mut(|l| l.timestamp = 30);
+    assert_eq!(client.top_up_to_runway(&sender, &id, &70), 0);
+    assert_eq!(token_client.balance(&sender), sender_before);
+    assert_eq!(client.get_stream(&id).unwrap().deposited_amount, 990);
+}
+
+#[test]
+fn test_top_up_to_runway_rejects_overflowing_amount() {
+    let env = Env::default();
+    let deposit = 10i128.pow(30);
+    let (client, token, sender, recipient) = setup(&env, deposit);
+    // 10^30 per second for u64::MAX seconds does not fit in an i128.
+    let id = client.create_stream(&sender, &recipient, &token, &deposit, &1);
+
+    assert_eq!(
+        client.try_top_up_to_runway(&sender, &id, &u64::MAX),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+    // Fits after the rate multiply, but not once grossed up by `MAX_BPS`.
+    let seconds = (i128::MAX / deposit / 5_000) as u64;
+    assert_eq!(
+        client.try_top_up_to_runway(&sender, &id, &seconds),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+}
+
+// ─── Stream range snapshots ───────────────────────────────────────────────────
+
+#[test]
+fn test_get_streams_page_stitches_full_set_with_count() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 10_000);
+    assert_eq!(client.get_stream_count(), 0);
+    let empty = client.get_streams_page(&1, &10);
+    assert!(empty.streams.is_empty());
+    assert_eq!(empty.next_id, None);
+
+    let mut public = Vec::new(&env);
+    for i in 0..5 {
+        let recipient = Address::generate(&env);
+        if i == 2 {
+            client.create_stream_with_options(
+                &sender,
+                &recipient,
+                &token,
+                &100,
+                &10,
+                &StreamOptions {
+                    is_private: true,
+                    ..Default::default()
+                },
+            );
+        } else {
+            public.push_back(client.create_stream(&sender, &recipient, &token, &100, &10));
+        }
+    }
+    // Closed streams are still part of the backfill.
+    client.cancel_stream(&sender, &public.get(0).unwrap());
+    assert_eq!(client.get_stream_count(), 5);
+
+    let mut seen = Vec::new(&env);
+    let mut cursor = Some(0);
+    let mut calls = 0;
+    while let Some(start_id) = cursor {
+        let page = client.get_streams_page(&start_id, &2);
+        for (id, stream) in page.streams.iter() {
+            assert_eq!(client.get_stream(&id), Some(stream));
+            seen.push_back(id);
+        }
+        cursor = page.next_id;
+        calls += 1;
+    }
+    assert_eq!(seen, public);
+    // Five ids scanned two at a time, the private one leaving a short page.
+    assert_eq!(calls, 3);
+
+    let page = client.get_streams_page(&1, &0);
+    assert!(page.streams.is_empty());
+    assert_eq!(page.next_id, Some(1));
+}
+
+#[test]
+fn test_get_streams_page_bounds_ids_scanned() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 10_000);
+    for _ in 0..3 {
+        client.create_stream_with_options(
+            &sender,
+            &Address::generate(&env),
+            &token,
+            &100,
+            &10,
+            &StreamOptions {
+                is_private: true,
+                ..Default::default()
+            },
+        );
+    }
+    let public = client.create_stream(&sender, &Address::generate(&env), &token, &100, &10);
+
+    // A page over private ids is empty but still advances the cursor.
+    let page = client.get_streams_page(&1, &2);
+    assert!(page.streams.is_empty());
+    assert_eq!(page.next_id, Some(3));
+
+    let page = client.get_streams_page(&3, &2);
+    assert_eq!(page.streams.len(), 1);
+    assert_eq!(page.streams.get(0).unwrap().0, public);
+    assert_eq!(page.next_id, None);
+}
+
+#[test]
+fn test_get_stream_range_reports_gaps_as_none() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 10_000);
+    let open = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    let cancelled = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    let private = client.create_stream_with_options(
+        &sender,
+        &recipient,
+        &token,
+        &1_000,
+        &100,
+        &StreamOptions {
+            is_private: true,
+            ..Default::default()
+        },
+    );
+    let later = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    client.cancel_stream(&sender, &cancelled);
+
+    let range = client.get_stream_range(&open, &(later + 1));
+    assert_eq!(range.len(), 5);
+    assert_eq!(range.get(0).unwrap(), (open, client.get_stream(&open)));
+    assert_eq!(range.get(1).unwrap(), (cancelled, None));
+    assert_eq!(range.get(2).unwrap(), (private, None));
+    assert_eq!(range.get(3).unwrap().1.unwrap().sender, sender);
+    assert_eq!(range.get(4).unwrap(), (later + 1, None));
+
+    assert_eq!(client.get_stream_range(&3, &2).len(), 0);
+    assert_eq!(client.get_stream_range(&1, &1_000).len(), 50);
+}
+
+// ─── Dust sweeping ────────────────────────────────────────────────────────────
+
+fn setup_dust_stream(
+    env: &Env,
+    threshold: i128,
+) -> (StreamContractClient<'_>, Address, Address, u64) {
+    let (client, token, sender, recipient) = setup(env, 1_000);
+    let admin = Address::generate(env);
+    client.initialize(&admin, &Address::generate(env), &0);
+    client.set_dust_threshold(&admin, &token, &threshold);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    (client, token, recipient, id)
+}
+
+#[test]
+fn test_withdraw_sweeps_dust_and_completes_stream() {
+    let env = Env::default();
+    let (client, token, recipient, id) = setup_dust_stream(&env, 50);
+    assert_eq!(client.get_dust_threshold(&token), 50);
+
+    // 960 vested leaves 40 < 50 behind, so the withdrawal takes everything.
+    env.ledger().with_mut(|l| l.timestamp += 96);
+    assert_eq!(client.withdraw(&recipient, &id), 1_000);
+    assert!(client.is_stream_completed(&id));
+    assert_eq!(token::Client::new(&env, &token).balance(&recipient), 1_000);
+
+    let stats = client.get_protocol_stats();
+    assert_eq!(stats.active_streams, 0);
+    assert_eq!(
+        client
+            .get_token_stats(&0, &50)
+            .get(token)
+            .unwrap()
+            .total_locked,
+        0
+    );
+}
+
+#[test]
+fn test_withdraw_leaves_remainder_at_or_above_dust_threshold() {
+    let env = Env::default();
+    let (client, _, recipient, id) = setup_dust_stream(&env, 50);
+
+    env.ledger().with_mut(|l| l.timestamp += 95);
+    assert_eq!(client.withdraw(&recipient, &id), 950);
+    assert!(!client.is_stream_completed(&id));
+
+    env.ledger().with_mut(|l| l.timestamp += 5);
+    assert_eq!(client.withdraw(&recipient, &id), 50);
+    assert!(client.is_stream_completed(&id));
+}
+
+// ─── Effective balance ────────────────────────────────────────────────────────
+
+#[test]
+fn test_get_effective_balance_components_stay_consistent() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 2_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    assert_eq!(client.get_effective_balance(&id), (1_000, 0, 0));
+
+    env.ledger().with_mut(|l| l.timestamp += 25);
+    assert_eq!(client.get_effective_balance(&id), (1_000, 0, 250));
+
+    client.withdraw(&recipient, &id);
+    env.ledger().with_mut(|l| l.timestamp += 25);
+    client.top_up_stream(&sender, &id, &500);
+    let (deposited, withdrawn, accrued) = client.get_effective_balance(&id);
+    assert_eq!((deposited, withdrawn, accrued), (1_500, 250, 250));
+    assert_eq!(Some(accrued), client.get_claimable_amount(&id));
+
+    env.ledger().with_mut(|l| l.timestamp += 1_000);
+    assert_eq!(client.get_effective_balance(&id), (1_500, 250, 1_250));
+    client.withdraw(&recipient, &id);
+    assert_eq!(client.get_effective_balance(&id), (1_500, 1_500, 0));
+
+    assert_eq!(
+        client.try_get_effective_balance(&99),
+        Err(Ok(StreamError::StreamNotFound))
+    );
+}
+
+// ─── Strict deposit check ─────────────────────────────────────────────────────
+
+#[test]
+fn test_strict_deposit_check_rejects_amount_below_duration() {
+    let env = Env::default();
+    let (client, token, sender, admin, _) = setup_token_fee(&env);
+    let recipient = Address::generate(&env);
+
+    client.set_strict_deposit_check(&admin, &true);
+    assert!(client.get_strict_deposit_check());
+    assert_eq!(
+        client.try_create_stream(&sender, &recipient, &token, &99, &100),
+        Err(Ok(StreamError::DepositTooSmallForDuration))
+    );
+    assert_eq!(
+        client.try_simulate_create(&99, &100),
+        Err(Ok(StreamError::DepositTooSmallForDuration))
+    );
+    client.create_stream(&sender, &recipient, &token, &1_000, &100);
+}
+
+#[test]
+fn test_permissive_deposit_check_falls_back_to_rate_guard() {
+    let env = Env::default();
+    let (client, token, sender, _, _) = setup_token_fee(&env);
+    let recipient = Address::generate(&env);
+
+    assert!(!client.get_strict_deposit_check());
+    assert_eq!(
+        client.try_create_stream(&sender, &recipient, &token, &99, &100),
+        Err(Ok(StreamError::InvalidRate))
+    );
+}
+
+// ─── Cancellation roles ───────────────────────────────────────────────────────
+
+fn cancellation_event(env: &Env) -> (CancellerRole, StreamCancelledEvent) {
+    let ev = env
+        .events()
+        .all()
+        .iter()
+        .find(|e| {
+            Symbol::try_from_val(env, &e.1.get(0).unwrap()).unwrap()
+                == Symbol::new(env, "stream_cancelled")
+        })
+        .expect("stream_cancelled event not found");
+    let role = CancellerRole::try_from_val(env, &ev.1.get(2).unwrap()).unwrap();
+    (
+        role,
+        StreamCancelledEvent::try_from_val(env, &ev.2).unwrap(),
+    )
+}
+
+#[test]
+fn test_sender_cancellation_is_tagged_sender() {
+    let env = Env::default();
+    let (client, _, sender, _, id) = setup_split_stream(&env);
+
+    client.cancel_stream(&sender, &id);
+    let (role, payload) = cancellation_event(&env);
+    assert_eq!(role, CancellerRole::Sender);
+    assert_eq!(payload.cancelled_by, CancellerRole::Sender);
+}
+
+#[test]
+fn test_split_cancellation_is_tagged_mutual() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_split_stream(&env);
+
+    client.cancel_with_split(&sender, &recipient, &id, &5_000);
+    let (role, payload) = cancellation_event(&env);
+    assert_eq!(role, CancellerRole::Mutual);
+    assert_eq!(payload.cancelled_by, CancellerRole::Mutual);
+}
+
+// ─── Utilization ──────────────────────────────────────────────────────────────
+
+#[test]
+fn test_get_utilization_bps_tracks_withdrawal_backlog() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_split_stream(&env);
+
+    // Nothing accrued yet: nothing is owed.
+    assert_eq!(client.get_utilization_bps(&id), 10_000);
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    assert_eq!(client.get_utilization_bps(&id), 0);
+
+    client.withdraw(&recipient, &id);
+    assert_eq!(client.get_utilization_bps(&id), 10_000);
+
+    // 400 withdrawn of 1 000 accrued.
+    env.ledger().with_mut(|l| l.timestamp += 60);
+    assert_eq!(client.get_utilization_bps(&id), 4_000);
+
+    client.cancel_stream(&sender, &id);
+    assert_eq!(client.get_utilization_bps(&id), 10_000);
+    assert_eq!(
+        client.try_get_utilization_bps(&99),
+        Err(Ok(StreamError::StreamNotFound))
+    );
+}
+
+// ─── Withdrawable amount ──────────────────────────────────────────────────────
+
+#[test]
+fn test_get_withdrawable_amount_matches_withdraw() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_split_stream(&env);
+    assert_eq!(client.get_withdrawable_amount(&id), 0);
+
+    env.ledger().with_mut(|l| l.timestamp += 30);
+    let withdrawable = client.get_withdrawable_amount(&id);
+    assert_eq!(withdrawable, 300);
+    assert_eq!(client.withdraw(&recipient, &id), withdrawable);
+    assert_eq!(client.get_withdrawable_amount(&id), 0);
+
+    // Capped at the deposit long after the end.
+    env.ledger().with_mut(|l| l.timestamp += 1_000);
+    assert_eq!(client.get_withdrawable_amount(&id), 700);
+
+    client.cancel_stream(&sender, &id);
+    assert_eq!(client.get_withdrawable_amount(&id), 0);
+    assert_eq!(
+        client.try_get_withdrawable_amount(&99),
+        Err(Ok(StreamError::StreamNotFound))
+    );
+}
+
+// ─── Token recovery ───────────────────────────────────────────────────────────
+
+#[test]
+fn test_recover_token_sweeps_only_the_excess() {
+    let env = Env::default();
+    let (client, token, sender, admin, _) = setup_token_fee(&env);
+    let recipient = Address::generate(&env);
+    let rescue = Address::generate(&env);
+    let token_client = token::Client::new(&env, &token);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    // Stray transfer straight to the contract, outside any stream.
+    mint(&env, &token, &client.address, 250);
+    assert_eq!(
+        client.try_recover_token(&recipient, &token, &rescue),
+        Err(Ok(StreamError::NotAdmin))
+    );
+    assert_eq!(client.recover_token(&admin, &token, &rescue), 250);
+    assert_eq!(token_client.balance(&rescue), 250);
+    assert_eq!(token_client.balance(&client.address), 990);
+    assert_eq!(client.recover_token(&admin, &token, &rescue), 0);
+
+    // The stream is still fully backed.
+    env.ledger().with_mut(|l| l.timestamp = 200);
+    assert_eq!(client.withdraw(&recipient, &id), 990);
+}
+
+#[test]
+fn test_recover_token_leaves_rebasing_surplus_to_streams() {
+    let env = Env::default();
+    let (client, token, sender, admin, _) = setup_token_fee(&env);
+    client.set_asset_kind(&admin, &token, &StreamAssetKind::Rebasing);
+    client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+
+    mint(&env, &token, &client.address, 250);
+    assert_eq!(
+        client.recover_token(&admin, &token, &Address::generate(&env)),
+        0
+    );
+    assert_eq!(
+        token::Client::new(&env, &token).balance(&client.address),
+        1_240
+    );
+}
+
+// ─── Defunct tokens ───────────────────────────────────────────────────────────
+
+#[test]
+fn test_mark_token_defunct_is_admin_only() {
+    let env = Env::default();
+    let (client, token, _, admin, _) = setup_token_fee(&env);
+    let outsider = Address::generate(&env);
+
+    assert_eq!(
+        client.try_mark_token_defunct(&outsider, &token),
+        Err(Ok(StreamError::NotAdmin))
+    );
+    assert!(!client.is_token_defunct(&token));
+    client.mark_token_defunct(&admin, &token);
+    assert!(client.is_token_defunct(&token));
+}
+
+#[test]
+fn test_force_close_defunct_clears_bookkeeping_without_transfers() {
+    let env = Env::default();
+    let (client, token, sender, admin, _) = setup_token_fee(&env);
+    let recipient = Address::generate(&env);
+    let id = client.create_stream(&sender, &recipient, &token, &10_000, &100);
+    env.ledger().with_mut(|l| l.timestamp += 10);
+    client.withdraw(&recipient, &id);
+
+    assert_eq!(
+        client.try_force_close_defunct(&id),
+        Err(Ok(StreamError::TokenNotDefunct))
+    );
+
+    client.mark_token_defunct(&admin, &token);
+    let token_client = token::Client::new(&env, &token);
+    let contract_balance = token_client.balance(&client.address);
+    client.force_close_defunct(&id);
+    let ev = env.events().all().last().unwrap();
+    let payload = ForceClosedEvent::try_from_val(&env, &ev.2).unwrap();
+    assert_eq!(payload.unpaid_amount, 9_900 - 990);
+
+    let stream = client.get_stream(&id).unwrap();
+    assert!(!stream.is_active);
+    assert_eq!(stream.status, StreamStatus::Cancelled);
+    assert_eq!(token_client.balance(&client.address), contract_balance);
+
+    let stats = client.get_protocol_stats();
+    assert_eq!(stats.active_streams, 0);
+    assert_eq!(
+        client
+            .get_token_stats(&0, &50)
+            .get(token)
+            .unwrap()
+            .total_locked,
+        0
+    );
+
+    assert_eq!(
+        client.try_force_close_defunct(&id),
+        Err(Ok(StreamError::StreamInactive))
+    );
+}
+
+// ─── Ancient checkpoints ──────────────────────────────────────────────────────
+
+#[test]
+fn test_withdrawable_for_ancient_checkpoint_and_high_rate() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token, _) = create_token(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let amount = 1_000_000_000_000_000_000_000_000_000_000i128;
+    mint(&env, &token, &sender, amount);
+
+    let client = create_contract(&env);
+    let id = client.create_stream(&sender, &recipient, &token, &amount, &10);
+    assert_eq!(client.get_stream(&id).unwrap().rate_per_second, amount / 10);
+
+    // ~95 years untouched: rate * elapsed alone would exceed i128::MAX.
+    env.ledger().with_mut(|l| l.timestamp += 3_000_000_000);
+    assert_eq!(client.get_withdrawable_amount(&id), amount);
+    assert_eq!(client.withdraw(&recipient, &id), amount);
+    assert!(client.is_stream_completed(&id));
+}
+
+// ─── Batch creation ───────────────────────────────────────────────────────────
+
+#[test]
+fn test_create_streams_batch_opens_every_stream() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token, _) = create_token(&env);
+    let sender = Address::generate(&env);
+    mint(&env, &token, &sender, 6_000);
+    let recipients = vec![
+        &env,
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+    ];
+
+    let client = create_contract(&env);
+    let ids = client.create_streams_batch(
+        &sender,
+        &recipients,
+        &token,
+        &vec![&env, 1_000, 2_000, 3_000],
+        &100,
+    );
+
+    assert_eq!(ids.len(), 3);
+    for (i, id) in ids.iter().enumerate() {
+        let s = client.get_stream(&id).unwrap();
+        assert_eq!(s.recipient, recipients.get(i as u32).unwrap());
+        assert_eq!(s.deposited_amount, 1_000 * (i as i128 + 1));
+        assert_eq!(s.created_by, Symbol::new(&env, "batch"));
+    }
+    assert_eq!(token::Client::new(&env, &token).balance(&sender), 0);
+}
+
+#[test]
+fn test_create_streams_batch_reverts_whole_batch_on_bad_amount() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token, _) = create_token(&env);
+    let sender = Address::generate(&env);
+    mint(&env, &token, &sender, 6_000);
+    let recipients = vec![
+        &env,
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+    ];
+
+    let client = create_contract(&env);
+    assert_eq!(
+        client.try_create_streams_batch(
+            &sender,
+            &recipients,
+            &token,
+            &vec![&env, 1_000, 0, 3_000],
+            &100,
+        ),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+    assert!(client.get_stream(&1).is_none());
+    assert_eq!(token::Client::new(&env, &token).balance(&sender), 6_000);
+
+    assert_eq!(
+        client.try_create_streams_batch(&sender, &recipients, &token, &vec![&env, 1_000], &100),
+        Err(Ok(StreamError::BatchLengthMismatch))
+    );
+}
+
+// ─── Bundles ──────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_bundle_streams_settle_each_token_independently() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token_a, _) = create_token(&env);
+    let (token_b, _) = create_token(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    mint(&env, &token_a, &sender, 1_000);
+    mint(&env, &token_b, &sender, 500);
+
+    let client = create_contract(&env);
+    let legs = vec![
+        &env,
+        (token_a.clone(), 1_000i128),
+        (token_b.clone(), 500i128),
+    ];
+    let bundle_id = client.create_bundle_stream(&sender, &recipient, &legs, &100);
+    let ids = client.get_bundle(&bundle_id);
+    assert_eq!(ids.len(), 2);
+    assert_eq!(
+        client
+            .get_stream(&ids.get(0).unwrap())
+            .unwrap()
+            .rate_per_second,
+        10
+    );
+    assert_eq!(
+        client
+            .get_stream(&ids.get(1).unwrap())
+            .unwrap()
+            .rate_per_second,
+        5
+    );
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    assert_eq!(
+        client.withdraw_bundle(&recipient, &bundle_id),
+        vec![&env, 400i128, 200]
+    );
+
+    // Drain one leg directly; the bundle call then skips it.
+    env.ledger().with_mut(|l| l.timestamp += 60);
+    client.withdraw(&recipient, &ids.get(1).unwrap());
+    assert_eq!(
+        client.withdraw_bundle(&recipient, &bundle_id),
+        vec![&env, 600i128, 0]
+    );
+    assert_eq!(
+        token::Client::new(&env, &token_a).balance(&recipient),
+        1_000
+    );
+    assert_eq!(token::Client::new(&env, &token_b).balance(&recipient), 500);
+}
+
+#[test]
+fn test_bundle_rejects_invalid_legs() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 2_000);
+    assert_eq!(
+        client.try_create_bundle_stream(&sender, &recipient, &Vec::new(&env), &100),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+    let duplicate = vec![&env, (token.clone(), 1_000i128), (token.clone(), 1_000i128)];
+    assert_eq!(
+        client.try_create_bundle_stream(&sender, &recipient, &duplicate, &100),
+        Err(Ok(StreamError::InvalidTokenAddress))
+    );
+    assert_eq!(client.get_bundle(&1), Vec::new(&env));
+    assert_eq!(
+        client.try_withdraw_bundle(&recipient, &1),
+        Err(Ok(StreamError::StreamNotFound))
+    );
+}
+
+// ─── Tithes ───────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_withdraw_routes_tithe_share() {
+    let env = Env::default();
+    let (client, token, _, recipient, id) = setup_split_stream(&env);
+    let charity = Address::generate(&env);
+    let token_client = token::Client::new(&env, &token);
+
+    client.set_tithe(&recipient, &id, &charity, &500);
+    assert_eq!(
+        client.get_tithe(&id),
+        Some(Tithe {
+            address: charity.clone(),
+            bps: 500,
+        })
+    );
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    assert_eq!(client.withdraw(&recipient, &id), 400);
+    assert_eq!(token_client.balance(&charity), 20);
+    assert_eq!(token_client.balance(&recipient), 380);
+
+    // Clearing the tithe sends the full payout to the recipient again.
+    client.set_tithe(&recipient, &id, &charity, &0);
+    assert_eq!(client.get_tithe(&id), None);
+    env.ledger().with_mut(|l| l.timestamp += 10);
+    client.withdraw(&recipient, &id);
+    assert_eq!(token_client.balance(&charity), 20);
+    assert_eq!(token_client.balance(&recipient), 480);
+}
+
+#[test]
+fn test_set_tithe_validates_caller_share_and_address() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_split_stream(&env);
+    let charity = Address::generate(&env);
+
+    assert_eq!(
+        client.try_set_tithe(&sender, &id, &charity, &500),
+        Err(Ok(StreamError::Unauthorized))
+    );
+    assert_eq!(
+        client.try_set_tithe(&recipient, &id, &charity, &5_001),
+        Err(Ok(StreamError::InvalidTithe))
+    );
+    assert_eq!(
+        client.try_set_tithe(&recipient, &id, &recipient, &500),
+        Err(Ok(StreamError::InvalidTithe))
+    );
+    assert_eq!(
+        client.try_set_tithe(&recipient, &id, &client.address, &500),
+        Err(Ok(StreamError::InvalidTithe))
+    );
+}
+
+// ─── Recipient validation ─────────────────────────────────────────────────────
+
+#[test]
+fn test_create_stream_rejects_contract_as_recipient() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    assert_eq!(
+        client.try_create_stream(&sender, &client.address, &token, &1_000, &100),
+        Err(Ok(StreamError::InvalidRecipient))
+    );
+    assert_eq!(
+        client.try_create_stream_with_options(
+            &sender,
+            &client.address,
+            &token,
+            &1_000,
+            &100,
+            &StreamOptions::default(),
+        ),
+        Err(Ok(StreamError::InvalidRecipient))
+    );
+}
+
+// ─── Legacy layout decoding ───────────────────────────────────────────────────
+
+#[test]
+fn test_legacy_stream_layout_decodes_with_defaults() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    // Fund and count the stream normally, then overwrite it with the old
+    // layout, which the token totals never tracked.
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    env.as_contract(&client.address, || {
+        env.storage()
+            .persistent()
+            .remove(&DataKey::TokenTotals(token.clone()));
+        env.storage().persistent().set(
+            &DataKey::Stream(id),
+            &types::LegacyStream {
+                sender: sender.clone(),
+                recipient: recipient.clone(),
+                token_address: token.clone(),
+                rate_per_second: 10,
+                deposited_amount: 1_000,
+                withdrawn_amount: 200,
+                start_time: 0,
+                last_update_time: 20,
+                is_active: true,
+                paused: false,
+                paused_at: None,
+                status: StreamStatus::Active,
+            },
+        );
+    });
+
+    let stream = client.get_stream(&id).unwrap();
+    assert_eq!(stream.accrued_checkpoint, 200);
+    assert_eq!(stream.withdrawn_amount, 200);
+    assert!(stream.is_public);
+    assert_eq!(stream.milestones, None);
+    assert_eq!(stream.end_time, 100);
+
+    // Accrual resumes from the legacy anchor: 10/s for 30s.
+    env.ledger().with_mut(|l| l.timestamp = 50);
+    assert_eq!(client.get_claimable_amount(&id), Some(300));
+    assert_eq!(client.withdraw(&recipient, &id), 300);
+
+    // The write re-persisted the stream in the current layout.
+    let upgraded: Option<Stream> = env.as_contract(&client.address, || {
+        env.storage().persistent().get(&DataKey::Stream(id))
+    });
+    assert_eq!(upgraded.unwrap().withdrawn_amount, 500);
+    // The migration seeded the unpaid 800 before the payout came out of it.
+    let totals = client.get_token_stats(&0, &50);
+    assert_eq!(totals.get(token).unwrap().total_locked, 500);
+}
+
+/// Writes stream `id` back in the legacy layout and drops its token totals,
+/// indexes and active count, as an entry written by the old contract version
+/// would be.
+fn downgrade_to_legacy(env: &Env, client: &StreamContractClient, id: u64) {
+    let stream = client.get_stream(&id).unwrap();
+    env.as_contract(&client.address, || {
+        let storage = env.storage().persistent();
+        storage.remove(&DataKey::TokenTotals(stream.token_address.clone()));
+        storage.remove(&DataKey::TokenStreams(stream.token_address.clone()));
+        storage.remove(&DataKey::SenderStreams(stream.sender.clone()));
+        storage.remove(&DataKey::RecipientStreams(stream.recipient.clone()));
+        if stream.is_active {
+            storage::decrement_active_streams(env);
+        }
+        env.storage().persistent().set(
+            &DataKey::Stream(id),
+            &types::LegacyStream {
+                sender: stream.sender,
+                recipient: stream.recipient,
+                token_address: stream.token_address,
+                rate_per_second: stream.rate_per_second,
+                deposited_amount: stream.deposited_amount,
+                withdrawn_amount: stream.withdrawn_amount,
+                start_time: stream.start_time,
+                last_update_time: stream.last_update_time,
+                is_active: stream.is_active,
+                paused: stream.paused,
+                paused_at: stream.paused_at,
+                status: stream.status,
+            },
+        );
+    });
+}
+
+#[test]
+fn test_cancelled_legacy_stream_keeps_active_count_and_indexes() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 2_000);
+    let legacy = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    downgrade_to_legacy(&env, &client, legacy);
+    let current = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    assert_eq!(client.get_protocol_stats().active_streams, 1);
+
+    client.cancel_stream(&sender, &legacy);
+
+    // Only the legacy stream ended; the current one is still counted.
+    assert_eq!(client.get_protocol_stats().active_streams, 1);
+    assert_eq!(
+        client.get_streams_by_sender(&sender),
+        vec![&env, legacy, current]
+    );
+    assert_eq!(
+        client.get_streams_by_recipient(&recipient),
+        vec![&env, legacy, current]
+    );
+    let cancelled =
+        client.get_streams_by_token_and_status(&token, &StreamStatus::Cancelled, &0, &50);
+    assert_eq!(cancelled.len(), 1);
+    assert_eq!(
+        cancelled.get(0).unwrap(),
+        client.get_stream(&legacy).unwrap()
+    );
+}
+
+#[test]
+fn test_migrate_legacy_streams_counts_and_indexes_active_streams() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    downgrade_to_legacy(&env, &client, id);
+    assert_eq!(client.get_protocol_stats().active_streams, 0);
+    assert_eq!(client.get_streams_by_sender(&sender).len(), 0);
+
+    assert!(client.migrate_legacy_streams(&10));
+
+    assert_eq!(client.get_protocol_stats().active_streams, 1);
+    assert_eq!(client.get_streams_by_sender(&sender), vec![&env, id]);
+    assert_eq!(client.get_streams_by_recipient(&recipient), vec![&env, id]);
+}
+
+#[test]
+fn test_recover_token_waits_for_legacy_migration() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 3_000);
+    let admin = Address::generate(&env);
+    let rescue = Address::generate(&env);
+    // Streams written by the old version exist before this one is initialized.
+    let ids: std::vec::Vec<u64> = (0..3)
+        .map(|_| client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100))
+        .collect();
+    for &id in &ids {
+        downgrade_to_legacy(&env, &client, id);
+    }
+    client.initialize(&admin, &Address::generate(&env), &0);
+    mint(&env, &token, &client.address, 250);
+
+    // Without the migration the whole balance would look like excess.
+    assert_eq!(
+        client.try_recover_token(&admin, &token, &rescue),
+        Err(Ok(StreamError::MigrationPending))
+    );
+
+    assert!(!client.migrate_legacy_streams(&2));
+    assert_eq!(
+        client.try_recover_token(&admin, &token, &rescue),
+        Err(Ok(StreamError::MigrationPending))
+    );
+    assert!(client.migrate_legacy_streams(&2));
+    assert!(client.migrate_legacy_streams(&2));
+
+    let totals = client.get_token_stats(&0, &50);
+    assert_eq!(totals.get(token.clone()).unwrap().total_locked, 3_000);
+    assert_eq!(client.recover_token(&admin, &token, &rescue), 250);
+    assert_eq!(
+        token::Client::new(&env, &token).balance(&client.address),
+        3_000
+    );
+}
+
+// ─── Indivisible deposits ─────────────────────────────────────────────────────
+
+#[test]
+fn test_indivisible_deposit_fully_withdrawable_at_duration() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (token, _) = create_token(&env);
+    let token_client = token::Client::new(&env, &token);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    mint(&env, &token, &sender, 550);
+
+    let client = create_contract(&env);
+    // 550 over 100 s truncates the rate to 5, leaving 50 unstreamed by rate.
+    let id = client.create_stream(&sender, &recipient, &token, &550, &100);
+    assert_eq!(client.get_stream(&id).unwrap().rate_per_second, 5);
+
+    env.ledger().with_mut(|l| l.timestamp = 99);
+    assert_eq!(client.get_claimable_amount(&id), Some(495));
+
+    env.ledger().with_mut(|l| l.timestamp = 100);
+    assert_eq!(client.withdraw(&recipient, &id), 550);
+    assert_eq!(token_client.balance(&recipient), 550);
+    assert_eq!(
+        client.get_stream(&id).unwrap().status,
+        StreamStatus::Completed
+    );
+}
+
+#[test]
+fn test_indivisible_deposit_end_moves_with_pause_and_top_up() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &550, &100);
+
+    env.ledger().with_mut(|l| l.timestamp = 40);
+    client.pause_stream(&sender, &id);
+    env.ledger().with_mut(|l| l.timestamp = 60);
+    client.resume_stream(&sender, &id);
+    // 100 more at 5/s adds 20 s on top of the 20 s pause.
+    client.top_up_stream(&sender, &id, &100);
+    assert_eq!(client.get_stream(&id).unwrap().end_time, 140);
+
+    env.ledger().with_mut(|l| l.timestamp = 139);
+    assert_eq!(client.get_claimable_amount(&id), Some(595));
+    env.ledger().with_mut(|l| l.timestamp = 140);
+    assert_eq!(client.get_claimable_amount(&id), Some(650));
+}
+
+// ─── Stream end time ──────────────────────────────────────────────────────────
+
+#[test]
+fn test_stream_stops_vesting_exactly_at_end_time() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 1_000);
+    env.ledger().with_mut(|l| l.timestamp = 500);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+    assert_eq!(client.get_stream(&id).unwrap().end_time, 600);
+
+    env.ledger().with_mut(|l| l.timestamp = 599);
+    assert_eq!(client.get_claimable_amount(&id), Some(990));
+    env.ledger().with_mut(|l| l.timestamp = 600);
+    assert_eq!(client.get_claimable_amount(&id), Some(1_000));
+    env.ledger().with_mut(|l| l.timestamp = 10_000);
+    assert_eq!(client.get_claimable_amount(&id), Some(1_000));
+}
+
+#[test]
+fn test_top_up_pushes_end_time_later() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 2_000);
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &1_000, &100);
+
+    env.ledger().with_mut(|l| l.timestamp = 30);
+    // 500 at 10/s streams for 50 more seconds.
+    let stream = client.top_up_stream(&sender, &id, &500);
+    assert_eq!(stream.end_time, 150);
+
+    env.ledger().with_mut(|l| l.timestamp = 149);
+    assert_eq!(client.get_claimable_amount(&id), Some(1_490));
+    env.ledger().with_mut(|l| l.timestamp = 150);
+    assert_eq!(client.get_claimable_amount(&id), Some(1_500));
+}
+
+#[test]
+fn test_stream_exhausted_before_end_time_completes_cleanly() {
+    let env = Env::default();
+    let (client, token, sender, recipient) = setup(&env, 1_000);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+
+    // Raise the rate in storage so the deposit runs out at t=50, well before
+    // the nominal end_time of 100.
+    env.as_contract(&client.address, || {
+        let mut stream = storage::load_stream(&env, id).unwrap();
+        stream.rate_per_second = 20;
+        storage::save_stream(&env, id, &stream);
+    });
+
+    env.ledger().with_mut(|l| l.timestamp = 49);
+    assert_eq!(client.get_claimable_amount(&id), Some(980));
+    env.ledger().with_mut(|l| l.timestamp = 75);
+    // Accrual caps at the deposit, and the projected end is the exhaustion time.
+    assert_eq!(client.get_claimable_amount(&id), Some(1_000));
+    assert_eq!(client.get_stream_summary(&id), Some((0, 1_000, 50)));
+
+    assert_eq!(client.withdraw(&recipient, &id), 1_000);
+    let stream = client.get_stream(&id).unwrap();
+    assert_eq!(stream.status, StreamStatus::Completed);
+    assert!(!stream.is_active);
+    let (status_code, withdrawable, _) = client.get_stream_summary(&id).unwrap();
+    assert_eq!((status_code, withdrawable), (3, 0));
+
+    env.ledger().with_mut(|l| l.timestamp = 100);
+    assert_eq!(client.get_claimable_amount(&id), Some(0));
+}
+
+// ─── Cliffs ───────────────────────────────────────────────────────────────────
+
+fn create_cliff_stream(
+    env: &Env,
+    client: &StreamContractClient<'_>,
+    cliff_duration: u64,
+) -> Result<(Address, Address, Address, u64), StreamError> {
+    let (token, sender, recipient) = funded_parties(env, 1_000);
+    let options = StreamOptions {
+        cliff_duration,
+        ..Default::default()
+    };
+    try_create_with_options(client, &token, &sender, &recipient, &options)
+        .map(|id| (token, sender, recipient, id))
+}
+
+#[test]
+fn test_nothing_withdrawable_before_cliff() {
+    let env = Env::default();
+    let client = create_contract(&env);
+    let (_, _, recipient, id) = create_cliff_stream(&env, &client, 40).unwrap();
+    assert_eq!(client.get_stream(&id).unwrap().cliff_time, 40);
+
+    env.ledger().with_mut(|l| l.timestamp = 39);
+    assert_eq!(client.get_claimable_amount(&id), Some(0));
+    assert_eq!(
+        client.try_withdraw(&recipient, &id),
+        Err(Ok(StreamError::InvalidAmount))
+    );
+}
+
+#[test]
+fn test_cliff_window_unlocks_as_a_lump() {
+    let env = Env::default();
+    let client = create_contract(&env);
+    let (_, _, recipient, id) = create_cliff_stream(&env, &client, 40).unwrap();
+
+    env.ledger().with_mut(|l| l.timestamp = 40);
+    assert_eq!(client.withdraw(&recipient, &id), 400);
+    // Linear afterwards.
+    env.ledger().with_mut(|l| l.timestamp = 45);
+    assert_eq!(client.withdraw(&recipient, &id), 50);
+}
+
+#[test]
+fn test_cancel_before_cliff_refunds_sender() {
+    let env = Env::default();
+    let client = create_contract(&env);
+    let (token, sender, recipient, id) = create_cliff_stream(&env, &client, 40).unwrap();
+
+    env.ledger().with_mut(|l| l.timestamp = 30);
+    client.cancel_stream(&sender, &id);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&sender), 1_000);
+    assert_eq!(token_client.balance(&recipient), 0);
+}
+
+#[test]
+fn test_cliff_longer_than_duration_rejected() {
+    let env = Env::default();
+    let client = create_contract(&env);
+    assert_eq!(
+        create_cliff_stream(&env, &client, 101),
+        Err(StreamError::InvalidCliff)
+    );
+    assert!(create_cliff_stream(&env, &client, 100).is_ok());
+}
+
+#[test]
+fn test_stream_created_event_carries_cliff_time() {
+    let env = Env::default();
+    let client = create_contract(&env);
+    env.ledger().with_mut(|l| l.timestamp = 1_000);
+    create_cliff_stream(&env, &client, 25).unwrap();
+
+    let ev = env
+        .events()
+        .all()
+        .iter()
+        .find(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap()).unwrap()
+                == Symbol::new(&env, "stream_created")
+        })
+        .unwrap();
+    let payload = StreamCreatedEvent::try_from_val(&env, &ev.2).unwrap();
+    assert_eq!(payload.cliff_time, 1_025);
+    assert_eq!(payload.end_time, 1_100);
+}
+
+// ─── Recipient transfer ───────────────────────────────────────────────────────
+
+#[test]
+fn test_transfer_recipient_moves_stream_and_index() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 3_000);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let a1 = client.create_stream(&sender, &alice, &token, &1_000, &100);
+    let b1 = client.create_stream(&sender, &bob, &token, &1_000, &100);
+    let a2 = client.create_stream(&sender, &alice, &token, &1_000, &100);
+    client.set_tithe(&alice, &a1, &Address::generate(&env), &1_000);
+
+    client.transfer_recipient(&alice, &a1, &bob);
+    let ev = env.events().all().last().unwrap();
+    assert_eq!(
+        RecipientTransferredEvent::try_from_val(&env, &ev.2).unwrap(),
+        RecipientTransferredEvent {
+            stream_id: a1,
+            old_recipient: alice.clone(),
+            new_recipient: bob.clone(),
+        }
+    );
+
+    assert_eq!(client.get_stream(&a1).unwrap().recipient, bob);
+    assert_eq!(client.get_tithe(&a1), None);
+    assert_eq!(client.get_streams_by_recipient(&alice), vec![&env, a2]);
+    assert_eq!(client.get_streams_by_recipient(&bob), vec![&env, a1, b1]);
+
+    // Accrual moves with the stream; the old recipient is locked out.
+    env.ledger().with_mut(|l| l.timestamp = 30);
+    assert_eq!(
+        client.try_withdraw(&alice, &a1),
+        Err(Ok(StreamError::Unauthorized))
+    );
+    assert_eq!(client.withdraw(&bob, &a1), 300);
+}
+
+#[test]
+fn test_transfer_recipient_requires_current_recipient_auth() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_split_stream(&env);
+    let new_recipient = Address::generate(&env);
+
+    env.mock_auths(&[]);
+    assert!(client
+        .try_transfer_recipient(&recipient, &id, &new_recipient)
+        .is_err());
+
+    env.mock_all_auths();
+    assert_eq!(
+        client.try_transfer_recipient(&sender, &id, &new_recipient),
+        Err(Ok(StreamError::Unauthorized))
+    );
+    assert_eq!(client.get_stream(&id).unwrap().recipient, recipient);
+}
+
+#[test]
+fn test_transfer_recipient_rejects_same_address_and_inactive_stream() {
+    let env = Env::default();
+    let (client, _, sender, recipient, id) = setup_split_stream(&env);
+
+    assert_eq!(
+        client.try_transfer_recipient(&recipient, &id, &recipient),
+        Err(Ok(StreamError::InvalidRecipient))
+    );
+    assert_eq!(
+        client.try_transfer_recipient(&recipient, &id, &client.address),
+        Err(Ok(StreamError::InvalidRecipient))
+    );
+    // Handing the stream to its sender would make it a self-stream.
+    assert_eq!(
+        client.try_transfer_recipient(&recipient, &id, &sender),
+        Err(Ok(StreamError::InvalidRecipient))
+    );
+    assert_eq!(client.get_stream(&id).unwrap().recipient, recipient);
+
+    client.cancel_stream(&sender, &id);
+    assert_eq!(
+        client.try_transfer_recipient(&recipient, &id, &Address::generate(&env)),
+        Err(Ok(StreamError::StreamInactive))
+    );
+}
+
+// ─── Reentrancy ───────────────────────────────────────────────────────────────
+
+mod reentrant_token {
+    use soroban_sdk::{
+        contract, contractimpl, contracttype, Address, Env, IntoVal, InvokeError, Symbol,
+    };
+
+    #[contracttype]
+    enum Key {
+        Balance(Address),
+        Target,
+        Reentered,
+    }
+
+    /// A token whose `transfer` out of an armed stream contract calls back
+    /// into its `withdraw`, recording whether the nested call got through.
+    #[contract]
+    pub struct ReentrantToken;
+
+    #[contractimpl]
+    impl ReentrantToken {
+        pub fn decimals(_env: Env) -> u32 {
+            7
+        }
+
+        pub fn balance(env: Env, id: Address) -> i128 {
+            env.storage().instance().get(&Key::Balance(id)).unwrap_or(0)
+        }
+
+        pub fn mint(env: Env, to: Address, amount: i128) {
+            let current = Self::balance(env.clone(), to.clone());
+            env.storage()
+                .instance()
+                .set(&Key::Balance(to), &(current + amount));
+        }
+
+        pub fn arm(env: Env, stream_contract: Address, stream_id: u64) {
+            env.storage()
+                .instance()
+                .set(&Key::Target, &(stream_contract, stream_id));
+        }
+
+        /// `None` until a nested call is attempted, then whether it succeeded.
+        pub fn reentered(env: Env) -> Option<bool> {
+            env.storage().instance().get(&Key::Reentered)
+        }
+
+        pub fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+            from.require_auth();
+            let from_balance = Self::balance(env.clone(), from.clone());
+            assert!(from_balance >= amount, "insufficient balance");
+            env.storage()
+                .instance()
+                .set(&Key::Balance(from.clone()), &(from_balance - amount));
+            let to_balance = Self::balance(env.clone(), to.clone());
+            env.storage()
+                .instance()
+                .set(&Key::Balance(to.clone()), &(to_balance + amount));
+
+            let target: Option<(Address, u64)> = env.storage().instance().get(&Key::Target);
+            if let Some((stream_contract, stream_id)) = target {
+                if from == stream_contract && Self::reentered(env.clone()).is_none() {
+                    let result = env.try_invoke_contract::<i128, InvokeError>(
+                        &stream_contract,
+                        &Symbol::new(&env, "withdraw"),
+                        (to, stream_id).into_val(&env),
+                    );
+                    env.storage()
+                        .instance()
+                        .set(&Key::Reentered, &matches!(result, Ok(Ok(_))));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_withdraw_rejects_reentry_from_token_transfer() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let token_id = env.register(reentrant_token::ReentrantToken, ());
+    let token = reentrant_token::ReentrantTokenClient::new(&env, &token_id);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    token.mint(&sender, &1_000);
+
+    let client = create_contract(&env);
+    let id = client.create_stream(&sender, &recipient, &token_id, &1_000, &1_000);
+    token.arm(&client.address, &id);
+
+    env.ledger().with_mut(|l| l.timestamp += 500);
+    assert_eq!(client.withdraw(&recipient, &id), 500);
+
+    // The nested withdraw was attempted and refused; only one payout landed.
+    assert_eq!(token.reentered(), Some(false));
+    assert_eq!(token.balance(&recipient), 500);
+    assert_eq!(client.get_stream(&id).unwrap().withdrawn_amount, 500);
+}
+
+#[test]
+fn test_token_transferring_calls_fail_while_locked() {
+    let env = Env::default();
+    let (client, _token, sender, recipient, id) = setup_split_stream(&env);
+    env.ledger().with_mut(|l| l.timestamp += 50);
+
+    // Simulate a call caught mid-transfer by holding the lock.
+    env.as_contract(&client.address, || {
+        env.storage().temporary().set(&DataKey::Locked, &true);
+    });
+    assert_eq!(
+        client.try_withdraw(&recipient, &id),
+        Err(Ok(StreamError::Reentrancy))
+    );
+    assert_eq!(
+        client.try_top_up_stream(&sender, &id, &100),
+        Err(Ok(StreamError::Reentrancy))
+    );
+    assert_eq!(
+        client.try_cancel_stream(&sender, &id),
+        Err(Ok(StreamError::Reentrancy))
+    );
+
+    env.as_contract(&client.address, || {
+        env.storage().temporary().remove(&DataKey::Locked);
+    });
+    assert_eq!(client.withdraw(&recipient, &id), 500);
+}
+
+// ─── Closing streams ──────────────────────────────────────────────────────────
+
+#[test]
+fn test_close_stream_after_vesting_pays_out_and_completes() {
+    let env = Env::default();
+    let (client, token, sender, recipient, id) = setup_split_stream(&env);
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    client.withdraw(&recipient, &id);
+    env.ledger().with_mut(|l| l.timestamp += 200);
+
+    // Either party may close once fully vested; here the recipient does.
+    client.close_stream(&recipient, &id);
+
+    let ev = env
+        .events()
+        .all()
+        .iter()
+        .find(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap()).unwrap()
+                == Symbol::new(&env, "stream_closed")
+        })
+        .expect("stream_closed event not found");
+    let payload = StreamClosedEvent::try_from_val(&env, &ev.2).unwrap();
+    assert_eq!(payload.closed_by, recipient);
+    assert_eq!(payload.recipient_payout, 600);
+    assert_eq!(payload.refunded_amount, 0);
+
+    let stream = client.get_stream(&id).unwrap();
+    assert!(!stream.is_active);
+    assert_eq!(stream.status, StreamStatus::Completed);
+    assert_eq!(stream.withdrawn_amount, 1_000);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&recipient), 1_000);
+    assert_eq!(token_client.balance(&sender), 0);
+    assert_eq!(
+        client.try_close_stream(&sender, &id),
+        Err(Ok(StreamError::StreamInactive))
+    );
+}
+
+#[test]
+fn test_close_stream_before_vesting_is_sender_only() {
+    let env = Env::default();
+    let (client, token, sender, recipient, id) = setup_split_stream(&env);
+    env.ledger().with_mut(|l| l.timestamp += 30);
+
+    assert_eq!(
+        client.try_close_stream(&recipient, &id),
+        Err(Ok(StreamError::TooSoon))
+    );
+    assert_eq!(
+        client.try_close_stream(&Address::generate(&env), &id),
+        Err(Ok(StreamError::Unauthorized))
+    );
+    assert!(client.get_stream(&id).unwrap().is_active);
+
+    // The sender's early close settles like a cancellation.
+    client.close_stream(&sender, &id);
+    let stream = client.get_stream(&id).unwrap();
+    assert!(!stream.is_active);
+    assert_eq!(stream.status, StreamStatus::Cancelled);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&recipient), 300);
+    assert_eq!(token_client.balance(&sender), 700);
+}
+
+#[test]
+fn test_close_stream_after_vesting_respects_withdraw_gate() {
+    let env = Env::default();
+    let (client, gate, token, recipient, id) = setup_gated_stream(&env);
+    env.ledger().with_mut(|l| l.timestamp += 100);
+
+    assert_eq!(
+        client.try_close_stream(&recipient, &id),
+        Err(Ok(StreamError::GateRejected))
+    );
+    assert!(client.get_stream(&id).unwrap().is_active);
+
+    gate.set_open(&true);
+    client.close_stream(&recipient, &id);
+    assert_eq!(token::Client::new(&env, &token).balance(&recipient), 1_000);
+}
+
+#[test]
+fn test_close_stream_after_vesting_respects_first_withdraw_delay() {
+    let env = Env::default();
+    let (client, recipient, id) = setup_delayed_stream(&env, 200);
+
+    env.ledger().with_mut(|l| l.timestamp = 1_100);
+    assert_eq!(
+        client.try_close_stream(&recipient, &id),
+        Err(Ok(StreamError::TooSoon))
+    );
+
+    env.ledger().with_mut(|l| l.timestamp = 1_200);
+    client.close_stream(&recipient, &id);
+    assert_eq!(
+        client.get_stream(&id).unwrap().status,
+        StreamStatus::Completed
+    );
+}
+
+#[test]
+fn test_close_stream_after_vesting_respects_circuit_breaker() {
+    let env = Env::default();
+    let (client, _, recipient, _) = setup_circuit_breaker(&env, 500, 100);
+
+    env.ledger().with_mut(|l| l.timestamp += 40);
+    client.withdraw(&recipient, &1);
+    client.withdraw(&recipient, &2);
+    assert!(client.is_protocol_paused());
+
+    env.ledger().with_mut(|l| l.timestamp += 60);
+    assert_eq!(
+        client.try_close_stream(&recipient, &1),
+        Err(Ok(StreamError::ProtocolPaused))
+    );
+    assert!(client.get_stream(&1).unwrap().is_active);
+}
+
+#[test]
+fn test_close_stream_after_vesting_respects_contract_pause() {
+    let env = Env::default();
+    let (client, token, sender, admin, _) = setup_token_fee(&env);
+    let recipient = Address::generate(&env);
+    let id = client.create_stream(&sender, &recipient, &token, &1_000, &100);
+    env.ledger().with_mut(|l| l.timestamp += 100);
+
+    client.pause_contract(&admin);
+    assert_eq!(
+        client.try_close_stream(&recipient, &id),
+        Err(Ok(StreamError::ContractPaused))
+    );
+    assert_eq!(
+        client.try_close_stream(&sender, &id),
+        Err(Ok(StreamError::ContractPaused))
+    );
+    assert!(client.get_stream(&id).unwrap().is_active);
+}
+
+#[test]
+fn test_close_stream_after_vesting_emits_withdrawal_events() {
+    let env = Env::default();
+    let (client, _, _, recipient, id) = setup_split_stream(&env);
+    env.ledger().with_mut(|l| l.timestamp += 100);
+
+    client.close_stream(&recipient, &id);
+    let topics: std::vec::Vec<Symbol> = env
+        .events()
+        .all()
+        .iter()
+        .map(|e| Symbol::try_from_val(&env, &e.1.get(0).unwrap()).unwrap())
+        .collect();
+    for name in ["tokens_withdrawn", "stream_completed", "stream_closed"] {
+        assert!(topics.contains(&Symbol::new(&env, name)), "{name} missing");
+    }
+}
+
+// ─── Memos ────────────────────────────────────────────────────────────────────
+
+#[test]
+fn test_create_stream_with_memo_stores_and_emits_it() {
+    let env = Env::default();
+    let (client, token, sender, _) = setup(&env, 2_000);
+    let memo = Symbol::new(&env, "INV_2024_0042");
+    let id = client.create_stream_with_options(
+        &sender,
+        &Address::generate(&env),
+        &token,
+        &1_000,
+        &100,
+        &StreamOptions {
+            memo: Some(memo.clone()),
+            ..Default::default()
+        },
+    );
+
+    let ev = env
+        .events()
+        .all()
+        .iter()
+        .find(|e| {
+            Symbol::try_from_val(&env, &e.1.get(0).unwrap()).unwrap()
+                == Symbol::new(&env, "stream_created")
+        })
+        .expect("stream_created event not found");
+    let payload = StreamCreatedEvent::try_from_val(&env, &ev.2).unwrap();
+    assert_eq!(payload.memo, Some(memo.clone()));
+    assert_eq!(client.get_stream(&id).unwrap().memo, Some(memo));
+
+    // An empty memo is accepted, and plain creation leaves it unset.
+    let empty = Symbol::new(&env, "");
+    let id = client.create_stream_with_options(
+        &sender,
+        &Address::generate(&env),
+        &token,
+        &500,
+        &100,
+        &StreamOptions {
+            memo: Some(empty.clone()),
+            ..Default::default()
+        },
+    );
+    assert_eq!(client.get_stream(&id).unwrap().memo, Some(empty));
+    let id = client.create_stream(&sender, &Address::generate(&env), &token, &500, &100);
+    assert_eq!(client.get_stream(&id).unwrap().memo, None);
+}
+
+// ─── Computed status ──────────────────────────────────────────────────────────
+
+#[test]
+fn test_get_stream_status_walks_through_each_state() {
+    let env = Env::default();
+    let client = create_contract(&env);
+    let (_, sender, recipient, id) = create_cliff_stream(&env, &client, 20).unwrap();
+
+    assert_eq!(client.get_stream_status(&id), StreamStatus::Pending);
+    env.ledger().with_mut(|l| l.timestamp += 20);
+    assert_eq!(client.get_stream_status(&id), StreamStatus::Active);
+
+    client.pause_stream(&sender, &id);
+    assert_eq!(client.get_stream_status(&id), StreamStatus::Paused);
+    env.ledger().with_mut(|l| l.timestamp += 10);
+    client.resume_stream(&sender, &id);
+    assert_eq!(client.get_stream_status(&id), StreamStatus::Active);
+
+    // Fully vested but not yet withdrawn: already reported as complete.
+    env.ledger().with_mut(|l| l.timestamp += 90);
+    assert_eq!(client.get_stream_status(&id), StreamStatus::Completed);
+    assert_eq!(client.get_stream(&id).unwrap().status, StreamStatus::Active);
+    client.withdraw(&recipient, &id);
+    assert_eq!(client.get_stream_status(&id), StreamStatus::Completed);
+
+    let (_, sender, _, id) = create_cliff_stream(&env, &client, 0).unwrap();
+    client.cancel_stream(&sender, &id);
+    assert_eq!(client.get_stream_status(&id), StreamStatus::Cancelled);
+
+    assert_eq!(
+        client.try_get_stream_status(&(id + 1)),
+        Err(Ok(StreamError::StreamNotFound))
+    );
+}
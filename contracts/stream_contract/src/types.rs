@@ -1,4 +1,4 @@
-use soroban_sdk::{contracttype, Address};
+use soroban_sdk::{contracttype, Address, Symbol, Vec};
 
 /// Status of a payment stream.
 #[contracttype]
@@ -8,6 +8,70 @@ pub enum StreamStatus {
     Paused,
     Cancelled,
     Completed,
+    /// Active but still before `cliff_time`. Only reported by
+    /// `get_stream_status`; never stored on a stream.
+    Pending,
+}
+
+/// Why `get_streams_needing_attention` flagged a stream.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AttentionReason {
+    /// Fully vested but still active: the recipient has not withdrawn the rest.
+    CompletedNotClosed,
+    /// Active and funded for less than a day at the current rate.
+    NearDepleted,
+    /// Not checkpointed for half the TTL threshold, so its TTL may be low.
+    LowTtl,
+}
+
+/// Who initiated a cancellation, recorded on `StreamCancelledEvent`.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CancellerRole {
+    /// The sender alone, via `cancel_stream` or `cancel_stream_with_mode`.
+    Sender,
+    /// Sender and recipient together, via `cancel_with_split`.
+    Mutual,
+}
+
+/// How the contract's balance of a token relates to stream accounting.
+///
+/// Stream accounting (`deposited_amount`, `withdrawn_amount`, TVL) is always
+/// kept in nominal deposit units. The asset kind only decides how those units
+/// are converted into a token transfer at payout time.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StreamAssetKind {
+    /// Standard SAC-style token: the contract balance only changes through
+    /// deposits and payouts, so one nominal unit is one token.
+    Plain,
+    /// Rebasing or yield-bearing wrapper: the contract balance can drift.
+    /// Payouts are scaled by `contract_balance / total_locked` so every stream
+    /// receives its pro-rata share of the rebase.
+    Rebasing,
+}
+
+/// Time unit of `Stream::rate_per_second`.
+///
+/// Every stream currently accrues per second; the unit is recorded so clients
+/// never have to assume it.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RateUnit {
+    PerSecond,
+}
+
+/// How `cancel_stream_with_mode` terminates a stream.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CancelMode {
+    /// Settle accrual now, pay the recipient what has vested, and refund the
+    /// unvested balance to the sender.
+    Immediate,
+    /// No clawback: the stream keeps paying the recipient normally until it is
+    /// fully drained, but no longer accepts top-ups, pauses, or cancellation.
+    Graceful,
 }
 
 /// Centralized storage key strategy.
@@ -21,10 +85,72 @@ pub enum StreamStatus {
 pub enum DataKey {
     /// Global monotonic counter for assigning stream IDs.
     StreamCounter,
+    /// Last assigned bundle id (instance storage).
+    BundleCounter,
+    /// Stream ids that make up a multi-token bundle, one per token.
+    Bundle(u64),
     /// Individual stream record, keyed by its unique u64 ID.
     Stream(u64),
     /// Protocol-level fee configuration (singleton).
     ProtocolConfig,
+    /// Number of streams currently active (not cancelled or completed).
+    ActiveStreamCount,
+    /// Number of distinct tokens that have ever backed a stream (instance storage).
+    TrackedTokenCount,
+    /// The n-th token to back a stream, in first-seen order.
+    TrackedToken(u32),
+    /// Running locked/fee totals for a single token.
+    TokenTotals(Address),
+    /// Marks an address as exempt from protocol fees.
+    FeeExempt(Address),
+    /// Maximum stream duration in seconds accepted by `create_stream` (0 = unlimited).
+    MaxDuration,
+    /// Set when `create_stream` rejects `amount < duration` up front.
+    StrictDepositCheck,
+    /// Admin-registered asset kind for a token (defaults to `Plain`).
+    AssetKind(Address),
+    /// Net top-up amount parked on a stream, awaiting recipient acknowledgment.
+    PendingTopUp(u64),
+    /// Recipient-configured share of each withdrawal routed to another address.
+    Tithe(u64),
+    /// Recipient-approved `withdraw_to` destinations for a stream (absent = any).
+    AllowedDestinations(u64),
+    /// Ids of every stream created for a token, in creation order.
+    TokenStreams(Address),
+    /// Ids of every public stream sent by an address, in creation order.
+    SenderStreams(Address),
+    /// Ids of every public stream paying an address, in creation order.
+    RecipientStreams(Address),
+    /// Ids of every private stream an address sends or receives, in creation
+    /// order; read only by `get_my_streams`.
+    PrivateStreams(Address),
+    /// Admin-configured cap on total locked for a token (absent = unlimited).
+    MaxExposure(Address),
+    /// Admin-configured fee rate for a token, overriding the global rate.
+    TokenFeeBps(Address),
+    /// Lifetime amount paid to a recipient in a token: `(recipient, token)`.
+    RecipientEarnings(Address, Address),
+    /// Admin-configured earnings-event step for a token (absent = disabled).
+    EarningsThreshold(Address),
+    /// Admin-configured remainder below which a withdrawal sweeps a token's
+    /// stream closed (absent = disabled).
+    DustThreshold(Address),
+    /// Set once the admin marks a token's contract as permanently broken.
+    DefunctToken(Address),
+    /// Set while the circuit breaker holds the protocol paused (absent = running).
+    GlobalPaused,
+    /// Set while the admin holds the contract paused (absent = running).
+    ContractPaused,
+    /// Temporary: set while a token-transferring call is in progress.
+    Locked,
+    /// Set once every stream id has been checked for the legacy layout.
+    LegacyMigrated,
+    /// Next stream id `migrate_legacy_streams` will check.
+    LegacyMigrationCursor,
+    /// Admin-configured withdrawal-velocity limit for a token (absent = disabled).
+    CircuitBreaker(Address),
+    /// Withdrawal volume in the token's current circuit-breaker window.
+    WithdrawalWindow(Address),
 }
 
 /// Immutable state of a payment stream.
@@ -46,6 +172,11 @@ pub struct Stream {
     pub deposited_amount: i128,
     /// Cumulative amount already withdrawn by the recipient.
     pub withdrawn_amount: i128,
+    /// Cumulative amount vested as of `last_update_time`.
+    ///
+    /// Advanced on every settlement so accrual is always computed as a delta
+    /// from this checkpoint rather than from `start_time`.
+    pub accrued_checkpoint: i128,
     /// Ledger timestamp at stream creation.
     pub start_time: u64,
     /// Ledger timestamp of the last state mutation.
@@ -56,8 +187,117 @@ pub struct Stream {
     pub paused: bool,
     /// Ledger timestamp when the stream was paused, `None` if not paused.
     pub paused_at: Option<u64>,
+    /// `true` once the sender has requested a graceful wind-down; the stream
+    /// pays out normally but rejects all further control changes.
+    pub winding_down: bool,
     /// Current status of the stream.
     pub status: StreamStatus,
+    /// Entry point that created the stream (e.g. `direct`).
+    pub created_by: Symbol,
+    /// When `true`, top-ups are parked until the recipient calls `ack_topup`,
+    /// so the stream's terms never change without the recipient's consent.
+    pub recipient_must_ack_topups: bool,
+    /// Seconds after `start_time` before the recipient may make any withdrawal.
+    /// Gates withdrawals only; accrual is unaffected.
+    pub first_withdraw_delay: u64,
+    /// Contract consulted via `can_release` before every withdrawal, if any.
+    pub withdraw_gate: Option<Address>,
+    /// Sum of all completed pause windows, in seconds.
+    pub total_paused_duration: u64,
+    /// When `false`, the stream is reachable by id only and omitted from
+    /// enumerable indexes such as `get_streams_by_token_and_status`.
+    pub is_public: bool,
+    /// Set by `finalize_stream`; a finalized stream rejects every mutation.
+    pub finalized: bool,
+    /// Amount released immediately at `start_time`, ahead of linear accrual.
+    pub initial_unlock: i128,
+    /// Ledger-sequence release schedule as `(sequence, cumulative released)`
+    /// pairs. When set, vesting follows these steps instead of linear time.
+    pub milestones: Option<Vec<(u32, i128)>>,
+    /// Nominal fully-vested timestamp: `start_time + duration`, pushed back by
+    /// top-ups and pauses. The whole deposit vests here even when the rate
+    /// was truncated, so no rounding remainder is left behind.
+    pub end_time: u64,
+    /// Nothing is withdrawable before this timestamp; accrual still runs from
+    /// `start_time`, so the whole cliff window unlocks at once when it passes.
+    pub cliff_time: u64,
+    /// Off-chain reference (invoice id, employee id) attached at creation.
+    pub memo: Option<Symbol>,
+    /// Set by `pause_until`: the pause lifts by itself at this timestamp, as
+    /// if `resume_stream` had been called then.
+    pub resume_at: Option<u64>,
+    /// Time unit of `rate_per_second`; always `PerSecond`.
+    pub rate_unit: RateUnit,
+}
+
+/// `Stream` as persisted before the checkpoint accrual model and per-stream
+/// options were introduced.
+///
+/// Kept only so `load_stream` can decode entries written by earlier contract
+/// versions; every save writes the current `Stream` layout.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacyStream {
+    pub sender: Address,
+    pub recipient: Address,
+    pub token_address: Address,
+    pub rate_per_second: i128,
+    pub deposited_amount: i128,
+    pub withdrawn_amount: i128,
+    pub start_time: u64,
+    /// Accrual anchor: every earlier withdrawal advanced it to `now`.
+    pub last_update_time: u64,
+    pub is_active: bool,
+    pub paused: bool,
+    pub paused_at: Option<u64>,
+    pub status: StreamStatus,
+}
+
+/// Optional per-stream settings accepted by `create_stream_with_options`.
+///
+/// `Default` yields the same stream `create_stream` would create.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StreamOptions {
+    /// See `Stream::first_withdraw_delay`.
+    pub first_withdraw_delay: u64,
+    /// See `Stream::withdraw_gate`.
+    pub withdraw_gate: Option<Address>,
+    /// Create the stream with `is_public = false`.
+    pub is_private: bool,
+    /// Net amount withdrawable immediately (e.g. a signing bonus); the rest
+    /// of the deposit streams linearly over the duration.
+    pub initial_unlock: i128,
+    /// See `Stream::milestones`. Sequences must strictly increase, cumulative
+    /// amounts must not decrease, and the last one must equal the net deposit.
+    pub milestones: Option<Vec<(u32, i128)>>,
+    /// Seconds after `start_time` before anything is withdrawable; see
+    /// `Stream::cliff_time`. Must not exceed the duration.
+    pub cliff_duration: u64,
+    /// See `Stream::memo`.
+    pub memo: Option<Symbol>,
+}
+
+/// Withdrawal-velocity limit for one token.
+///
+/// Stored in persistent storage under `DataKey::CircuitBreaker(token)`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CircuitBreakerConfig {
+    /// Maximum nominal amount withdrawn per window before the breaker trips.
+    pub max_volume: i128,
+    /// Window length in seconds.
+    pub window_secs: u64,
+}
+
+/// Running withdrawal volume for one token's circuit-breaker window.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WithdrawalWindow {
+    /// Ledger timestamp at which the current window opened.
+    pub window_start: u64,
+    /// Nominal amount withdrawn since `window_start`.
+    pub volume: i128,
 }
 
 /// Protocol-wide fee configuration.
@@ -73,3 +313,82 @@ pub struct ProtocolConfig {
     /// Fee expressed in basis points (1 bps = 0.01%). Max: 1 000 bps = 10%.
     pub fee_rate_bps: u32,
 }
+
+/// Running aggregates maintained per token.
+///
+/// Stored in persistent storage under `DataKey::TokenTotals(token)` and updated
+/// on every deposit, payout, refund, and fee collection.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TokenTotals {
+    /// Tokens currently held by the contract on behalf of streams (TVL).
+    pub total_locked: i128,
+    /// Cumulative protocol fees sent to the treasury.
+    pub total_fees_collected: i128,
+}
+
+/// Recipient-configured share of each withdrawal sent to a fixed address.
+///
+/// Stored under `DataKey::Tithe(stream_id)`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Tithe {
+    /// Address receiving the routed share.
+    pub address: Address,
+    /// Share of each payout, in basis points.
+    pub bps: u32,
+}
+
+/// Stream record plus derived values, returned by `preview_stream`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamView {
+    pub stream: Stream,
+    /// Cumulative amount vested as of the current ledger timestamp.
+    pub vested: i128,
+    /// Amount `withdraw` would pay right now (0 once inactive).
+    pub claimable: i128,
+    /// Projected timestamp at which the stream becomes fully vested.
+    pub end_time: u64,
+    /// Net top-up parked awaiting recipient acknowledgment.
+    pub pending_topup: i128,
+}
+
+/// Every boolean state of a stream, returned by `get_stream_flags`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamFlags {
+    pub is_active: bool,
+    pub paused: bool,
+    pub winding_down: bool,
+    pub finalized: bool,
+    pub is_public: bool,
+    pub recipient_must_ack_topups: bool,
+    /// Whether `cancel_stream` would currently accept the stream.
+    pub cancelable: bool,
+    /// Whether `transfer_recipient` would currently accept the stream.
+    pub transferable: bool,
+}
+
+/// One page of public streams returned by `get_streams_page`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamsPage {
+    /// Public streams found in the scanned ids, ascending.
+    pub streams: Vec<(u64, Stream)>,
+    /// First id not yet scanned, or `None` once the last id has been covered.
+    pub next_id: Option<u64>,
+}
+
+/// Protocol-wide aggregate view returned by `get_protocol_stats`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProtocolStats {
+    /// Total number of streams ever created.
+    pub total_streams: u64,
+    /// Number of streams that are currently active (including paused ones).
+    pub active_streams: u64,
+    /// Number of distinct tokens that have ever backed a stream; pages of
+    /// `get_token_stats` cover `[0, token_count)`.
+    pub token_count: u32,
+}